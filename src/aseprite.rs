@@ -0,0 +1,516 @@
+use failure::Error;
+use flate2::read::ZlibDecoder;
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::sheet::{Animation, AnimationFrame};
+
+// Reads .ase/.aseprite files as documented in https://github.com/aseprite/aseprite/blob/master/docs/ase-file-specs.md
+//
+// Tiger frames are backed by image files, so the file is converted as follows:
+// - Every Aseprite frame is flattened (visible layers, normal blending) and written as a PNG
+//   next to the file, in `<name>_frames/<name>_<index>.png`
+// - Every tag becomes an animation using these frames and their Aseprite durations
+// - Files without tags become a single animation named after the file
+
+const HEADER_MAGIC: u16 = 0xA5E0;
+const FRAME_MAGIC: u16 = 0xF1FA;
+const HEADER_SIZE: usize = 128;
+const FRAME_HEADER_SIZE: usize = 16;
+
+const CHUNK_OLD_PALETTE: u16 = 0x0004;
+const CHUNK_LAYER: u16 = 0x2004;
+const CHUNK_CEL: u16 = 0x2005;
+const CHUNK_TAGS: u16 = 0x2018;
+const CHUNK_PALETTE: u16 = 0x2019;
+
+const LAYER_FLAG_VISIBLE: u16 = 1;
+const LAYER_TYPE_GROUP: u16 = 1;
+const HEADER_FLAG_LAYER_OPACITY: u32 = 1;
+
+#[derive(Fail, Debug)]
+pub enum AsepriteError {
+    #[fail(display = "Not an Aseprite file")]
+    InvalidHeader,
+    #[fail(display = "Corrupted Aseprite frame")]
+    InvalidFrame,
+    #[fail(display = "Unexpected end of Aseprite file")]
+    UnexpectedEndOfFile,
+    #[fail(display = "Unsupported Aseprite color depth: {}", _0)]
+    UnsupportedColorDepth(u16),
+}
+
+pub struct AsepriteImport {
+    pub frames: Vec<PathBuf>,
+    pub animations: Vec<Animation>,
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Reader<'a> {
+        Reader { data, position: 0 }
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], Error> {
+        let end = self.position + count;
+        if end > self.data.len() {
+            return Err(AsepriteError::UnexpectedEndOfFile.into());
+        }
+        let bytes = &self.data[self.position..end];
+        self.position = end;
+        Ok(bytes)
+    }
+
+    fn skip(&mut self, count: usize) -> Result<(), Error> {
+        self.bytes(count).map(|_| ())
+    }
+
+    fn byte(&mut self) -> Result<u8, Error> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn word(&mut self) -> Result<u16, Error> {
+        let b = self.bytes(2)?;
+        Ok(u16::from(b[0]) | u16::from(b[1]) << 8)
+    }
+
+    fn short(&mut self) -> Result<i16, Error> {
+        Ok(self.word()? as i16)
+    }
+
+    fn dword(&mut self) -> Result<u32, Error> {
+        let b = self.bytes(4)?;
+        Ok(u32::from(b[0]) | u32::from(b[1]) << 8 | u32::from(b[2]) << 16 | u32::from(b[3]) << 24)
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        let length = self.word()? as usize;
+        Ok(String::from_utf8_lossy(self.bytes(length)?).into_owned())
+    }
+}
+
+struct Header {
+    num_frames: u16,
+    width: u16,
+    height: u16,
+    color_depth: u16,
+    flags: u32,
+    transparent_index: u8,
+}
+
+struct Layer {
+    is_visible: bool,
+    is_group: bool,
+    opacity: u8,
+}
+
+#[derive(Clone)]
+struct Cel {
+    layer: usize,
+    x: i32,
+    y: i32,
+    opacity: u8,
+    width: u32,
+    height: u32,
+    pixels: Vec<[u8; 4]>,
+}
+
+struct Frame {
+    duration: u32,
+    cels: Vec<Cel>,
+}
+
+struct Tag {
+    name: String,
+    from: usize,
+    to: usize,
+    direction: u8,
+}
+
+struct File {
+    header: Header,
+    layers: Vec<Layer>,
+    palette: Vec<[u8; 4]>,
+    frames: Vec<Frame>,
+    tags: Vec<Tag>,
+}
+
+fn read_header(reader: &mut Reader) -> Result<Header, Error> {
+    let start = reader.position;
+    reader.skip(4)?; // File size
+    if reader.word()? != HEADER_MAGIC {
+        return Err(AsepriteError::InvalidHeader.into());
+    }
+    let num_frames = reader.word()?;
+    let width = reader.word()?;
+    let height = reader.word()?;
+    let color_depth = reader.word()?;
+    let flags = reader.dword()?;
+    reader.skip(2 + 4 + 4)?; // Deprecated speed and reserved fields
+    let transparent_index = reader.byte()?;
+    reader.skip(HEADER_SIZE - (reader.position - start))?;
+    Ok(Header {
+        num_frames,
+        width,
+        height,
+        color_depth,
+        flags,
+        transparent_index,
+    })
+}
+
+fn read_pixels(data: &[u8], header: &Header, palette: &[[u8; 4]]) -> Result<Vec<[u8; 4]>, Error> {
+    Ok(match header.color_depth {
+        32 => data
+            .chunks_exact(4)
+            .map(|p| [p[0], p[1], p[2], p[3]])
+            .collect(),
+        16 => data
+            .chunks_exact(2)
+            .map(|p| [p[0], p[0], p[0], p[1]])
+            .collect(),
+        8 => data
+            .iter()
+            .map(|&i| {
+                if i == header.transparent_index {
+                    [0, 0, 0, 0]
+                } else {
+                    palette.get(i as usize).cloned().unwrap_or([0, 0, 0, 0])
+                }
+            })
+            .collect(),
+        d => return Err(AsepriteError::UnsupportedColorDepth(d).into()),
+    })
+}
+
+fn read_cel(
+    reader: &mut Reader,
+    chunk_end: usize,
+    header: &Header,
+    palette: &[[u8; 4]],
+    frames: &[Frame],
+) -> Result<Option<Cel>, Error> {
+    let layer = reader.word()? as usize;
+    let x = i32::from(reader.short()?);
+    let y = i32::from(reader.short()?);
+    let opacity = reader.byte()?;
+    let cel_type = reader.word()?;
+    reader.skip(2 + 5)?; // Z-index and reserved bytes
+    match cel_type {
+        // Raw or compressed image
+        0 | 2 => {
+            let width = u32::from(reader.word()?);
+            let height = u32::from(reader.word()?);
+            if reader.position > chunk_end {
+                return Err(AsepriteError::InvalidFrame.into());
+            }
+            let data = reader.bytes(chunk_end - reader.position)?;
+            let data = if cel_type == 2 {
+                let mut decompressed = Vec::new();
+                ZlibDecoder::new(data).read_to_end(&mut decompressed)?;
+                decompressed
+            } else {
+                data.to_vec()
+            };
+            let pixels = read_pixels(&data, header, palette)?;
+            if pixels.len() < (width * height) as usize {
+                return Err(AsepriteError::InvalidFrame.into());
+            }
+            Ok(Some(Cel {
+                layer,
+                x,
+                y,
+                opacity,
+                width,
+                height,
+                pixels,
+            }))
+        }
+        // Linked cel, re-uses the cel of an earlier frame on the same layer
+        1 => {
+            let frame_position = reader.word()? as usize;
+            Ok(frames
+                .get(frame_position)
+                .and_then(|f| f.cels.iter().find(|c| c.layer == layer))
+                .map(|c| Cel {
+                    x,
+                    y,
+                    opacity,
+                    ..c.clone()
+                }))
+        }
+        // Tilemaps are not supported
+        _ => Ok(None),
+    }
+}
+
+fn read_file(data: &[u8]) -> Result<File, Error> {
+    let mut reader = Reader::new(data);
+    let header = read_header(&mut reader)?;
+    let mut file = File {
+        header,
+        layers: vec![],
+        palette: vec![],
+        frames: vec![],
+        tags: vec![],
+    };
+
+    for _ in 0..file.header.num_frames {
+        let frame_start = reader.position;
+        let frame_size = reader.dword()? as usize;
+        if reader.word()? != FRAME_MAGIC || frame_size < FRAME_HEADER_SIZE {
+            return Err(AsepriteError::InvalidFrame.into());
+        }
+        let old_num_chunks = reader.word()?;
+        let duration = u32::from(reader.word()?);
+        reader.skip(2)?;
+        let num_chunks = match reader.dword()? {
+            0 => u32::from(old_num_chunks),
+            n => n,
+        };
+
+        let mut frame = Frame {
+            duration,
+            cels: vec![],
+        };
+        for _ in 0..num_chunks {
+            let chunk_start = reader.position;
+            let chunk_size = reader.dword()? as usize;
+            let chunk_type = reader.word()?;
+            let chunk_end = chunk_start + chunk_size;
+            if chunk_size < 6 || chunk_end > data.len() {
+                return Err(AsepriteError::InvalidFrame.into());
+            }
+            match chunk_type {
+                CHUNK_LAYER => {
+                    let flags = reader.word()?;
+                    let layer_type = reader.word()?;
+                    reader.skip(2 + 2 + 2 + 2)?; // Child level, default size and blend mode
+                    let opacity = reader.byte()?;
+                    file.layers.push(Layer {
+                        is_visible: flags & LAYER_FLAG_VISIBLE != 0,
+                        is_group: layer_type == LAYER_TYPE_GROUP,
+                        opacity,
+                    });
+                }
+                CHUNK_CEL => {
+                    let cel = read_cel(
+                        &mut reader,
+                        chunk_end,
+                        &file.header,
+                        &file.palette,
+                        &file.frames,
+                    )?;
+                    if let Some(cel) = cel {
+                        frame.cels.push(cel);
+                    }
+                }
+                CHUNK_TAGS => {
+                    let num_tags = reader.word()?;
+                    reader.skip(8)?;
+                    for _ in 0..num_tags {
+                        let from = reader.word()? as usize;
+                        let to = reader.word()? as usize;
+                        let direction = reader.byte()?;
+                        reader.skip(2 + 6 + 3 + 1)?; // Repeat count, reserved bytes and color
+                        let name = reader.string()?;
+                        file.tags.push(Tag {
+                            name,
+                            from,
+                            to,
+                            direction,
+                        });
+                    }
+                }
+                CHUNK_PALETTE => {
+                    let size = reader.dword()? as usize;
+                    let first = reader.dword()? as usize;
+                    let last = reader.dword()? as usize;
+                    reader.skip(8)?;
+                    file.palette
+                        .resize(std::cmp::max(size, file.palette.len()), [0; 4]);
+                    for index in first..=last {
+                        let flags = reader.word()?;
+                        let color = reader.bytes(4)?;
+                        if let Some(entry) = file.palette.get_mut(index) {
+                            *entry = [color[0], color[1], color[2], color[3]];
+                        }
+                        if flags & 1 != 0 {
+                            reader.string()?;
+                        }
+                    }
+                }
+                CHUNK_OLD_PALETTE if file.palette.is_empty() => {
+                    let num_packets = reader.word()?;
+                    let mut index = 0;
+                    for _ in 0..num_packets {
+                        index += reader.byte()? as usize;
+                        let num_colors = match reader.byte()? {
+                            0 => 256,
+                            n => n as usize,
+                        };
+                        for _ in 0..num_colors {
+                            let color = reader.bytes(3)?;
+                            if file.palette.len() <= index {
+                                file.palette.resize(index + 1, [0; 4]);
+                            }
+                            file.palette[index] = [color[0], color[1], color[2], 255];
+                            index += 1;
+                        }
+                    }
+                }
+                _ => (),
+            }
+            reader.position = chunk_end;
+        }
+
+        file.frames.push(frame);
+        reader.position = frame_start + frame_size;
+    }
+
+    Ok(file)
+}
+
+// Composites visible layers from bottom to top with normal blending
+fn flatten(file: &File, frame: &Frame) -> image::RgbaImage {
+    let width = u32::from(file.header.width);
+    let height = u32::from(file.header.height);
+    let mut output = image::RgbaImage::new(width, height);
+    let use_layer_opacity = file.header.flags & HEADER_FLAG_LAYER_OPACITY != 0;
+
+    let mut cels: Vec<&Cel> = frame.cels.iter().collect();
+    cels.sort_by_key(|c| c.layer);
+    for cel in cels {
+        let layer = match file.layers.get(cel.layer) {
+            Some(l) if l.is_visible && !l.is_group => l,
+            _ => continue,
+        };
+        let layer_opacity = if use_layer_opacity {
+            layer.opacity
+        } else {
+            255
+        };
+        let opacity = u32::from(cel.opacity) * u32::from(layer_opacity) / 255;
+        for cel_y in 0..cel.height {
+            for cel_x in 0..cel.width {
+                let x = cel.x + cel_x as i32;
+                let y = cel.y + cel_y as i32;
+                if x < 0 || y < 0 || x >= width as i32 || y >= height as i32 {
+                    continue;
+                }
+                let source = cel.pixels[(cel_y * cel.width + cel_x) as usize];
+                let source_alpha = u32::from(source[3]) * opacity / 255;
+                if source_alpha == 0 {
+                    continue;
+                }
+                let destination = output.get_pixel_mut(x as u32, y as u32);
+                let destination_alpha = u32::from(destination.data[3]);
+                let alpha = source_alpha + destination_alpha * (255 - source_alpha) / 255;
+                for channel in 0..3 {
+                    let blended = (u32::from(source[channel]) * source_alpha
+                        + u32::from(destination.data[channel])
+                            * destination_alpha
+                            * (255 - source_alpha)
+                            / 255)
+                        / alpha;
+                    destination.data[channel] = blended as u8;
+                }
+                destination.data[3] = alpha as u8;
+            }
+        }
+    }
+
+    output
+}
+
+// Order in which a tag plays its frames
+fn tag_frames(tag: &Tag) -> Vec<usize> {
+    let forward: Vec<usize> = (tag.from..=tag.to).collect();
+    let reverse: Vec<usize> = forward.iter().rev().cloned().collect();
+    let inner = |frames: &[usize]| frames[1..frames.len().saturating_sub(1)].to_vec();
+    match tag.direction {
+        2 if forward.len() > 2 => [forward, inner(&reverse)].concat(),
+        3 if forward.len() > 2 => [reverse, inner(&forward)].concat(),
+        1 | 3 => reverse,
+        _ => forward,
+    }
+}
+
+pub fn import<T: AsRef<Path>>(path: T) -> Result<AsepriteImport, Error> {
+    let path = path.as_ref();
+    let file = read_file(&fs::read(path)?)?;
+
+    let name = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "aseprite".to_owned());
+    let directory = path.with_file_name(format!("{}_frames", name));
+    fs::create_dir_all(&directory)?;
+
+    let mut frames = Vec::new();
+    for (index, frame) in file.frames.iter().enumerate() {
+        let frame_path = directory.join(format!("{}_{}.png", name, index));
+        flatten(&file, frame).save(&frame_path)?;
+        frames.push(frame_path);
+    }
+
+    let tags = if file.tags.is_empty() {
+        vec![Tag {
+            name: name.clone(),
+            from: 0,
+            to: file.frames.len().saturating_sub(1),
+            direction: 0,
+        }]
+    } else {
+        file.tags
+    };
+
+    let mut animations = Vec::new();
+    for tag in &tags {
+        let mut animation = Animation::new(&tag.name);
+        for index in tag_frames(tag) {
+            if let (Some(frame_path), Some(frame)) = (frames.get(index), file.frames.get(index)) {
+                let mut animation_frame = AnimationFrame::new(frame_path);
+                animation_frame.set_duration(frame.duration);
+                let position = animation.get_num_frames();
+                animation.insert_animation_frame(animation_frame, position)?;
+            }
+        }
+        animations.push(animation);
+    }
+
+    Ok(AsepriteImport { frames, animations })
+}
+
+#[test]
+fn test_tag_frames() {
+    let tag = |direction| Tag {
+        name: "tag".to_owned(),
+        from: 2,
+        to: 5,
+        direction,
+    };
+    assert_eq!(tag_frames(&tag(0)), vec![2, 3, 4, 5]);
+    assert_eq!(tag_frames(&tag(1)), vec![5, 4, 3, 2]);
+    assert_eq!(tag_frames(&tag(2)), vec![2, 3, 4, 5, 4, 3]);
+    assert_eq!(tag_frames(&tag(3)), vec![5, 4, 3, 2, 3, 4]);
+}
+
+#[test]
+fn test_read_pixels_ignores_partial_pixels() {
+    let header = Header {
+        num_frames: 1,
+        width: 1,
+        height: 1,
+        color_depth: 32,
+        flags: 0,
+        transparent_index: 0,
+    };
+    let pixels = read_pixels(&[1, 2, 3, 4, 5, 6], &header, &[]).unwrap();
+    assert_eq!(pixels, vec![[1, 2, 3, 4]]);
+}
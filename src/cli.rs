@@ -0,0 +1,98 @@
+use failure::Error;
+use std::path::PathBuf;
+
+use crate::export;
+use crate::sheet::{ExportFormat, ExportSettings, Sheet};
+
+const USAGE: &str = "Usage: tiger export <sheet> [--texture-out <path>] [--metadata-out <path>] [--template <path>]";
+
+#[derive(Fail, Debug)]
+pub enum CliError {
+    #[fail(display = "{}", _0)]
+    InvalidArguments(&'static str),
+    #[fail(display = "Unknown argument: {}", _0)]
+    UnknownArgument(String),
+    #[fail(display = "Missing value for argument: {}", _0)]
+    MissingValue(String),
+    #[fail(display = "Sheet has no export settings, {} must be provided", _0)]
+    MissingOverride(&'static str),
+}
+
+#[derive(Debug, Default)]
+struct ExportOverrides {
+    texture_destination: Option<PathBuf>,
+    metadata_destination: Option<PathBuf>,
+    template: Option<PathBuf>,
+}
+
+impl ExportOverrides {
+    fn apply(&self, export_settings: Option<&ExportSettings>) -> Result<ExportSettings, Error> {
+        let mut export_settings = match export_settings {
+            Some(s) => s.clone(),
+            None => {
+                if self.template.is_none() {
+                    return Err(CliError::MissingOverride("--template").into());
+                }
+                if self.metadata_destination.is_none() {
+                    return Err(CliError::MissingOverride("--metadata-out").into());
+                }
+                if self.texture_destination.is_none() {
+                    return Err(CliError::MissingOverride("--texture-out").into());
+                }
+                ExportSettings::new()
+            }
+        };
+        if let Some(p) = &self.texture_destination {
+            export_settings.texture_destination = p.clone();
+            export_settings.skip_texture = false;
+        }
+        if let Some(p) = &self.metadata_destination {
+            export_settings.metadata_destination = p.clone();
+        }
+        if let Some(p) = &self.template {
+            export_settings.format = ExportFormat::Template(p.clone());
+        }
+        if export_settings.metadata_paths_root.as_os_str().is_empty() {
+            if let Some(directory) = export_settings.metadata_destination.parent() {
+                export_settings.metadata_paths_root = directory.to_owned();
+            }
+        }
+        Ok(export_settings)
+    }
+}
+
+// Returns false when the arguments do not start with a subcommand, so the GUI launches as usual
+pub fn run<I: Iterator<Item = String>>(mut args: I) -> Result<bool, Error> {
+    match args.next() {
+        Some(ref command) if command == "export" => (),
+        Some(_) | None => return Ok(false),
+    };
+
+    let mut sheet_path = None;
+    let mut overrides = ExportOverrides::default();
+    while let Some(arg) = args.next() {
+        let destination = match arg.as_str() {
+            "--texture-out" => &mut overrides.texture_destination,
+            "--metadata-out" => &mut overrides.metadata_destination,
+            "--template" => &mut overrides.template,
+            _ if arg.starts_with("--") => return Err(CliError::UnknownArgument(arg).into()),
+            _ if sheet_path.is_none() => {
+                sheet_path = Some(PathBuf::from(arg));
+                continue;
+            }
+            _ => return Err(CliError::InvalidArguments(USAGE).into()),
+        };
+        let value = args
+            .next()
+            .ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+        *destination = Some(std::env::current_dir()?.join(value));
+    }
+    let sheet_path = sheet_path.ok_or(CliError::InvalidArguments(USAGE))?;
+
+    let mut sheet = Sheet::open(&sheet_path)?;
+    let export_settings = overrides.apply(sheet.get_export_settings().as_ref())?;
+    sheet.set_export_settings(export_settings);
+    export::export_to_files(&sheet)?;
+
+    Ok(true)
+}
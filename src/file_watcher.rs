@@ -0,0 +1,78 @@
+use failure::Error;
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver};
+use std::time::Duration;
+
+// Bursts of writes within this delay are reported as a single event
+const DEBOUNCE_DELAY: Duration = Duration::from_millis(500);
+
+pub struct FileWatcher {
+    watcher: RecommendedWatcher,
+    receiver: Receiver<DebouncedEvent>,
+    files: HashSet<PathBuf>,
+    directories: HashSet<PathBuf>,
+}
+
+impl FileWatcher {
+    pub fn new() -> Result<FileWatcher, Error> {
+        let (sender, receiver) = channel();
+        let watcher = notify::watcher(sender, DEBOUNCE_DELAY)?;
+        Ok(FileWatcher {
+            watcher,
+            receiver,
+            files: HashSet::new(),
+            directories: HashSet::new(),
+        })
+    }
+
+    // Watching parent directories rather than the files themselves keeps working when
+    // an editor saves by replacing the file. Directories which could not be watched are
+    // not kept, so they are attempted again the next time the watched files change.
+    pub fn set_watched_files(&mut self, files: HashSet<PathBuf>) -> Vec<Error> {
+        let mut errors = Vec::new();
+        if files == self.files {
+            return errors;
+        }
+        let directories: HashSet<PathBuf> = files
+            .iter()
+            .filter_map(|f| f.parent().map(Path::to_owned))
+            .collect();
+        for directory in self.directories.difference(&directories) {
+            self.watcher.unwatch(directory).ok();
+        }
+        let mut watched_directories = HashSet::new();
+        for directory in directories {
+            if self.directories.contains(&directory) {
+                watched_directories.insert(directory);
+                continue;
+            }
+            match self.watcher.watch(&directory, RecursiveMode::NonRecursive) {
+                Ok(()) => {
+                    watched_directories.insert(directory);
+                }
+                Err(e) => errors.push(e.into()),
+            }
+        }
+        self.files = files;
+        self.directories = watched_directories;
+        errors
+    }
+
+    pub fn take_written_files(&mut self) -> Vec<PathBuf> {
+        let mut written_files = Vec::new();
+        while let Ok(event) = self.receiver.try_recv() {
+            let path = match event {
+                DebouncedEvent::Write(p)
+                | DebouncedEvent::Create(p)
+                | DebouncedEvent::Rename(_, p) => p,
+                _ => continue,
+            };
+            if self.files.contains(&path) && !written_files.contains(&path) {
+                written_files.push(path);
+            }
+        }
+        written_files
+    }
+}
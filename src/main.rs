@@ -10,15 +10,21 @@ use imgui_winit_support;
 extern crate serde_derive;
 
 use gfx::Device;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::*;
 
-mod export;
-mod sheet;
+mod aseprite;
+mod cli;
+mod file_watcher;
 mod state;
 mod streamer;
 mod ui;
 mod utils;
 
+use tiger_core::{export, sheet};
+
 const WINDOW_TITLE: &str = "Tiger";
 
 #[derive(Fail, Debug)]
@@ -57,6 +63,22 @@ fn get_shaders(version: gfx_device_gl::Version) -> imgui_gfx_renderer::Shaders {
     }
 }
 
+// Templates whose changes trigger a new export of the current document
+fn get_live_export_templates(state: &state::AppState) -> HashSet<PathBuf> {
+    let mut templates = HashSet::new();
+    if !state.get_preferences().live_export {
+        return templates;
+    }
+    if let Some(document) = state.get_current_document() {
+        if let Some(export_settings) = document.sheet.get_export_settings() {
+            if let sheet::ExportFormat::Template(p) = &export_settings.format {
+                templates.insert(p.clone());
+            }
+        }
+    }
+    templates
+}
+
 #[derive(Debug, Default)]
 struct AsyncCommands {
     commands: Vec<state::AsyncCommand>,
@@ -68,6 +90,16 @@ struct AsyncResults {
 }
 
 fn main() -> Result<(), failure::Error> {
+    // Headless runs report failures as plain messages so build scripts can surface them
+    match cli::run(std::env::args().skip(1)) {
+        Ok(true) => return Ok(()),
+        Ok(false) => (),
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        }
+    }
+
     let mut events_loop = glutin::EventsLoop::new();
     let context = glutin::ContextBuilder::new().with_vsync(true);
     let window = glutin::WindowBuilder::new().with_title(WINDOW_TITLE);
@@ -97,14 +129,19 @@ fn main() -> Result<(), failure::Error> {
     let async_commands: Arc<(Mutex<AsyncCommands>, Condvar)> =
         Arc::new((Mutex::new(Default::default()), Condvar::new()));
     let async_results: Arc<Mutex<AsyncResults>> = Arc::new(Mutex::new(Default::default()));
-    let state_mutex: Arc<Mutex<state::AppState>> = Arc::new(Mutex::new(Default::default()));
+    let preferences = state::Preferences::read().unwrap_or_default();
+    let state_mutex: Arc<Mutex<state::AppState>> =
+        Arc::new(Mutex::new(state::AppState::with_preferences(preferences)));
     let texture_cache = Arc::new(Mutex::new(streamer::TextureCache::new()));
     let (streamer_from_disk, streamer_to_gpu) = streamer::init();
     let main_thread_frame = Arc::new((Mutex::new(false), Condvar::new()));
+    let export_cancelled = Arc::new(AtomicBool::new(false));
+    let mut file_watcher = file_watcher::FileWatcher::new()?;
 
     // Thread processing async commands without blocking the UI
     let async_commands_for_worker = async_commands.clone();
     let async_results_for_worker = async_results.clone();
+    let export_cancelled_for_worker = export_cancelled.clone();
     std::thread::spawn(move || loop {
         let commands;
 
@@ -117,8 +154,16 @@ fn main() -> Result<(), failure::Error> {
             commands = async_commands.commands.clone();
         }
 
+        // Progress reports reach the main thread while the command is still running
+        let report = |buffer: state::CommandBuffer| {
+            let mut result_mutex = async_results_for_worker.lock().unwrap();
+            result_mutex.results.push(Ok(buffer));
+        };
+
         for command in &commands {
-            let process_result = state::process_async_command(&command);
+            export_cancelled_for_worker.store(false, Ordering::Relaxed);
+            let process_result =
+                state::process_async_command(&command, &export_cancelled_for_worker, &report);
             {
                 let mut result_mutex = async_results_for_worker.lock().unwrap();
                 result_mutex.results.push(process_result);
@@ -223,7 +268,19 @@ fn main() -> Result<(), failure::Error> {
 
             state.tick(delta);
 
+            // Autosave
+            for (path, sheet) in state.take_autosaves() {
+                new_commands.autosave(path, &sheet, state.get_preferences().absolute_paths);
+            }
+
             if state.get_exit_state() == Some(state::ExitState::Allowed) {
+                let views = state
+                    .documents_iter()
+                    .map(|d| (d.source.clone(), (&d.view).into()))
+                    .collect();
+                if let Err(e) = state::write_saved_views(views) {
+                    println!("Error saving view state: {}", e);
+                }
                 break;
             }
 
@@ -236,13 +293,23 @@ fn main() -> Result<(), failure::Error> {
                             new_commands.append(buffer);
                         }
                         Err(e) => {
-                            // TODO surface to user
                             println!("Error: {}", e);
+                            new_commands.show_error((&e).into());
                         }
                     }
                 }
             }
 
+            // Live export
+            for e in file_watcher.set_watched_files(get_live_export_templates(&state)) {
+                new_commands.show_error((&e).into());
+            }
+            if !file_watcher.take_written_files().is_empty() {
+                if let Some(document) = state.get_current_document() {
+                    new_commands.export(document);
+                }
+            }
+
             // Process new commands
             use state::Command;
             for command in &new_commands.flush() {
@@ -272,6 +339,19 @@ fn main() -> Result<(), failure::Error> {
                 }
             }
 
+            // Interrupt the export running on the async worker
+            if state.take_export_cancel_request() {
+                export_cancelled.store(true, Ordering::Relaxed);
+            }
+
+            // Reload textures that were refreshed from disk
+            {
+                let mut texture_cache = texture_cache.lock().unwrap();
+                for path in state.take_textures_to_reload() {
+                    texture_cache.invalidate(path);
+                }
+            }
+
             // Commit new state
             {
                 let mut s = state_mutex.lock().unwrap();
@@ -1,17 +1,23 @@
+use clipboard::{ClipboardContext, ClipboardProvider};
 use failure::Error;
-use std::fs::File;
-use std::io::prelude::*;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
 use crate::export::*;
 use crate::sheet::*;
 use crate::state::*;
 
+pub use crate::export::{ExportProgress, ExportStep};
+
 const SHEET_FILE_EXTENSION: &str = "tiger";
 const TEMPLATE_FILE_EXTENSION: &str = "liquid";
 const IMAGE_IMPORT_FILE_EXTENSIONS: &str = "png;tga;bmp";
+const ASEPRITE_FILE_EXTENSIONS: &str = "ase;aseprite";
 const IMAGE_EXPORT_FILE_EXTENSIONS: &str = "png";
+const GIF_EXPORT_FILE_EXTENSION: &str = "gif";
+const MAX_IMPORT_FOLDER_DEPTH: u32 = 16;
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(60);
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum ExitState {
@@ -20,20 +26,72 @@ pub enum ExitState {
     Allowed,
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidSave {
+    pub path: PathBuf,
+    pub sheet: Sheet,
+    pub version: i32,
+    pub absolute_paths: bool,
+    pub reason: String,
+}
+
+// Failure of an async command, shown until the user dismisses it
+#[derive(Clone, Debug, PartialEq)]
+pub struct UserFacingError {
+    pub message: String,
+}
+
+impl From<&Error> for UserFacingError {
+    fn from(error: &Error) -> UserFacingError {
+        UserFacingError {
+            message: error.to_string(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default)]
 pub struct AppState {
     documents: Vec<Document>,
     current_document: Option<PathBuf>,
     clock: Duration,
     exit_state: Option<ExitState>,
+    revert_request: Option<PathBuf>,
+    preferences: Preferences,
+    textures_to_reload: Vec<PathBuf>,
+    invalid_save: Option<InvalidSave>,
+    documents_to_close: Vec<PathBuf>,
+    discard_changes_on_close: bool, // Set by "Discard All" until the queued documents are closed
+    clipboard: Option<ClipboardContent>,
+    last_autosave: Duration,
+    autosaves: Vec<(PathBuf, Sheet)>,
+    export_progress: Option<ExportProgress>,
+    export_cancel_requested: bool,
+    error: Option<UserFacingError>,
 }
 
 impl AppState {
+    pub fn with_preferences(preferences: Preferences) -> AppState {
+        AppState {
+            preferences,
+            ..Default::default()
+        }
+    }
+
     pub fn tick(&mut self, delta: Duration) {
         self.clock += delta;
+        if self.clock >= self.last_autosave + AUTOSAVE_INTERVAL {
+            self.last_autosave = self.clock;
+            self.autosaves = self
+                .documents
+                .iter()
+                .filter(|d| !d.is_saved())
+                .map(|d| (d.source.clone(), d.sheet.clone()))
+                .collect();
+        }
         if let Some(document) = self.get_current_document_mut() {
             document.tick(delta);
         }
+        self.advance_close();
         if self.exit_state.is_some() {
             if self.documents.iter().all(|d| d.is_saved()) {
                 self.exit_state = Some(ExitState::Allowed);
@@ -49,6 +107,49 @@ impl AppState {
         self.exit_state
     }
 
+    // Document waiting for the user to decide what happens to its unsaved changes
+    pub fn get_document_to_close(&self) -> Option<&Document> {
+        self.documents_to_close
+            .first()
+            .and_then(|p| self.documents.iter().find(|d| &d.source == p))
+    }
+
+    pub fn get_num_documents_to_close(&self) -> usize {
+        self.documents_to_close.len()
+    }
+
+    pub fn get_preferences(&self) -> &Preferences {
+        &self.preferences
+    }
+
+    pub fn take_textures_to_reload(&mut self) -> Vec<PathBuf> {
+        std::mem::replace(&mut self.textures_to_reload, vec![])
+    }
+
+    pub fn take_autosaves(&mut self) -> Vec<(PathBuf, Sheet)> {
+        std::mem::replace(&mut self.autosaves, vec![])
+    }
+
+    pub fn take_export_cancel_request(&mut self) -> bool {
+        std::mem::replace(&mut self.export_cancel_requested, false)
+    }
+
+    pub fn get_export_progress(&self) -> Option<&ExportProgress> {
+        self.export_progress.as_ref()
+    }
+
+    pub fn get_error(&self) -> Option<&UserFacingError> {
+        self.error.as_ref()
+    }
+
+    pub fn get_invalid_save(&self) -> Option<&InvalidSave> {
+        self.invalid_save.as_ref()
+    }
+
+    pub fn get_revert_request(&self) -> Option<&Path> {
+        self.revert_request.as_ref().map(|p| p.as_path())
+    }
+
     fn is_opened<T: AsRef<Path>>(&self, path: T) -> bool {
         self.documents.iter().any(|t| t.source == path.as_ref())
     }
@@ -95,6 +196,20 @@ impl AppState {
         Ok(())
     }
 
+    fn end_new_document_from_template<T: AsRef<Path>>(
+        &mut self,
+        path: T,
+        sheet: &Sheet,
+    ) -> Result<(), Error> {
+        let document = Document::from_template(path.as_ref(), sheet.clone());
+        match self.get_document_mut(&path) {
+            Some(d) => *d = document,
+            None => self.add_document(document),
+        }
+        self.current_document = Some(path.as_ref().to_owned());
+        Ok(())
+    }
+
     fn end_open_document<T: AsRef<Path>>(&mut self, path: T) -> Result<(), Error> {
         if self.get_document(&path).is_none() {
             let document = Document::open(&path)?;
@@ -135,6 +250,28 @@ impl AppState {
         return Ok(());
     }
 
+    fn request_revert_document<T: AsRef<Path>>(&mut self, path: T) -> Result<(), Error> {
+        if !self.is_opened(&path) {
+            return Err(StateError::DocumentNotFound.into());
+        }
+        self.revert_request = Some(path.as_ref().to_owned());
+        Ok(())
+    }
+
+    fn revert_document<T: AsRef<Path>>(&mut self, path: T) -> Result<(), Error> {
+        self.revert_request = None;
+        let reverted_document = Document::open(&path)?;
+        let document = self
+            .get_document_mut(&path)
+            .ok_or(StateError::DocumentNotFound)?;
+        *document = reverted_document;
+        self.focus_document(path)
+    }
+
+    fn cancel_revert_document(&mut self) {
+        self.revert_request = None;
+    }
+
     fn focus_document<T: AsRef<Path>>(&mut self, path: T) -> Result<(), Error> {
         let document = self
             .get_document_mut(&path)
@@ -149,31 +286,136 @@ impl AppState {
         self.documents.push(added_document);
     }
 
-    fn close_current_document(&mut self) -> Result<(), Error> {
-        let document = self
-            .get_current_document()
-            .ok_or(StateError::NoDocumentOpen)?;
+    // Only changes the order of document tabs, sheets and their history are untouched
+    fn reorder_document<T: AsRef<Path>>(&mut self, path: T, new_index: usize) -> Result<(), Error> {
         let index = self
             .documents
             .iter()
-            .position(|d| d as *const Document == document as *const Document)
+            .position(|d| d.source == path.as_ref())
             .ok_or(StateError::DocumentNotFound)?;
-        self.documents.remove(index);
-        self.current_document = if self.documents.is_empty() {
-            None
-        } else {
-            Some(
-                self.documents[std::cmp::min(index, self.documents.len() - 1)]
-                    .source
-                    .clone(),
-            )
-        };
+        let document = self.documents.remove(index);
+        let new_index = std::cmp::min(new_index, self.documents.len());
+        self.documents.insert(new_index, document);
+        Ok(())
+    }
+
+    fn close_document<T: AsRef<Path>>(&mut self, path: T) -> Result<(), Error> {
+        let index = self
+            .documents
+            .iter()
+            .position(|d| d.source == path.as_ref())
+            .ok_or(StateError::DocumentNotFound)?;
+        let document = self.documents.remove(index);
+        if let Err(e) = write_saved_views(vec![(document.source, (&document.view).into())]) {
+            println!("Error saving view state: {}", e);
+        }
+        if self.current_document.as_ref().map(|p| p.as_path()) == Some(path.as_ref()) {
+            self.current_document = if self.documents.is_empty() {
+                None
+            } else {
+                Some(
+                    self.documents[std::cmp::min(index, self.documents.len() - 1)]
+                        .source
+                        .clone(),
+                )
+            };
+        }
+        Ok(())
+    }
+
+    // Closes queued documents until one has unsaved changes the user has not decided about
+    fn advance_close(&mut self) {
+        while let Some(path) = self.documents_to_close.first().cloned() {
+            let is_saved = match self.get_document(&path) {
+                Some(d) => d.is_saved(),
+                None => {
+                    self.documents_to_close.remove(0);
+                    continue;
+                }
+            };
+            if !is_saved && !self.discard_changes_on_close {
+                break;
+            }
+            self.documents_to_close.remove(0);
+            self.close_document(path).ok();
+        }
+        if self.documents_to_close.is_empty() {
+            self.discard_changes_on_close = false;
+        }
+    }
+
+    fn begin_close<T: AsRef<Path>>(&mut self, paths: Vec<T>) {
+        for path in paths {
+            if !self.documents_to_close.iter().any(|p| p == path.as_ref()) {
+                self.documents_to_close.push(path.as_ref().to_owned());
+            }
+        }
+        self.advance_close();
+    }
+
+    fn close_current_document(&mut self) -> Result<(), Error> {
+        let path = self
+            .current_document
+            .clone()
+            .ok_or(StateError::NoDocumentOpen)?;
+        self.begin_close(vec![path]);
         Ok(())
     }
 
     fn close_all_documents(&mut self) {
-        self.documents.clear();
-        self.current_document = None;
+        let paths: Vec<PathBuf> = self.documents.iter().map(|d| d.source.clone()).collect();
+        self.begin_close(paths);
+    }
+
+    fn close_without_saving(&mut self) -> Result<(), Error> {
+        if self.documents_to_close.is_empty() {
+            return Err(StateError::NotClosingDocuments.into());
+        }
+        let path = self.documents_to_close.remove(0);
+        self.close_document(path)?;
+        self.advance_close();
+        Ok(())
+    }
+
+    fn discard_all_on_close(&mut self) {
+        self.discard_changes_on_close = true;
+        self.advance_close();
+    }
+
+    fn cancel_close(&mut self) {
+        self.documents_to_close.clear();
+        self.discard_changes_on_close = false;
+    }
+
+    fn copy_selection(&mut self) -> Result<(), Error> {
+        let document = self
+            .get_current_document()
+            .ok_or(StateError::NoDocumentOpen)?;
+        self.clipboard = Some(document.copy_selection()?);
+        Ok(())
+    }
+
+    fn paste(&mut self) -> Result<(), Error> {
+        let content = self.clipboard.clone().ok_or(StateError::ClipboardEmpty)?;
+        self.process_document_command(&DocumentCommand::Paste(content))
+    }
+
+    // Re-reads every frame of the current document from disk, for changes the file watcher missed
+    fn reload_textures(&mut self) -> Result<(), Error> {
+        let document = self
+            .get_current_document()
+            .ok_or(StateError::NoDocumentOpen)?;
+        let sources: Vec<PathBuf> = document
+            .sheet
+            .frames_iter()
+            .map(|f| f.get_source().to_owned())
+            .collect();
+        self.textures_to_reload.extend(sources);
+        Ok(())
+    }
+
+    pub fn get_clipboard(&self) -> Option<&ClipboardContent> {
+        self.clipboard.as_ref()
     }
 
     fn exit(&mut self) {
@@ -194,6 +436,27 @@ impl AppState {
 
     fn cancel_exit(&mut self) {
         self.exit_state = None;
+        self.discard_changes_on_close = false;
+    }
+
+    // Errors raised before the previous ones were dismissed are listed together
+    fn show_error(&mut self, error: &UserFacingError) {
+        self.error = Some(match self.error.take() {
+            Some(mut shown) if shown != *error => {
+                shown.message.push('\n');
+                shown.message.push_str(&error.message);
+                shown
+            }
+            Some(shown) => shown,
+            None => error.clone(),
+        });
+    }
+
+    fn cancel_save_without_validation(&mut self) {
+        self.invalid_save = None;
+        if self.exit_state == Some(ExitState::Saving) {
+            self.exit_state = None;
+        }
     }
 
     fn process_app_command(&mut self, command: &AppCommand) -> Result<(), Error> {
@@ -201,11 +464,31 @@ impl AppState {
 
         match command {
             EndNewDocument(p) => self.end_new_document(p)?,
+            EndNewDocumentFromTemplate(p, s) => self.end_new_document_from_template(p, s)?,
             EndOpenDocument(p) => self.end_open_document(p)?,
             RelocateDocument(from, to) => self.relocate_document(from, to)?,
+            RequestRevertDocument(p) => self.request_revert_document(p)?,
+            RevertDocument(p) => self.revert_document(p)?,
+            CancelRevertDocument => self.cancel_revert_document(),
+            SetPreferences(p) => self.preferences = p.clone(),
+            RequestSaveWithoutValidation(s) => self.invalid_save = Some(s.clone()),
+            ConfirmSaveWithoutValidation => self.invalid_save = None,
+            CancelSaveWithoutValidation => self.cancel_save_without_validation(),
+            UpdateExportProgress(p) => self.export_progress = Some(p.clone()),
+            EndExport => self.export_progress = None,
+            CancelExport => self.export_cancel_requested = true,
+            ShowError(e) => self.show_error(e),
+            DismissError => self.error = None,
             FocusDocument(p) => self.focus_document(p)?,
+            ReorderDocument(p, i) => self.reorder_document(p, *i)?,
             CloseCurrentDocument => self.close_current_document()?,
             CloseAllDocuments => self.close_all_documents(),
+            CloseWithoutSaving => self.close_without_saving()?,
+            DiscardAllOnClose => self.discard_all_on_close(),
+            CancelClose => self.cancel_close(),
+            CopySelection => self.copy_selection()?,
+            PasteClipboard => self.paste()?,
+            ReloadTextures => self.reload_textures()?,
             Undo => self
                 .get_current_document_mut()
                 .ok_or(StateError::NoDocumentOpen)?
@@ -227,18 +510,31 @@ impl AppState {
         use DocumentCommand::*;
         let document = match command {
             EndImport(p, _)
+            | EndSelectGridImage(p, _, _)
+            | EndImportAseprite(p, _, _)
             | MarkAsSaved(p, _)
             | EndSetExportTextureDestination(p, _)
             | EndSetExportMetadataDestination(p, _)
             | EndSetExportMetadataPathsRoot(p, _)
-            | EndSetExportFormat(p, _) => {
+            | EndSetExportFormat(p, _)
+            | EndRelinkFrames(p, _)
+            | EndEstimatePacking(p, _)
+            | EndValidateExport(p, _)
+            | RecordExportDestinations(p)
+            | BeginExportOverwrite(p, _)
+            | EndExportOverwrite(p, _)
+            | CancelExportOverwrite(p) => {
                 self.get_document_mut(p).ok_or(StateError::DocumentNotFound)
             }
             _ => self
                 .get_current_document_mut()
                 .ok_or(StateError::NoDocumentOpen),
         }?;
-        document.process_command(command)
+        document.process_command(command)?;
+        if let RefreshFrame(p, _) = command {
+            self.textures_to_reload.push(p.clone());
+        }
+        Ok(())
     }
 
     pub fn process_sync_command(&mut self, command: &SyncCommand) -> Result<(), Error> {
@@ -249,18 +545,47 @@ impl AppState {
     }
 }
 
+// Appends the sheet extension rather than replacing another one (`foo.png` -> `foo.png.tiger`)
+fn with_sheet_extension<T: AsRef<Path>>(path: T) -> PathBuf {
+    let path = path.as_ref();
+    match path.extension() {
+        Some(e) if e == SHEET_FILE_EXTENSION => path.to_owned(),
+        _ => {
+            let mut file_name = path.as_os_str().to_owned();
+            file_name.push(".");
+            file_name.push(SHEET_FILE_EXTENSION);
+            PathBuf::from(file_name)
+        }
+    }
+}
+
 fn begin_new_document() -> Result<CommandBuffer, Error> {
     let mut command_buffer = CommandBuffer::new();
     if let nfd::Response::Okay(path_string) =
         nfd::open_save_dialog(Some(SHEET_FILE_EXTENSION), None)?
     {
-        let mut path = std::path::PathBuf::from(path_string);
-        path.set_extension(SHEET_FILE_EXTENSION);
+        let path = with_sheet_extension(path_string);
         command_buffer.end_new_document(path);
     };
     Ok(command_buffer)
 }
 
+fn begin_new_document_from_template() -> Result<CommandBuffer, Error> {
+    let mut command_buffer = CommandBuffer::new();
+    if let nfd::Response::Okay(template_path_string) =
+        nfd::open_file_dialog(Some(SHEET_FILE_EXTENSION), None)?
+    {
+        let template = Document::open(std::path::PathBuf::from(template_path_string))?;
+        if let nfd::Response::Okay(path_string) =
+            nfd::open_save_dialog(Some(SHEET_FILE_EXTENSION), None)?
+        {
+            let path = with_sheet_extension(path_string);
+            command_buffer.end_new_document_from_template(path, template.sheet.to_template());
+        };
+    };
+    Ok(command_buffer)
+}
+
 fn begin_open_document() -> Result<CommandBuffer, Error> {
     let mut buffer = CommandBuffer::new();
     match nfd::open_file_multiple_dialog(Some(SHEET_FILE_EXTENSION), None)? {
@@ -279,22 +604,64 @@ fn begin_open_document() -> Result<CommandBuffer, Error> {
     Ok(buffer)
 }
 
-fn save<T: AsRef<Path>>(sheet: &Sheet, source: T, version: i32) -> Result<CommandBuffer, Error> {
+fn save<T: AsRef<Path>>(
+    sheet: &Sheet,
+    source: T,
+    version: i32,
+    absolute_paths: bool,
+) -> Result<CommandBuffer, Error> {
     let mut buffer = CommandBuffer::new();
-    Document::save(sheet, source.as_ref())?;
-    buffer.mark_as_saved(source, version);
+    match Document::save(sheet, source.as_ref(), absolute_paths) {
+        Ok(()) => {
+            buffer.mark_as_saved(&source, version);
+            buffer.delete_autosave(&source);
+        }
+        Err(e) => match e.downcast::<ValidationError>() {
+            Ok(validation_error) => buffer.request_save_without_validation(InvalidSave {
+                path: source.as_ref().to_owned(),
+                sheet: sheet.clone(),
+                version,
+                absolute_paths,
+                reason: validation_error.to_string(),
+            }),
+            Err(e) => return Err(e),
+        },
+    };
     Ok(buffer)
 }
 
-fn save_as<T: AsRef<Path>>(sheet: &Sheet, source: T, version: i32) -> Result<CommandBuffer, Error> {
+fn save_without_validation<T: AsRef<Path>>(
+    sheet: &Sheet,
+    source: T,
+    version: i32,
+    absolute_paths: bool,
+) -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    Document::save_without_validation(sheet, source.as_ref(), absolute_paths)?;
+    buffer.mark_as_saved(&source, version);
+    buffer.delete_autosave(&source);
+    Ok(buffer)
+}
+
+// Writes next to the sheet without marking the document as saved
+fn autosave<T: AsRef<Path>>(sheet: &Sheet, source: T, absolute_paths: bool) -> Result<(), Error> {
+    let autosave_path = Document::get_autosave_path(source);
+    Document::save_without_validation(sheet, autosave_path, absolute_paths)
+}
+
+fn save_as<T: AsRef<Path>>(
+    sheet: &Sheet,
+    source: T,
+    version: i32,
+    absolute_paths: bool,
+) -> Result<CommandBuffer, Error> {
     let mut buffer = CommandBuffer::new();
     if let nfd::Response::Okay(path_string) =
         nfd::open_save_dialog(Some(SHEET_FILE_EXTENSION), None)?
     {
-        let mut new_path = std::path::PathBuf::from(path_string);
-        new_path.set_extension(SHEET_FILE_EXTENSION);
+        let new_path = with_sheet_extension(path_string);
         buffer.relocate_document(source, &new_path);
-        buffer.save(&new_path, sheet, version);
+        buffer.save(&new_path, sheet, version, absolute_paths);
     };
     Ok(buffer)
 }
@@ -317,6 +684,85 @@ fn begin_import<T: AsRef<Path>>(into: T) -> Result<CommandBuffer, Error> {
     Ok(buffer)
 }
 
+fn begin_import_grid<T: AsRef<Path>>(into: T) -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    if let nfd::Response::Okay(path_string) =
+        nfd::open_file_dialog(Some(IMAGE_IMPORT_FILE_EXTENSIONS), None)?
+    {
+        let path = std::path::PathBuf::from(path_string);
+        let image_size = image::open(&path)?.to_rgba().dimensions();
+        buffer.end_select_grid_image(into, path, image_size.into());
+    };
+    Ok(buffer)
+}
+
+fn begin_import_aseprite<T: AsRef<Path>>(into: T) -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    if let nfd::Response::Okay(path_string) =
+        nfd::open_file_dialog(Some(ASEPRITE_FILE_EXTENSIONS), None)?
+    {
+        let import = crate::aseprite::import(path_string)?;
+        buffer.end_import_aseprite(into, import.frames, import.animations);
+    };
+    Ok(buffer)
+}
+
+fn begin_import_folder<T: AsRef<Path>>(into: T, sheet: &Sheet) -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    if let nfd::Response::Okay(path_string) = nfd::open_pick_folder(None)? {
+        let mut images = vec![];
+        find_images(&std::path::PathBuf::from(path_string), 0, &mut images)?;
+        for path in images {
+            if !sheet.has_frame(&path) {
+                buffer.end_import(&into, path);
+            }
+        }
+    };
+    Ok(buffer)
+}
+
+fn begin_relink_frames<T: AsRef<Path>>(document_path: T) -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    if let nfd::Response::Okay(path_string) = nfd::open_pick_folder(None)? {
+        let mut images = vec![];
+        find_images(&std::path::PathBuf::from(path_string), 0, &mut images)?;
+        buffer.end_relink_frames(document_path, images);
+    }
+    Ok(buffer)
+}
+
+// Hidden directories and directories nested too deeply are skipped
+fn find_images(directory: &Path, depth: u32, images: &mut Vec<PathBuf>) -> Result<(), Error> {
+    if depth > MAX_IMPORT_FOLDER_DEPTH {
+        return Ok(());
+    }
+    let mut entries = std::fs::read_dir(directory)?
+        .map(|e| e.map(|e| e.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+    for path in entries {
+        if path.is_dir() {
+            let is_hidden = path
+                .file_name()
+                .map_or(false, |n| n.to_string_lossy().starts_with('.'));
+            if !is_hidden {
+                find_images(&path, depth + 1, images)?;
+            }
+        } else {
+            let is_image = path.extension().map_or(false, |e| {
+                let extension = e.to_string_lossy().to_lowercase();
+                IMAGE_IMPORT_FILE_EXTENSIONS
+                    .split(';')
+                    .any(|x| x == extension)
+            });
+            if is_image {
+                images.push(path);
+            }
+        }
+    }
+    Ok(())
+}
+
 fn begin_set_export_texture_destination<T: AsRef<Path>>(
     document_path: T,
 ) -> Result<CommandBuffer, Error> {
@@ -363,35 +809,155 @@ fn begin_set_export_format<T: AsRef<Path>>(document_path: T) -> Result<CommandBu
     Ok(buffer)
 }
 
-fn export(sheet: &Sheet) -> Result<(), Error> {
+fn begin_export_animation_gif(sheet: &Sheet, animation_name: &str) -> Result<CommandBuffer, Error> {
+    if let nfd::Response::Okay(path_string) =
+        nfd::open_save_dialog(Some(GIF_EXPORT_FILE_EXTENSION), None)?
+    {
+        let animation = sheet
+            .get_animation(animation_name)
+            .ok_or(StateError::AnimationNotInDocument)?;
+        export_animation_gif(sheet, animation, std::path::PathBuf::from(path_string))?;
+    };
+    Ok(CommandBuffer::new())
+}
+
+fn estimate_packing<T: AsRef<Path>>(
+    document_path: T,
+    sheet: &Sheet,
+    settings: &PackSettings,
+    version: i32,
+) -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    let stats = pack_sheet(sheet, settings)?.get_stats();
+    buffer.end_estimate_packing(
+        document_path,
+        PackEstimate {
+            version,
+            settings: settings.clone(),
+            stats,
+        },
+    );
+    Ok(buffer)
+}
+
+// Runs every step of an export except writing files
+fn dry_run_export(sheet: &Sheet, validation: &mut ExportValidation) -> Result<(), Error> {
     let export_settings = sheet
         .get_export_settings()
         .as_ref()
         .ok_or(StateError::NoExistingExportSettings)?;
 
-    // TODO texture export performance is awful
-    let packed_sheet = pack_sheet(&sheet)?;
-    let exported_data = export_sheet(&sheet, &export_settings, &packed_sheet.get_layout())?;
+    if export_settings.skip_texture {
+        let layout = measure_sheet(&sheet)?;
+        validation.num_frames_packed = layout.len() as u32;
+        export_sheet(&sheet, &export_settings, &layout, None)?;
+        return Ok(());
+    }
 
-    {
-        let mut file = File::create(&export_settings.metadata_destination)?;
-        file.write_all(&exported_data.into_bytes())?;
+    let packed_sheet = pack_sheet(&sheet, &export_settings.into())?;
+    validation.num_frames_packed = packed_sheet.get_layout().len() as u32;
+    validation.stats = Some(packed_sheet.get_stats());
+    export_sheet(
+        &sheet,
+        &export_settings,
+        &packed_sheet.get_layout(),
+        Some(packed_sheet.get_size()),
+    )?;
+    Ok(())
+}
+
+// Errors are part of the validation report rather than failures of the command
+fn validate_export<T: AsRef<Path>>(
+    document_path: T,
+    sheet: &Sheet,
+) -> Result<CommandBuffer, Error> {
+    let mut buffer = CommandBuffer::new();
+    let mut validation = ExportValidation::default();
+    if let Err(e) = dry_run_export(sheet, &mut validation) {
+        validation.error = Some(e.to_string());
     }
-    {
-        let mut file = File::create(&export_settings.texture_destination)?;
-        packed_sheet.get_texture().write_to(&mut file, image::PNG)?;
+    buffer.end_validate_export(document_path, validation);
+    Ok(buffer)
+}
+
+// Exports report their progress to the main thread and stop at the next checkpoint once cancelled
+fn run_export<F>(
+    is_cancelled: &AtomicBool,
+    report: &dyn Fn(CommandBuffer),
+    export: F,
+) -> Result<CommandBuffer, Error>
+where
+    F: FnOnce(ExportCheckpoint) -> Result<(), Error>,
+{
+    let checkpoint = |progress: ExportProgress| -> Result<(), Error> {
+        if is_cancelled.load(Ordering::Relaxed) {
+            return Err(StateError::ExportCancelled.into());
+        }
+        let mut buffer = CommandBuffer::new();
+        buffer.update_export_progress(progress);
+        report(buffer);
+        Ok(())
+    };
+    let result = export(&checkpoint);
+
+    let mut buffer = CommandBuffer::new();
+    buffer.end_export();
+    match result {
+        Ok(()) => Ok(buffer),
+        Err(_) if is_cancelled.load(Ordering::Relaxed) => Ok(buffer),
+        Err(e) => {
+            report(buffer);
+            Err(e)
+        }
     }
+}
 
+fn copy_frame_rectangle<T: AsRef<Path>>(sheet: &Sheet, frame: T) -> Result<(), Error> {
+    let pack_settings = sheet
+        .get_export_settings()
+        .as_ref()
+        .map(PackSettings::from)
+        .unwrap_or_default();
+    let packed_sheet = pack_sheet(&sheet, &pack_settings)?;
+    let packed_frame = packed_sheet
+        .get_layout()
+        .get(frame.as_ref())
+        .ok_or(ExportError::FrameWasNotPacked)?;
+    let rectangle = format!(
+        "{},{},{},{}",
+        packed_frame.position_in_sheet.0,
+        packed_frame.position_in_sheet.1,
+        packed_frame.size_in_sheet.0,
+        packed_frame.size_in_sheet.1
+    );
+    let mut clipboard: ClipboardContext =
+        ClipboardProvider::new().map_err(|_| StateError::ClipboardError)?;
+    clipboard
+        .set_contents(rectangle)
+        .map_err(|_| StateError::ClipboardError)?;
     Ok(())
 }
 
-pub fn process_async_command(command: &AsyncCommand) -> Result<CommandBuffer, Error> {
+// Long-running commands can report to the main thread before they complete
+pub fn process_async_command(
+    command: &AsyncCommand,
+    is_cancelled: &AtomicBool,
+    report: &dyn Fn(CommandBuffer),
+) -> Result<CommandBuffer, Error> {
     let no_commands = CommandBuffer::new();
     match command {
         AsyncCommand::BeginNewDocument => begin_new_document(),
+        AsyncCommand::BeginNewDocumentFromTemplate => begin_new_document_from_template(),
         AsyncCommand::BeginOpenDocument => begin_open_document(),
-        AsyncCommand::Save(p, sheet, version) => save(sheet, p, *version),
-        AsyncCommand::SaveAs(p, sheet, version) => save_as(sheet, p, *version),
+        AsyncCommand::Save(p, sheet, version, absolute_paths) => {
+            save(sheet, p, *version, *absolute_paths)
+        }
+        AsyncCommand::SaveAs(p, sheet, version, absolute_paths) => {
+            save_as(sheet, p, *version, *absolute_paths)
+        }
+        AsyncCommand::SaveWithoutValidation(p, sheet, version, absolute_paths) => {
+            save_without_validation(sheet, p, *version, *absolute_paths)
+        }
         AsyncCommand::BeginSetExportTextureDestination(p) => {
             begin_set_export_texture_destination(p)
         }
@@ -401,6 +967,27 @@ pub fn process_async_command(command: &AsyncCommand) -> Result<CommandBuffer, Er
         AsyncCommand::BeginSetExportMetadataPathsRoot(p) => begin_set_export_metadata_paths_root(p),
         AsyncCommand::BeginSetExportFormat(p) => begin_set_export_format(p),
         AsyncCommand::BeginImport(p) => begin_import(p),
-        AsyncCommand::Export(sheet) => export(sheet).and(Ok(no_commands)),
+        AsyncCommand::BeginImportFolder(p, sheet) => begin_import_folder(p, sheet),
+        AsyncCommand::BeginImportGrid(p) => begin_import_grid(p),
+        AsyncCommand::BeginImportAseprite(p) => begin_import_aseprite(p),
+        AsyncCommand::BeginRelinkFrames(p) => begin_relink_frames(p),
+        AsyncCommand::EstimatePacking(p, sheet, settings, version) => {
+            estimate_packing(p, sheet, settings, *version)
+        }
+        AsyncCommand::ValidateExport(p, sheet) => validate_export(p, sheet),
+        AsyncCommand::Export(sheet) => run_export(is_cancelled, report, |checkpoint| {
+            export_to_files_with_progress(sheet, checkpoint)
+        }),
+        AsyncCommand::Autosave(p, sheet, absolute_paths) => {
+            autosave(sheet, p, *absolute_paths).and(Ok(no_commands))
+        }
+        AsyncCommand::DeleteAutosave(p) => Document::delete_autosave(p).and(Ok(no_commands)),
+        AsyncCommand::BeginExportAnimationGif(sheet, animation) => {
+            begin_export_animation_gif(sheet, animation)
+        }
+        AsyncCommand::CopyFrameRectangle(sheet, frame) => {
+            copy_frame_rectangle(sheet, frame).and(Ok(no_commands))
+        }
+        AsyncCommand::SavePreferences(preferences) => preferences.write().and(Ok(no_commands)),
     }
 }
@@ -3,31 +3,65 @@ use std::fmt;
 use std::path::PathBuf;
 use std::time::Duration;
 
+use crate::export::PackSettings;
 use crate::sheet::*;
 use crate::state::*;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum AsyncCommand {
     BeginNewDocument,
+    BeginNewDocumentFromTemplate,
     BeginOpenDocument,
-    Save(PathBuf, Sheet, i32),
-    SaveAs(PathBuf, Sheet, i32),
+    Save(PathBuf, Sheet, i32, bool),
+    SaveAs(PathBuf, Sheet, i32, bool),
+    SaveWithoutValidation(PathBuf, Sheet, i32, bool),
     BeginSetExportTextureDestination(PathBuf),
     BeginSetExportMetadataDestination(PathBuf),
     BeginSetExportMetadataPathsRoot(PathBuf),
     BeginSetExportFormat(PathBuf),
     BeginImport(PathBuf),
+    BeginImportFolder(PathBuf, Sheet),
+    BeginImportGrid(PathBuf),
+    BeginImportAseprite(PathBuf),
+    BeginRelinkFrames(PathBuf),
+    EstimatePacking(PathBuf, Sheet, PackSettings, i32),
+    ValidateExport(PathBuf, Sheet),
     Export(Sheet),
+    BeginExportAnimationGif(Sheet, String),
+    CopyFrameRectangle(Sheet, PathBuf),
+    Autosave(PathBuf, Sheet, bool),
+    DeleteAutosave(PathBuf),
+    SavePreferences(Preferences),
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum AppCommand {
     EndNewDocument(PathBuf),
+    EndNewDocumentFromTemplate(PathBuf, Sheet),
     EndOpenDocument(PathBuf), // TODO This should be async (has IO + heavylifting)
     CloseCurrentDocument,
     CloseAllDocuments,
+    CloseWithoutSaving,
+    DiscardAllOnClose,
+    CancelClose,
+    CopySelection,
+    PasteClipboard,
+    ReloadTextures,
     FocusDocument(PathBuf),
+    ReorderDocument(PathBuf, usize),
     RelocateDocument(PathBuf, PathBuf),
+    RequestRevertDocument(PathBuf),
+    RevertDocument(PathBuf),
+    CancelRevertDocument,
+    SetPreferences(Preferences),
+    RequestSaveWithoutValidation(InvalidSave),
+    ConfirmSaveWithoutValidation,
+    CancelSaveWithoutValidation,
+    UpdateExportProgress(ExportProgress),
+    EndExport,
+    CancelExport,
+    ShowError(UserFacingError),
+    DismissError,
     Undo,
     Redo,
     Exit,
@@ -43,67 +77,158 @@ pub enum DocumentCommand {
     EndSetExportMetadataDestination(PathBuf, PathBuf),
     EndSetExportMetadataPathsRoot(PathBuf, PathBuf),
     EndSetExportFormat(PathBuf, ExportFormat),
+    SetExportSkipTexture(bool),
+    SetExportAbsoluteImagePaths(bool),
+    SetExportAtlasSize(Option<(u32, u32)>),
+    SetExportMaxSize(Option<(u32, u32)>),
+    SetExportSortFramesBySize(bool),
+    SetExportPadding(u32),
+    SetExportExtrude(u32),
+    SetExportPackStrategy(PackStrategy),
+    AddExportTemplateVariable,
+    SetExportTemplateVariable(usize, TemplateVariable),
+    RemoveExportTemplateVariable(usize),
     CancelExportAs,
     EndExportAs,
     MarkAsSaved(PathBuf, i32),
     EndImport(PathBuf, PathBuf),
+    EndSelectGridImage(PathBuf, PathBuf, Vector2D<u32>),
+    EndImportAseprite(PathBuf, Vec<PathBuf>, Vec<Animation>),
+    SetGridImportTileSize(Vector2D<u32>),
+    EndGridImport,
+    CancelGridImport,
     SwitchToContentTab(ContentTab),
+    SetAnimationTagFilter(Option<String>),
     ClearSelection,
+    SelectAll,
+    InvertSelection,
     SelectFrame(PathBuf),
+    ToggleFrameSelection(PathBuf),
+    SelectFrameRange(Vec<PathBuf>),
     SelectAnimation(String),
     SelectHitbox(String),
+    ToggleHitboxSelection(String),
+    SelectHitboxRange(Vec<String>),
     SelectAnimationFrame(usize),
     SelectPrevious,
     SelectNext,
     EditFrame(PathBuf),
     EditAnimation(String),
-    CreateAnimation,
+    CreateAnimation(bool),
     BeginFrameDrag(PathBuf),
     EndFrameDrag,
     InsertAnimationFrameBefore(PathBuf, usize),
+    InsertFramesAtPlayhead,
     ReorderAnimationFrame(usize, usize),
     BeginAnimationFrameDurationDrag(usize),
     UpdateAnimationFrameDurationDrag(u32),
     EndAnimationFrameDurationDrag,
+    SetAnimationFrameDuration(u32),
+    SetAllKeyframeDurations(u32),
+    ReverseAnimation,
     BeginAnimationFrameDrag(usize),
     EndAnimationFrameDrag,
     BeginAnimationFrameOffsetDrag(usize),
-    UpdateAnimationFrameOffsetDrag(Vector2D<f32>, bool),
+    UpdateAnimationFrameOffsetDrag(Vector2D<f32>, bool, bool),
     EndAnimationFrameOffsetDrag,
     WorkbenchZoomIn,
     WorkbenchZoomOut,
+    WorkbenchZoomInAt(Vector2D<f32>),
+    WorkbenchZoomOutAt(Vector2D<f32>),
+    WorkbenchFit(Rect<f32>, Vector2D<f32>),
     WorkbenchResetZoom,
     WorkbenchCenter,
+    ToggleOnionSkin,
+    ToggleSnapToGrid,
+    ToggleAnimationHitboxes,
+    SetSnapGridSize(u32),
     Pan(Vector2D<f32>),
+    Paste(ClipboardContent),
     CreateHitbox(Vector2D<f32>),
     BeginHitboxScale(String, ResizeAxis),
-    UpdateHitboxScale(Vector2D<f32>, bool),
+    UpdateHitboxScale(Vector2D<f32>, bool, bool),
     EndHitboxScale,
     BeginHitboxDrag(String),
-    UpdateHitboxDrag(Vector2D<f32>, bool),
+    UpdateHitboxDrag(Vector2D<f32>, bool, bool),
     EndHitboxDrag,
+    BeginAnchorDrag,
+    UpdateAnchorDrag(Vector2D<f32>, bool),
+    EndAnchorDrag,
+    ToggleHitboxShape,
+    DuplicateHitbox,
+    MirrorHitbox(Vector2D<u32>),
     TogglePlayback,
+    SetPlaybackSpeed(f32),
+    TogglePlaybackDirection,
     SnapToPreviousFrame,
     SnapToNextFrame,
-    ToggleLooping,
+    StepKeyframe(i32),
+    StepTimelineClock(i64),
+    SetLoopMode(LoopMode),
+    SetAnimationFrameRate(Option<f32>),
     TimelineZoomIn,
     TimelineZoomOut,
     TimelineResetZoom,
     BeginScrub,
     UpdateScrub(Duration),
     EndScrub,
+    SetLoopIn,
+    SetLoopOut,
+    ClearLoopRegion,
+    BeginLoopMarkerDrag(LoopMarker),
+    UpdateLoopMarkerDrag(Duration),
+    EndLoopMarkerDrag,
+    BeginWorkbenchScrub,
+    EndWorkbenchScrub,
+    BeginRuler(Vector2D<i32>),
+    UpdateRuler(Vector2D<i32>),
+    EndRuler,
     NudgeSelection(Vector2D<i32>, bool),
+    SetSelectionPosition(Vector2D<i32>),
+    SetHitboxSize(Vector2D<u32>),
+    FlipSelection(bool, bool),
+    AlignHitboxes(HitboxAlignment),
+    DistributeHitboxes(HitboxDistribution),
     DeleteSelection,
     BeginRenameSelection,
     UpdateRenameSelection(String),
     EndRenameSelection,
+    BeginSetAnimationGroup,
+    BeginSetAnimationDirection,
+    BeginAddAnimationTag,
+    RemoveAnimationTag(String),
+    BeginSetAnimationFrameSound,
+    BeginSetAnimationFrameUserData,
+    RefreshFrame(PathBuf, Option<(Vector2D<u32>, Vector2D<u32>)>),
+    BeginBuildAnimationsFromFrameNames,
+    EndBuildAnimationsFromFrameNames(bool),
+    CancelBuildAnimationsFromFrameNames,
+    BeginMergeDuplicateAnimations,
+    EndMergeDuplicateAnimations,
+    CancelMergeDuplicateAnimations,
+    EndRecoverAutosave,
+    CancelRecoverAutosave,
+    EndRelinkFrames(PathBuf, Vec<PathBuf>),
+    CancelRelinkFrames,
+    RecordExportDestinations(PathBuf),
+    BeginExportOverwrite(PathBuf, Vec<PathBuf>),
+    EndExportOverwrite(PathBuf, bool),
+    CancelExportOverwrite(PathBuf),
+    EndEstimatePacking(PathBuf, PackEstimate),
+    EndValidateExport(PathBuf, ExportValidation),
+    DismissExportValidation,
 }
 
 impl fmt::Display for DocumentCommand {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         use DocumentCommand::*;
         match self {
-            EndImport(_, _) => write!(f, "Import Image"),
+            EndImport(_, _)
+            | EndSelectGridImage(_, _, _)
+            | EndImportAseprite(_, _, _)
+            | SetGridImportTileSize(_)
+            | EndGridImport
+            | CancelGridImport => write!(f, "Import Image"),
 
             // Export
             BeginExportAs
@@ -111,15 +236,33 @@ impl fmt::Display for DocumentCommand {
             | EndSetExportMetadataDestination(_, _)
             | EndSetExportMetadataPathsRoot(_, _)
             | EndSetExportFormat(_, _)
+            | SetExportSkipTexture(_)
+            | SetExportAbsoluteImagePaths(_)
+            | SetExportAtlasSize(_)
+            | SetExportMaxSize(_)
+            | SetExportSortFramesBySize(_)
+            | SetExportPadding(_)
+            | SetExportExtrude(_)
+            | SetExportPackStrategy(_)
+            | AddExportTemplateVariable
+            | SetExportTemplateVariable(_, _)
+            | RemoveExportTemplateVariable(_)
             | CancelExportAs
             | EndExportAs => write!(f, "Change Export Options"),
 
             // Navigation
             SwitchToContentTab(_)
+            | SetAnimationTagFilter(_)
             | ClearSelection
+            | SelectAll
+            | InvertSelection
             | SelectFrame(_)
+            | ToggleFrameSelection(_)
+            | SelectFrameRange(_)
             | SelectAnimation(_)
             | SelectHitbox(_)
+            | ToggleHitboxSelection(_)
+            | SelectHitboxRange(_)
             | SelectAnimationFrame(_)
             | SelectPrevious
             | SelectNext
@@ -127,49 +270,108 @@ impl fmt::Display for DocumentCommand {
             | EditAnimation(_)
             | WorkbenchZoomIn
             | WorkbenchZoomOut
+            | WorkbenchZoomInAt(_)
+            | WorkbenchZoomOutAt(_)
+            | WorkbenchFit(_, _)
             | WorkbenchResetZoom
             | WorkbenchCenter
+            | ToggleOnionSkin
+            | ToggleSnapToGrid
+            | ToggleAnimationHitboxes
+            | SetSnapGridSize(_)
             | TogglePlayback
+            | SetPlaybackSpeed(_)
+            | TogglePlaybackDirection
             | SnapToPreviousFrame
             | SnapToNextFrame
+            | StepKeyframe(_)
+            | StepTimelineClock(_)
             | TimelineZoomIn
             | TimelineZoomOut
             | TimelineResetZoom
             | BeginScrub
             | UpdateScrub(_)
             | EndScrub
+            | SetLoopIn
+            | SetLoopOut
+            | ClearLoopRegion
+            | BeginLoopMarkerDrag(_)
+            | UpdateLoopMarkerDrag(_)
+            | EndLoopMarkerDrag
+            | BeginWorkbenchScrub
+            | EndWorkbenchScrub
+            | BeginRuler(_)
+            | UpdateRuler(_)
+            | EndRuler
             | Pan(_) => write!(f, "Navigation"),
 
             MarkAsSaved(_, _) => write!(f, "Mark As Saved"),
 
             // Animation
-            CreateAnimation => write!(f, "Create Animation"),
-            ToggleLooping => write!(f, "Toggle Looping"),
-            BeginFrameDrag(_) | EndFrameDrag | InsertAnimationFrameBefore(_, _) => {
-                write!(f, "Create Frame")
-            }
+            CreateAnimation(_) => write!(f, "Create Animation"),
+            SetLoopMode(_) => write!(f, "Change Loop Mode"),
+            SetAnimationFrameRate(_) => write!(f, "Change Frame Rate"),
+            BeginFrameDrag(_)
+            | EndFrameDrag
+            | InsertAnimationFrameBefore(_, _)
+            | InsertFramesAtPlayhead => write!(f, "Create Frame"),
             BeginAnimationFrameDrag(_) | EndAnimationFrameDrag | ReorderAnimationFrame(_, _) => {
                 write!(f, "Re-order Frames")
             }
             BeginAnimationFrameDurationDrag(_)
             | UpdateAnimationFrameDurationDrag(_)
-            | EndAnimationFrameDurationDrag => write!(f, "Adjust Frame Duration"),
+            | EndAnimationFrameDurationDrag
+            | SetAnimationFrameDuration(_) => write!(f, "Resize Keyframe"),
+            SetAllKeyframeDurations(_) => write!(f, "Resize All Keyframes"),
+            ReverseAnimation => write!(f, "Reverse Animation"),
             BeginAnimationFrameOffsetDrag(_)
-            | UpdateAnimationFrameOffsetDrag(_, _)
-            | EndAnimationFrameOffsetDrag => write!(f, "Move Frame"),
+            | UpdateAnimationFrameOffsetDrag(_, _, _)
+            | EndAnimationFrameOffsetDrag => write!(f, "Move Keyframe"),
 
             // Hitbox
             CreateHitbox(_) => write!(f, "Create Hitbox"),
-            BeginHitboxScale(_, _) | UpdateHitboxScale(_, _) | EndHitboxScale => {
+            BeginHitboxScale(_, _) | UpdateHitboxScale(_, _, _) | EndHitboxScale => {
                 write!(f, "Resize Hitbox")
             }
-            BeginHitboxDrag(_) | UpdateHitboxDrag(_, _) | EndHitboxDrag => write!(f, "Move Hitbox"),
+            BeginHitboxDrag(_) | UpdateHitboxDrag(_, _, _) | EndHitboxDrag => {
+                write!(f, "Move Hitbox")
+            }
+            BeginAnchorDrag | UpdateAnchorDrag(_, _) | EndAnchorDrag => write!(f, "Move Anchor"),
+            ToggleHitboxShape => write!(f, "Change Hitbox Shape"),
+            DuplicateHitbox => write!(f, "Duplicate Hitbox"),
+            MirrorHitbox(_) => write!(f, "Mirror Hitbox"),
 
-            NudgeSelection(_, _) => write!(f, "Nudge"),
+            NudgeSelection(_, _) | SetSelectionPosition(_) => write!(f, "Nudge"),
+            SetHitboxSize(_) => write!(f, "Resize Hitbox"),
+            FlipSelection(_, _) => write!(f, "Flip"),
+            AlignHitboxes(_) => write!(f, "Align Hitboxes"),
+            DistributeHitboxes(_) => write!(f, "Distribute Hitboxes"),
             DeleteSelection => write!(f, "Delete"),
+            Paste(_) => write!(f, "Paste"),
             BeginRenameSelection | UpdateRenameSelection(_) | EndRenameSelection => {
                 write!(f, "Rename")
             }
+            BeginSetAnimationGroup => write!(f, "Change Animation Group"),
+            BeginSetAnimationDirection => write!(f, "Change Animation Direction"),
+            BeginAddAnimationTag => write!(f, "Add Animation Tag"),
+            RemoveAnimationTag(_) => write!(f, "Remove Animation Tag"),
+            BeginSetAnimationFrameSound => write!(f, "Change Sound"),
+            BeginSetAnimationFrameUserData => write!(f, "Change User Data"),
+            RefreshFrame(_, _) => write!(f, "Refresh Frame"),
+            BeginBuildAnimationsFromFrameNames
+            | EndBuildAnimationsFromFrameNames(_)
+            | CancelBuildAnimationsFromFrameNames => write!(f, "Build Animations"),
+            BeginMergeDuplicateAnimations
+            | EndMergeDuplicateAnimations
+            | CancelMergeDuplicateAnimations => write!(f, "Merge Duplicate Animations"),
+            EndRecoverAutosave | CancelRecoverAutosave => write!(f, "Recover Autosave"),
+            EndRelinkFrames(_, _) | CancelRelinkFrames => write!(f, "Relink Frames"),
+            RecordExportDestinations(_)
+            | BeginExportOverwrite(_, _)
+            | EndExportOverwrite(_, _)
+            | CancelExportOverwrite(_) => write!(f, "Export"),
+            EndEstimatePacking(_, _) => write!(f, "Estimate Atlas Size"),
+            EndValidateExport(_, _) | DismissExportValidation => write!(f, "Validate Export"),
         }
     }
 }
@@ -1,5 +1,5 @@
 use euclid::*;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use crate::sheet::*;
@@ -11,6 +11,8 @@ use Command::*;
 use DocumentCommand::*;
 use SyncCommand::*;
 
+const TIMELINE_CLOCK_STEP_MS: i64 = 10;
+
 #[derive(Debug)]
 pub struct CommandBuffer {
     queue: Vec<Command>,
@@ -38,6 +40,17 @@ impl CommandBuffer {
             .push(Sync(App(EndNewDocument(path.as_ref().to_owned()))));
     }
 
+    pub fn begin_new_document_from_template(&mut self) {
+        self.queue.push(Async(BeginNewDocumentFromTemplate));
+    }
+
+    pub fn end_new_document_from_template<T: AsRef<Path>>(&mut self, path: T, sheet: Sheet) {
+        self.queue.push(Sync(App(EndNewDocumentFromTemplate(
+            path.as_ref().to_owned(),
+            sheet,
+        ))));
+    }
+
     pub fn begin_open_document(&mut self) {
         self.queue.push(Async(BeginOpenDocument));
     }
@@ -59,6 +72,33 @@ impl CommandBuffer {
             .push(Sync(App(FocusDocument(document.source.to_owned()))));
     }
 
+    pub fn reorder_document(&mut self, document: &Document, new_index: usize) {
+        self.queue.push(Sync(App(ReorderDocument(
+            document.source.to_owned(),
+            new_index,
+        ))));
+    }
+
+    pub fn request_revert_document(&mut self, document: &Document) {
+        self.queue
+            .push(Sync(App(RequestRevertDocument(document.source.to_owned()))));
+    }
+
+    pub fn revert_document<T: AsRef<Path>>(&mut self, path: T) {
+        self.queue
+            .push(Sync(App(RevertDocument(path.as_ref().to_owned()))));
+    }
+
+    pub fn cancel_revert_document(&mut self) {
+        self.queue.push(Sync(App(CancelRevertDocument)));
+    }
+
+    pub fn set_preferences(&mut self, preferences: &Preferences) {
+        self.queue
+            .push(Sync(App(SetPreferences(preferences.clone()))));
+        self.queue.push(Async(SavePreferences(preferences.clone())));
+    }
+
     pub fn close_current_document(&mut self) {
         self.queue.push(Sync(App(CloseCurrentDocument)));
     }
@@ -67,22 +107,67 @@ impl CommandBuffer {
         self.queue.push(Sync(App(CloseAllDocuments)));
     }
 
-    pub fn save<T: AsRef<Path>>(&mut self, path: T, sheet: &Sheet, version: i32) {
+    pub fn close_without_saving(&mut self) {
+        self.queue.push(Sync(App(CloseWithoutSaving)));
+    }
+
+    pub fn discard_all_on_close(&mut self) {
+        self.queue.push(Sync(App(DiscardAllOnClose)));
+    }
+
+    pub fn cancel_close(&mut self) {
+        self.queue.push(Sync(App(CancelClose)));
+    }
+
+    pub fn save<T: AsRef<Path>>(
+        &mut self,
+        path: T,
+        sheet: &Sheet,
+        version: i32,
+        absolute_paths: bool,
+    ) {
         self.queue.push(Async(Save(
             path.as_ref().to_owned(),
             sheet.clone(),
             version,
+            absolute_paths,
         )));
     }
 
-    pub fn save_as<T: AsRef<Path>>(&mut self, path: T, sheet: &Sheet, version: i32) {
+    pub fn save_as<T: AsRef<Path>>(
+        &mut self,
+        path: T,
+        sheet: &Sheet,
+        version: i32,
+        absolute_paths: bool,
+    ) {
         self.queue.push(Async(SaveAs(
             path.as_ref().to_owned(),
             sheet.clone(),
             version,
+            absolute_paths,
         )));
     }
 
+    pub fn request_save_without_validation(&mut self, invalid_save: InvalidSave) {
+        self.queue
+            .push(Sync(App(RequestSaveWithoutValidation(invalid_save))));
+    }
+
+    pub fn save_without_validation(&mut self, invalid_save: &InvalidSave) {
+        self.queue.push(Sync(App(ConfirmSaveWithoutValidation)));
+        self.queue.push(Async(SaveWithoutValidation(
+            invalid_save.path.clone(),
+            invalid_save.sheet.clone(),
+            invalid_save.version,
+            invalid_save.absolute_paths,
+        )));
+    }
+
+    pub fn cancel_save_without_validation(&mut self) {
+        self.queue.push(Sync(App(CancelSaveWithoutValidation)));
+    }
+
     pub fn mark_as_saved<T: AsRef<Path>>(&mut self, path: T, version: i32) {
         self.queue.push(Sync(Document(MarkAsSaved(
             path.as_ref().to_owned(),
@@ -171,28 +256,285 @@ impl CommandBuffer {
         ))));
     }
 
+    pub fn set_export_atlas_size(&mut self, atlas_size: Option<(u32, u32)>) {
+        self.queue
+            .push(Sync(Document(SetExportAtlasSize(atlas_size))));
+    }
+
+    pub fn set_export_max_size(&mut self, max_size: Option<(u32, u32)>) {
+        self.queue.push(Sync(Document(SetExportMaxSize(max_size))));
+    }
+
+    pub fn set_export_sort_frames_by_size(&mut self, sort: bool) {
+        self.queue
+            .push(Sync(Document(SetExportSortFramesBySize(sort))));
+    }
+
+    pub fn set_export_padding(&mut self, padding: u32) {
+        self.queue.push(Sync(Document(SetExportPadding(padding))));
+    }
+
+    pub fn set_export_extrude(&mut self, extrude: u32) {
+        self.queue.push(Sync(Document(SetExportExtrude(extrude))));
+    }
+
+    pub fn add_export_template_variable(&mut self) {
+        self.queue.push(Sync(Document(AddExportTemplateVariable)));
+    }
+
+    pub fn set_export_template_variable(&mut self, index: usize, variable: TemplateVariable) {
+        self.queue
+            .push(Sync(Document(SetExportTemplateVariable(index, variable))));
+    }
+
+    pub fn remove_export_template_variable(&mut self, index: usize) {
+        self.queue
+            .push(Sync(Document(RemoveExportTemplateVariable(index))));
+    }
+
+    pub fn set_export_pack_strategy(&mut self, strategy: PackStrategy) {
+        self.queue
+            .push(Sync(Document(SetExportPackStrategy(strategy))));
+    }
+
+    pub fn set_export_absolute_image_paths(&mut self, absolute_image_paths: bool) {
+        self.queue.push(Sync(Document(SetExportAbsoluteImagePaths(
+            absolute_image_paths,
+        ))));
+    }
+
+    pub fn set_export_skip_texture(&mut self, skip_texture: bool) {
+        self.queue
+            .push(Sync(Document(SetExportSkipTexture(skip_texture))));
+    }
+
     pub fn cancel_export_as(&mut self) {
         self.queue.push(Sync(Document(CancelExportAs)));
     }
 
-    pub fn end_export_as(&mut self, sheet: &Sheet) {
+    pub fn end_export_as(&mut self, document: &Document) {
         self.queue.push(Sync(Document(EndExportAs)));
-        self.queue.push(Async(Export(sheet.clone())));
+        let mut sheet = document.sheet.clone();
+        if let Some(settings) = &document.persistent.export_settings_edit {
+            sheet.set_export_settings(settings.clone());
+        }
+        self.export_sheet(document, sheet);
+    }
+
+    // Packs the frames of `document` with `settings` without writing anything
+    pub fn estimate_packing(
+        &mut self,
+        document: &crate::state::Document,
+        settings: &ExportSettings,
+    ) {
+        self.queue.push(Async(EstimatePacking(
+            document.source.to_owned(),
+            document.sheet.clone(),
+            settings.into(),
+            document.get_version(),
+        )));
+    }
+
+    pub fn end_estimate_packing<T: AsRef<Path>>(
+        &mut self,
+        document_path: T,
+        estimate: PackEstimate,
+    ) {
+        self.queue.push(Sync(Document(EndEstimatePacking(
+            document_path.as_ref().to_owned(),
+            estimate,
+        ))));
+    }
+
+    pub fn validate_export(&mut self, document: &Document) {
+        self.queue.push(Async(ValidateExport(
+            document.source.to_owned(),
+            document.sheet.clone(),
+        )));
+    }
+
+    pub fn end_validate_export<T: AsRef<Path>>(
+        &mut self,
+        document_path: T,
+        validation: ExportValidation,
+    ) {
+        self.queue.push(Sync(Document(EndValidateExport(
+            document_path.as_ref().to_owned(),
+            validation,
+        ))));
+    }
+
+    pub fn dismiss_export_validation(&mut self) {
+        self.queue.push(Sync(Document(DismissExportValidation)));
+    }
+
+    pub fn export(&mut self, document: &Document) {
+        self.export_sheet(document, document.sheet.clone());
+    }
+
+    // Existing files the document did not export are only overwritten once the user agrees
+    fn export_sheet(&mut self, document: &Document, sheet: Sheet) {
+        let source = document.source.to_owned();
+        let conflicts = document.get_export_overwrite_conflicts(&sheet);
+        if conflicts.is_empty() {
+            self.queue
+                .push(Sync(Document(RecordExportDestinations(source))));
+            self.queue.push(Async(Export(sheet)));
+        } else {
+            self.queue
+                .push(Sync(Document(BeginExportOverwrite(source, conflicts))));
+        }
+    }
+
+    pub fn end_export_overwrite(&mut self, document: &Document, always_overwrite: bool) {
+        self.queue.push(Sync(Document(EndExportOverwrite(
+            document.source.to_owned(),
+            always_overwrite,
+        ))));
+        self.queue.push(Async(Export(document.sheet.clone())));
+    }
+
+    pub fn cancel_export_overwrite(&mut self, document: &Document) {
+        self.queue.push(Sync(Document(CancelExportOverwrite(
+            document.source.to_owned(),
+        ))));
     }
 
-    pub fn export(&mut self, sheet: &Sheet) {
-        self.queue.push(Async(Export(sheet.clone())));
+    pub fn update_export_progress(&mut self, progress: ExportProgress) {
+        self.queue.push(Sync(App(UpdateExportProgress(progress))));
+    }
+
+    pub fn end_export(&mut self) {
+        self.queue.push(Sync(App(EndExport)));
+    }
+
+    pub fn cancel_export(&mut self) {
+        self.queue.push(Sync(App(CancelExport)));
+    }
+
+    pub fn show_error(&mut self, error: UserFacingError) {
+        self.queue.push(Sync(App(ShowError(error))));
+    }
+
+    pub fn dismiss_error(&mut self) {
+        self.queue.push(Sync(App(DismissError)));
+    }
+
+    // Documents export separately so a failure does not prevent the others from exporting.
+    // Their errors and the documents skipped for lack of settings are shown together.
+    pub fn export_all<'a, I: Iterator<Item = &'a Document>>(&mut self, documents: I) {
+        let mut skipped_documents = vec![];
+        for document in documents {
+            if document.sheet.get_export_settings().is_some() {
+                self.export(document);
+            } else {
+                skipped_documents.push(document.source.to_string_lossy().into_owned());
+            }
+        }
+        if !skipped_documents.is_empty() {
+            let error = StateError::DocumentsWithoutExportSettings(skipped_documents.join(", "));
+            self.show_error(UserFacingError {
+                message: error.to_string(),
+            });
+        }
+    }
+
+    pub fn autosave<T: AsRef<Path>>(&mut self, path: T, sheet: &Sheet, absolute_paths: bool) {
+        self.queue.push(Async(Autosave(
+            path.as_ref().to_owned(),
+            sheet.clone(),
+            absolute_paths,
+        )));
+    }
+
+    pub fn delete_autosave<T: AsRef<Path>>(&mut self, path: T) {
+        self.queue
+            .push(Async(DeleteAutosave(path.as_ref().to_owned())));
+    }
+
+    pub fn begin_export_animation_gif(&mut self, sheet: &Sheet, animation: &Animation) {
+        self.queue.push(Async(BeginExportAnimationGif(
+            sheet.clone(),
+            animation.get_name().to_owned(),
+        )));
+    }
+
+    pub fn copy_frame_rectangle(&mut self, sheet: &Sheet, frame: &Frame) {
+        self.queue.push(Async(CopyFrameRectangle(
+            sheet.clone(),
+            frame.get_source().to_owned(),
+        )));
     }
 
     pub fn switch_to_content_tab(&mut self, tab: ContentTab) {
         self.queue.push(Sync(Document(SwitchToContentTab(tab))));
     }
 
+    pub fn set_animation_tag_filter(&mut self, tag: Option<String>) {
+        self.queue.push(Sync(Document(SetAnimationTagFilter(tag))));
+    }
+
     pub fn import(&mut self, document: &crate::state::Document) {
         self.queue
             .push(Async(BeginImport(document.source.to_owned())));
     }
 
+    pub fn import_folder(&mut self, document: &crate::state::Document) {
+        self.queue.push(Async(BeginImportFolder(
+            document.source.to_owned(),
+            document.sheet.clone(),
+        )));
+    }
+
+    pub fn import_grid(&mut self, document: &crate::state::Document) {
+        self.queue
+            .push(Async(BeginImportGrid(document.source.to_owned())));
+    }
+
+    pub fn import_aseprite(&mut self, document: &crate::state::Document) {
+        self.queue
+            .push(Async(BeginImportAseprite(document.source.to_owned())));
+    }
+
+    pub fn end_import_aseprite<T: AsRef<Path>>(
+        &mut self,
+        into: T,
+        frames: Vec<PathBuf>,
+        animations: Vec<Animation>,
+    ) {
+        self.queue.push(Sync(Document(EndImportAseprite(
+            into.as_ref().to_owned(),
+            frames,
+            animations,
+        ))));
+    }
+
+    pub fn end_select_grid_image<T: AsRef<Path>, U: AsRef<Path>>(
+        &mut self,
+        into: T,
+        image: U,
+        image_size: Vector2D<u32>,
+    ) {
+        self.queue.push(Sync(Document(EndSelectGridImage(
+            into.as_ref().to_owned(),
+            image.as_ref().to_owned(),
+            image_size,
+        ))));
+    }
+
+    pub fn set_grid_import_tile_size(&mut self, tile_size: Vector2D<u32>) {
+        self.queue
+            .push(Sync(Document(SetGridImportTileSize(tile_size))));
+    }
+
+    pub fn end_grid_import(&mut self) {
+        self.queue.push(Sync(Document(EndGridImport)));
+    }
+
+    pub fn cancel_grid_import(&mut self) {
+        self.queue.push(Sync(Document(CancelGridImport)));
+    }
+
     pub fn end_import<T: AsRef<Path>, U: AsRef<Path>>(&mut self, into: T, path: U) {
         self.queue.push(Sync(Document(EndImport(
             into.as_ref().to_owned(),
@@ -204,11 +546,29 @@ impl CommandBuffer {
         self.queue.push(Sync(Document(ClearSelection)));
     }
 
+    pub fn select_all(&mut self) {
+        self.queue.push(Sync(Document(SelectAll)));
+    }
+
+    pub fn invert_selection(&mut self) {
+        self.queue.push(Sync(Document(InvertSelection)));
+    }
+
     pub fn select_frame(&mut self, frame: &Frame) {
         self.queue
             .push(Sync(Document(SelectFrame(frame.get_source().to_owned()))));
     }
 
+    pub fn toggle_frame_selection(&mut self, frame: &Frame) {
+        self.queue.push(Sync(Document(ToggleFrameSelection(
+            frame.get_source().to_owned(),
+        ))));
+    }
+
+    pub fn select_frame_range(&mut self, frames: Vec<PathBuf>) {
+        self.queue.push(Sync(Document(SelectFrameRange(frames))));
+    }
+
     pub fn select_animation(&mut self, animation: &Animation) {
         self.queue.push(Sync(Document(SelectAnimation(
             animation.get_name().to_owned(),
@@ -220,6 +580,16 @@ impl CommandBuffer {
             .push(Sync(Document(SelectHitbox(hitbox.get_name().to_owned()))));
     }
 
+    pub fn toggle_hitbox_selection(&mut self, hitbox: &Hitbox) {
+        self.queue.push(Sync(Document(ToggleHitboxSelection(
+            hitbox.get_name().to_owned(),
+        ))));
+    }
+
+    pub fn select_hitbox_range(&mut self, hitboxes: Vec<String>) {
+        self.queue.push(Sync(Document(SelectHitboxRange(hitboxes))));
+    }
+
     pub fn select_animation_frame(&mut self, animation_frame_index: usize) {
         self.queue
             .push(Sync(Document(SelectAnimationFrame(animation_frame_index))));
@@ -244,8 +614,8 @@ impl CommandBuffer {
         ))));
     }
 
-    pub fn create_animation(&mut self) {
-        self.queue.push(Sync(Document(CreateAnimation)));
+    pub fn create_animation(&mut self, is_looping: bool) {
+        self.queue.push(Sync(Document(CreateAnimation(is_looping))));
     }
 
     pub fn begin_frame_drag(&mut self, frame: &Frame) {
@@ -258,6 +628,10 @@ impl CommandBuffer {
         self.queue.push(Sync(Document(EndFrameDrag)));
     }
 
+    pub fn insert_frames_at_playhead(&mut self) {
+        self.queue.push(Sync(Document(InsertFramesAtPlayhead)));
+    }
+
     pub fn insert_animation_frame_before<T: AsRef<Path>>(
         &mut self,
         frame: T,
@@ -312,11 +686,13 @@ impl CommandBuffer {
         &mut self,
         mouse_delta: Vector2D<f32>,
         both_axis: bool,
+        allow_snapping: bool,
     ) {
         self.queue
             .push(Sync(Document(UpdateAnimationFrameOffsetDrag(
                 mouse_delta,
                 both_axis,
+                allow_snapping,
             ))));
     }
 
@@ -332,6 +708,19 @@ impl CommandBuffer {
         self.queue.push(Sync(Document(WorkbenchZoomOut)));
     }
 
+    pub fn workbench_zoom_in_at(&mut self, cursor: Vector2D<f32>) {
+        self.queue.push(Sync(Document(WorkbenchZoomInAt(cursor))));
+    }
+
+    pub fn workbench_zoom_out_at(&mut self, cursor: Vector2D<f32>) {
+        self.queue.push(Sync(Document(WorkbenchZoomOutAt(cursor))));
+    }
+
+    pub fn workbench_fit(&mut self, bounds: Rect<f32>, workbench_size: Vector2D<f32>) {
+        self.queue
+            .push(Sync(Document(WorkbenchFit(bounds, workbench_size))));
+    }
+
     pub fn workbench_reset_zoom(&mut self) {
         self.queue.push(Sync(Document(WorkbenchResetZoom)));
     }
@@ -340,10 +729,46 @@ impl CommandBuffer {
         self.queue.push(Sync(Document(WorkbenchCenter)));
     }
 
+    pub fn copy_selection(&mut self) {
+        self.queue.push(Sync(App(CopySelection)));
+    }
+
+    pub fn paste(&mut self) {
+        self.queue.push(Sync(App(PasteClipboard)));
+    }
+
+    pub fn reload_textures(&mut self) {
+        self.queue.push(Sync(App(ReloadTextures)));
+    }
+
+    pub fn toggle_onion_skin(&mut self) {
+        self.queue.push(Sync(Document(ToggleOnionSkin)));
+    }
+
+    pub fn toggle_snap_to_grid(&mut self) {
+        self.queue.push(Sync(Document(ToggleSnapToGrid)));
+    }
+
+    pub fn toggle_animation_hitboxes(&mut self) {
+        self.queue.push(Sync(Document(ToggleAnimationHitboxes)));
+    }
+
+    pub fn set_snap_grid_size(&mut self, grid_size: u32) {
+        self.queue.push(Sync(Document(SetSnapGridSize(grid_size))));
+    }
+
     pub fn pan(&mut self, delta: Vector2D<f32>) {
         self.queue.push(Sync(Document(Pan(delta))));
     }
 
+    pub fn duplicate_hitbox(&mut self) {
+        self.queue.push(Sync(Document(DuplicateHitbox)));
+    }
+
+    pub fn mirror_hitbox(&mut self, frame_size: Vector2D<u32>) {
+        self.queue.push(Sync(Document(MirrorHitbox(frame_size))));
+    }
+
     pub fn create_hitbox(&mut self, mouse_position: Vector2D<f32>) {
         self.queue
             .push(Sync(Document(CreateHitbox(mouse_position))));
@@ -356,10 +781,16 @@ impl CommandBuffer {
         ))));
     }
 
-    pub fn update_hitbox_scale(&mut self, mouse_delta: Vector2D<f32>, preserve_aspect_ratio: bool) {
+    pub fn update_hitbox_scale(
+        &mut self,
+        mouse_delta: Vector2D<f32>,
+        preserve_aspect_ratio: bool,
+        allow_snapping: bool,
+    ) {
         self.queue.push(Sync(Document(UpdateHitboxScale(
             mouse_delta,
             preserve_aspect_ratio,
+            allow_snapping,
         ))));
     }
 
@@ -373,19 +804,52 @@ impl CommandBuffer {
         ))));
     }
 
-    pub fn update_hitbox_drag(&mut self, mouse_delta: Vector2D<f32>, both_axis: bool) {
-        self.queue
-            .push(Sync(Document(UpdateHitboxDrag(mouse_delta, both_axis))));
+    pub fn update_hitbox_drag(
+        &mut self,
+        mouse_delta: Vector2D<f32>,
+        both_axis: bool,
+        allow_snapping: bool,
+    ) {
+        self.queue.push(Sync(Document(UpdateHitboxDrag(
+            mouse_delta,
+            both_axis,
+            allow_snapping,
+        ))));
     }
 
     pub fn end_hitbox_drag(&mut self) {
         self.queue.push(Sync(Document(EndHitboxDrag)));
     }
 
+    pub fn begin_anchor_drag(&mut self) {
+        self.queue.push(Sync(Document(BeginAnchorDrag)));
+    }
+
+    pub fn update_anchor_drag(&mut self, mouse_delta: Vector2D<f32>, both_axis: bool) {
+        self.queue
+            .push(Sync(Document(UpdateAnchorDrag(mouse_delta, both_axis))));
+    }
+
+    pub fn end_anchor_drag(&mut self) {
+        self.queue.push(Sync(Document(EndAnchorDrag)));
+    }
+
+    pub fn toggle_hitbox_shape(&mut self) {
+        self.queue.push(Sync(Document(ToggleHitboxShape)));
+    }
+
     pub fn toggle_playback(&mut self) {
         self.queue.push(Sync(Document(TogglePlayback)));
     }
 
+    pub fn toggle_playback_direction(&mut self) {
+        self.queue.push(Sync(Document(TogglePlaybackDirection)));
+    }
+
+    pub fn set_playback_speed(&mut self, speed: f32) {
+        self.queue.push(Sync(Document(SetPlaybackSpeed(speed))));
+    }
+
     pub fn snap_to_previous_frame(&mut self) {
         self.queue.push(Sync(Document(SnapToPreviousFrame)));
     }
@@ -394,8 +858,31 @@ impl CommandBuffer {
         self.queue.push(Sync(Document(SnapToNextFrame)));
     }
 
-    pub fn toggle_looping(&mut self) {
-        self.queue.push(Sync(Document(ToggleLooping)));
+    pub fn step_timeline_clock_backward(&mut self) {
+        self.queue
+            .push(Sync(Document(StepTimelineClock(-TIMELINE_CLOCK_STEP_MS))));
+    }
+
+    pub fn step_timeline_clock_forward(&mut self) {
+        self.queue
+            .push(Sync(Document(StepTimelineClock(TIMELINE_CLOCK_STEP_MS))));
+    }
+
+    pub fn step_to_previous_keyframe(&mut self) {
+        self.queue.push(Sync(Document(StepKeyframe(-1))));
+    }
+
+    pub fn step_to_next_keyframe(&mut self) {
+        self.queue.push(Sync(Document(StepKeyframe(1))));
+    }
+
+    pub fn set_loop_mode(&mut self, loop_mode: LoopMode) {
+        self.queue.push(Sync(Document(SetLoopMode(loop_mode))));
+    }
+
+    pub fn set_animation_frame_rate(&mut self, frame_rate: Option<f32>) {
+        self.queue
+            .push(Sync(Document(SetAnimationFrameRate(frame_rate))));
     }
 
     pub fn timeline_zoom_in(&mut self) {
@@ -422,6 +909,51 @@ impl CommandBuffer {
         self.queue.push(Sync(Document(EndScrub)));
     }
 
+    pub fn set_loop_in(&mut self) {
+        self.queue.push(Sync(Document(SetLoopIn)));
+    }
+
+    pub fn set_loop_out(&mut self) {
+        self.queue.push(Sync(Document(SetLoopOut)));
+    }
+
+    pub fn clear_loop_region(&mut self) {
+        self.queue.push(Sync(Document(ClearLoopRegion)));
+    }
+
+    pub fn begin_loop_marker_drag(&mut self, marker: LoopMarker) {
+        self.queue.push(Sync(Document(BeginLoopMarkerDrag(marker))));
+    }
+
+    pub fn update_loop_marker_drag(&mut self, new_time: Duration) {
+        self.queue
+            .push(Sync(Document(UpdateLoopMarkerDrag(new_time))));
+    }
+
+    pub fn end_loop_marker_drag(&mut self) {
+        self.queue.push(Sync(Document(EndLoopMarkerDrag)));
+    }
+
+    pub fn begin_workbench_scrub(&mut self) {
+        self.queue.push(Sync(Document(BeginWorkbenchScrub)));
+    }
+
+    pub fn end_workbench_scrub(&mut self) {
+        self.queue.push(Sync(Document(EndWorkbenchScrub)));
+    }
+
+    pub fn begin_ruler(&mut self, start: Vector2D<i32>) {
+        self.queue.push(Sync(Document(BeginRuler(start))));
+    }
+
+    pub fn update_ruler(&mut self, end: Vector2D<i32>) {
+        self.queue.push(Sync(Document(UpdateRuler(end))));
+    }
+
+    pub fn end_ruler(&mut self) {
+        self.queue.push(Sync(Document(EndRuler)));
+    }
+
     pub fn nudge_selection_left(&mut self, large: bool) {
         self.queue
             .push(Sync(Document(NudgeSelection(vec2(-1, 0), large))));
@@ -442,6 +974,46 @@ impl CommandBuffer {
             .push(Sync(Document(NudgeSelection(vec2(0, 1), large))));
     }
 
+    pub fn set_selection_position(&mut self, position: Vector2D<i32>) {
+        self.queue
+            .push(Sync(Document(SetSelectionPosition(position))));
+    }
+
+    pub fn set_animation_frame_duration(&mut self, duration: u32) {
+        self.queue
+            .push(Sync(Document(SetAnimationFrameDuration(duration))));
+    }
+
+    pub fn set_all_keyframe_durations(&mut self, duration: u32) {
+        self.queue
+            .push(Sync(Document(SetAllKeyframeDurations(duration))));
+    }
+
+    pub fn reverse_animation(&mut self) {
+        self.queue.push(Sync(Document(ReverseAnimation)));
+    }
+
+    pub fn set_hitbox_size(&mut self, size: Vector2D<u32>) {
+        self.queue.push(Sync(Document(SetHitboxSize(size))));
+    }
+
+    pub fn flip_selection_horizontally(&mut self) {
+        self.queue.push(Sync(Document(FlipSelection(true, false))));
+    }
+
+    pub fn flip_selection_vertically(&mut self) {
+        self.queue.push(Sync(Document(FlipSelection(false, true))));
+    }
+
+    pub fn align_hitboxes(&mut self, alignment: HitboxAlignment) {
+        self.queue.push(Sync(Document(AlignHitboxes(alignment))));
+    }
+
+    pub fn distribute_hitboxes(&mut self, distribution: HitboxDistribution) {
+        self.queue
+            .push(Sync(Document(DistributeHitboxes(distribution))));
+    }
+
     pub fn delete_selection(&mut self) {
         self.queue.push(Sync(Document(DeleteSelection)));
     }
@@ -460,6 +1032,104 @@ impl CommandBuffer {
         self.queue.push(Sync(Document(EndRenameSelection)));
     }
 
+    pub fn begin_set_animation_group(&mut self) {
+        self.queue.push(Sync(Document(BeginSetAnimationGroup)));
+    }
+
+    pub fn begin_set_animation_direction(&mut self) {
+        self.queue.push(Sync(Document(BeginSetAnimationDirection)));
+    }
+
+    pub fn begin_add_animation_tag(&mut self) {
+        self.queue.push(Sync(Document(BeginAddAnimationTag)));
+    }
+
+    pub fn remove_animation_tag<T: AsRef<str>>(&mut self, tag: T) {
+        self.queue
+            .push(Sync(Document(RemoveAnimationTag(tag.as_ref().to_owned()))));
+    }
+
+    pub fn begin_set_animation_frame_sound(&mut self) {
+        self.queue.push(Sync(Document(BeginSetAnimationFrameSound)));
+    }
+
+    pub fn begin_set_animation_frame_user_data(&mut self) {
+        self.queue
+            .push(Sync(Document(BeginSetAnimationFrameUserData)));
+    }
+
+    // Keyframe offsets only follow the new frame size when both sizes are known
+    pub fn refresh_frame(&mut self, frame: &Frame, keep_position_from: Option<Vector2D<u32>>) {
+        let new_size = match frame.get_source_rect() {
+            Some(r) => Some(vec2(r.size.width, r.size.height)),
+            None => image::image_dimensions(frame.get_image())
+                .ok()
+                .map(|(w, h)| vec2(w, h)),
+        };
+        let resize = keep_position_from.and_then(|old_size| new_size.map(|n| (old_size, n)));
+        self.queue.push(Sync(Document(RefreshFrame(
+            frame.get_source().to_owned(),
+            resize,
+        ))));
+    }
+
+    pub fn begin_build_animations_from_frame_names(&mut self) {
+        self.queue
+            .push(Sync(Document(BeginBuildAnimationsFromFrameNames)));
+    }
+
+    pub fn end_build_animations_from_frame_names(&mut self, is_looping: bool) {
+        self.queue
+            .push(Sync(Document(EndBuildAnimationsFromFrameNames(is_looping))));
+    }
+
+    pub fn cancel_build_animations_from_frame_names(&mut self) {
+        self.queue
+            .push(Sync(Document(CancelBuildAnimationsFromFrameNames)));
+    }
+
+    pub fn begin_merge_duplicate_animations(&mut self) {
+        self.queue
+            .push(Sync(Document(BeginMergeDuplicateAnimations)));
+    }
+
+    pub fn end_merge_duplicate_animations(&mut self) {
+        self.queue.push(Sync(Document(EndMergeDuplicateAnimations)));
+    }
+
+    pub fn end_recover_autosave(&mut self) {
+        self.queue.push(Sync(Document(EndRecoverAutosave)));
+    }
+
+    pub fn cancel_recover_autosave(&mut self) {
+        self.queue.push(Sync(Document(CancelRecoverAutosave)));
+    }
+
+    pub fn begin_relink_frames(&mut self, document: &crate::state::Document) {
+        self.queue
+            .push(Async(BeginRelinkFrames(document.source.to_owned())));
+    }
+
+    pub fn end_relink_frames<T: AsRef<Path>>(
+        &mut self,
+        document_path: T,
+        candidates: Vec<PathBuf>,
+    ) {
+        self.queue.push(Sync(Document(EndRelinkFrames(
+            document_path.as_ref().to_owned(),
+            candidates,
+        ))));
+    }
+
+    pub fn cancel_relink_frames(&mut self) {
+        self.queue.push(Sync(Document(CancelRelinkFrames)));
+    }
+
+    pub fn cancel_merge_duplicate_animations(&mut self) {
+        self.queue
+            .push(Sync(Document(CancelMergeDuplicateAnimations)));
+    }
+
     pub fn exit(&mut self) {
         self.queue.push(Sync(App(Exit)));
     }
@@ -1,26 +1,139 @@
 use euclid::*;
 use failure::Error;
+use std::collections::BTreeMap;
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
+use crate::export::{validate_export_settings, PackSettings, PackStats};
+use crate::sheet::constants::*;
 use crate::sheet::*;
 use crate::state::*;
 
+const MIN_PLAYBACK_SPEED: f32 = 0.25;
+const MAX_PLAYBACK_SPEED: f32 = 4.0;
+const KEYFRAME_ORIGIN_SNAP_DISTANCE: f32 = 6.0; // TODO DPI?
+
 #[derive(Clone, Debug, Default)]
 struct HistoryEntry {
-    last_command: Option<DocumentCommand>,
+    last_command_label: Option<String>,
     sheet: Sheet,
     view: View,
     version: i32,
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationProposal {
+    pub name: String,
+    pub frames: Vec<PathBuf>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationDuplicate {
+    pub name: String,
+    pub original: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClipboardContent {
+    Frames(Vec<Frame>),
+    Animation(Animation),
+    Hitboxes(Vec<Hitbox>),
+    AnimationFrames(Vec<AnimationFrame>),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GridImport {
+    pub image: PathBuf,
+    pub image_size: Vector2D<u32>,
+    pub tile_size: Vector2D<u32>,
+}
+
+// Only describes the sheet version and settings it was computed for
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackEstimate {
+    pub version: i32,
+    pub settings: PackSettings,
+    pub stats: PackStats,
+}
+
+// Outcome of an export which ran without writing any file
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ExportValidation {
+    pub stats: Option<PackStats>,
+    pub num_frames_packed: u32,
+    pub error: Option<String>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HitboxAlignment {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    HorizontalCenter,
+    VerticalCenter,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HitboxDistribution {
+    Horizontal,
+    Vertical,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlaybackDirection {
+    Forward,
+    Backward,
+}
+
+#[derive(Clone, Debug)]
 pub struct Persistent {
     pub export_settings_edit: Option<ExportSettings>,
+    pub export_settings_error: Option<String>,
+    pub animation_proposals: Option<Vec<AnimationProposal>>,
+    pub animation_duplicates: Option<Vec<AnimationDuplicate>>,
+    pub autosave_recovery: Option<Sheet>,
+    pub missing_images: Option<Vec<PathBuf>>,
+    pub pack_estimate: Option<PackEstimate>,
+    pub export_validation: Option<ExportValidation>,
+    pub grid_import: Option<GridImport>,
+    // Only covers the current session, files exported before the document was opened count as
+    // foreign files the user is asked about
+    pub exported_files: Vec<PathBuf>,
+    pub export_overwrite_conflicts: Option<Vec<PathBuf>>,
+    pub allow_export_overwrites: bool,
+    pub playback_speed: f32,
+    pub playback_direction: PlaybackDirection,
+    ping_pong_returning: bool, // Set while a ping-pong animation plays opposite to playback_direction
     timeline_is_playing: bool,
     disk_version: i32,
 }
 
+impl Default for Persistent {
+    fn default() -> Persistent {
+        Persistent {
+            export_settings_edit: None,
+            export_settings_error: None,
+            animation_proposals: None,
+            animation_duplicates: None,
+            autosave_recovery: None,
+            missing_images: None,
+            pack_estimate: None,
+            export_validation: None,
+            grid_import: None,
+            exported_files: Vec::new(),
+            export_overwrite_conflicts: None,
+            allow_export_overwrites: false,
+            playback_speed: 1.0,
+            playback_direction: PlaybackDirection::Forward,
+            ping_pong_returning: false,
+            timeline_is_playing: false,
+            disk_version: 0,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Document {
     pub source: PathBuf,
@@ -33,6 +146,107 @@ pub struct Document {
     history_index: usize,
 }
 
+fn is_newer<T: AsRef<Path>, U: AsRef<Path>>(path: T, than: U) -> bool {
+    let modified = |p: &Path| fs::metadata(p).and_then(|m| m.modified()).ok();
+    match (modified(path.as_ref()), modified(than.as_ref())) {
+        (Some(a), Some(b)) => a > b,
+        _ => false,
+    }
+}
+
+// Only the first page destination is known before packing
+fn get_export_destinations(sheet: &Sheet) -> Vec<PathBuf> {
+    match sheet.get_export_settings() {
+        Some(s) if s.skip_texture => vec![s.metadata_destination.clone()],
+        Some(s) => vec![
+            s.texture_destination.clone(),
+            s.metadata_destination.clone(),
+        ],
+        None => Vec::new(),
+    }
+}
+
+// Splits a file name like `walk_12` into `walk` and 12
+fn split_numeric_suffix(name: &str) -> Option<(&str, u32)> {
+    let prefix = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    let number = name[prefix.len()..].parse().ok()?;
+    let prefix = prefix.trim_end_matches(|c| c == '_' || c == '-' || c == '.' || c == ' ');
+    if prefix.is_empty() {
+        None
+    } else {
+        Some((prefix, number))
+    }
+}
+
+// Aligns rectangles against the edges or center of their bounding box
+fn align_rectangles(
+    rectangles: &[(String, Rect<i32>)],
+    alignment: HitboxAlignment,
+) -> Vec<(String, Vector2D<i32>)> {
+    let min_x = rectangles.iter().map(|(_, r)| r.min_x()).min().unwrap_or(0);
+    let max_x = rectangles.iter().map(|(_, r)| r.max_x()).max().unwrap_or(0);
+    let min_y = rectangles.iter().map(|(_, r)| r.min_y()).min().unwrap_or(0);
+    let max_y = rectangles.iter().map(|(_, r)| r.max_y()).max().unwrap_or(0);
+    rectangles
+        .iter()
+        .map(|(name, r)| {
+            let position = match alignment {
+                HitboxAlignment::Left => vec2(min_x, r.min_y()),
+                HitboxAlignment::Right => vec2(max_x - r.size.width, r.min_y()),
+                HitboxAlignment::Top => vec2(r.min_x(), min_y),
+                HitboxAlignment::Bottom => vec2(r.min_x(), max_y - r.size.height),
+                HitboxAlignment::HorizontalCenter => {
+                    vec2((min_x + max_x) / 2 - r.size.width / 2, r.min_y())
+                }
+                HitboxAlignment::VerticalCenter => {
+                    vec2(r.min_x(), (min_y + max_y) / 2 - r.size.height / 2)
+                }
+            };
+            (name.clone(), position)
+        })
+        .collect()
+}
+
+// Spaces rectangles so the gaps between them are equal, the outermost ones do not move
+fn distribute_rectangles(
+    rectangles: &[(String, Rect<i32>)],
+    distribution: HitboxDistribution,
+) -> Vec<(String, Vector2D<i32>)> {
+    let horizontal = distribution == HitboxDistribution::Horizontal;
+    let extent = |r: &Rect<i32>| {
+        if horizontal {
+            (r.min_x(), r.size.width)
+        } else {
+            (r.min_y(), r.size.height)
+        }
+    };
+    let mut sorted: Vec<&(String, Rect<i32>)> = rectangles.iter().collect();
+    sorted.sort_by_key(|(_, r)| extent(r).0);
+    if sorted.len() < 2 {
+        return Vec::new();
+    }
+
+    let (first_start, _) = extent(&sorted[0].1);
+    let (last_start, last_length) = extent(&sorted[sorted.len() - 1].1);
+    let total_length: i32 = sorted.iter().map(|(_, r)| extent(r).1).sum();
+    let total_gap = last_start + last_length - first_start - total_length;
+    let num_gaps = sorted.len() as i32 - 1;
+
+    let mut cursor = first_start;
+    let mut positions = Vec::new();
+    for (index, (name, r)) in sorted.iter().enumerate() {
+        let start = cursor + total_gap * index as i32 / num_gaps;
+        let position = if horizontal {
+            vec2(start, r.min_y())
+        } else {
+            vec2(r.min_x(), start)
+        };
+        positions.push((name.clone(), position));
+        cursor += extent(r).1;
+    }
+    positions
+}
+
 impl Document {
     pub fn new<T: AsRef<Path>>(path: T) -> Document {
         let history_entry: HistoryEntry = Default::default();
@@ -48,24 +262,75 @@ impl Document {
         }
     }
 
+    pub fn from_template<T: AsRef<Path>>(path: T, sheet: Sheet) -> Document {
+        let mut document = Document::new(&path);
+        document.sheet = sheet;
+        document.history[0].sheet = document.sheet.clone();
+        document
+    }
+
     pub fn open<T: AsRef<Path>>(path: T) -> Result<Document, Error> {
         let mut document = Document::new(&path);
+        document.sheet = Sheet::open(&path)?;
 
-        let mut directory = path.as_ref().to_owned();
-        directory.pop();
-        let sheet: Sheet = compat::read_sheet(path.as_ref())?;
-        document.sheet = sheet.with_absolute_paths(&directory)?;
+        match read_saved_view(&path) {
+            Ok(Some(saved_view)) => document.view.restore(&saved_view, &document.sheet),
+            Ok(None) => (),
+            Err(e) => println!("Ignoring unreadable view state: {}", e),
+        }
 
         document.history[0].sheet = document.sheet.clone();
+        document.history[0].view = document.view.clone();
         document.persistent.disk_version = document.next_version;
+        document.check_missing_images();
+
+        let autosave_path = Document::get_autosave_path(&path);
+        if is_newer(&autosave_path, &path) {
+            let mut directory = path.as_ref().to_owned();
+            directory.pop();
+            match compat::read_sheet(&autosave_path)
+                .and_then(|sheet| sheet.with_absolute_paths(&directory))
+            {
+                Ok(sheet) => document.persistent.autosave_recovery = Some(sheet),
+                Err(e) => println!("Ignoring unreadable autosave: {}", e),
+            }
+        }
 
         Ok(document)
     }
 
-    pub fn save<T: AsRef<Path>>(sheet: &Sheet, to: T) -> Result<(), Error> {
+    // Autosaves live next to their sheet (`foo.tiger` -> `foo.tiger.autosave`)
+    pub fn get_autosave_path<T: AsRef<Path>>(source: T) -> PathBuf {
+        let mut file_name = source.as_ref().as_os_str().to_owned();
+        file_name.push(".autosave");
+        PathBuf::from(file_name)
+    }
+
+    pub fn delete_autosave<T: AsRef<Path>>(source: T) -> Result<(), Error> {
+        let autosave_path = Document::get_autosave_path(source);
+        if autosave_path.exists() {
+            fs::remove_file(autosave_path)?;
+        }
+        Ok(())
+    }
+
+    pub fn save<T: AsRef<Path>>(sheet: &Sheet, to: T, absolute_paths: bool) -> Result<(), Error> {
+        sheet.validate()?;
+        Document::save_without_validation(sheet, to, absolute_paths)
+    }
+
+    pub fn save_without_validation<T: AsRef<Path>>(
+        sheet: &Sheet,
+        to: T,
+        absolute_paths: bool,
+    ) -> Result<(), Error> {
         let mut directory = to.as_ref().to_owned();
         directory.pop();
-        let sheet = sheet.with_relative_paths(directory)?;
+        let sheet = if absolute_paths {
+            sheet.clone()
+        } else {
+            sheet.with_relative_paths(directory)?
+        };
         compat::write_sheet(to, &sheet)?;
         Ok(())
     }
@@ -79,23 +344,58 @@ impl Document {
     }
 
     pub fn tick(&mut self, delta: Duration) {
+        #[cfg(debug_assertions)]
+        self.check_integrity();
+
         if self.persistent.timeline_is_playing {
-            self.view.timeline_clock += delta;
+            let speed = f64::from(self.persistent.playback_speed);
+            let scaled_delta = Duration::from_nanos((delta.as_nanos() as f64 * speed) as u64);
+            if let Some(loop_region) = self.get_loop_region() {
+                self.tick_loop_region(scaled_delta, loop_region);
+                return;
+            }
+            match self.get_playhead_direction() {
+                PlaybackDirection::Forward => self.view.timeline_clock += scaled_delta,
+                PlaybackDirection::Backward => self.rewind_timeline_clock(scaled_delta),
+            };
             if let Some(WorkbenchItem::Animation(animation_name)) = &self.view.workbench_item {
                 if let Some(animation) = self.sheet.get_animation(animation_name) {
                     match animation.get_duration() {
                         Some(d) if d > 0 => {
                             let clock_ms = self.view.timeline_clock.as_millis();
-                            // Loop animation
-                            if animation.is_looping() {
-                                self.view.timeline_clock =
-                                    Duration::from_millis((clock_ms % u128::from(d)) as u64)
-
-                            // Stop playhead at the end of animation
-                            } else if clock_ms >= u128::from(d) {
-                                self.persistent.timeline_is_playing = false;
-                                self.view.timeline_clock = Duration::from_millis(u64::from(d))
-                            }
+                            let d = u128::from(d);
+                            let is_forward =
+                                self.get_playhead_direction() == PlaybackDirection::Forward;
+                            match animation.get_loop_mode() {
+                                // Loop animation
+                                LoopMode::Loop => {
+                                    self.view.timeline_clock =
+                                        Duration::from_millis((clock_ms % d) as u64)
+                                }
+
+                                // Bounce playhead off the end of animation
+                                LoopMode::PingPong if clock_ms >= d => {
+                                    let period_time = clock_ms % (2 * d);
+                                    if period_time >= d {
+                                        self.persistent.ping_pong_returning =
+                                            self.persistent.playback_direction
+                                                == PlaybackDirection::Forward;
+                                        self.view.timeline_clock =
+                                            Duration::from_millis((2 * d - period_time) as u64)
+                                    } else {
+                                        self.view.timeline_clock =
+                                            Duration::from_millis(period_time as u64)
+                                    }
+                                }
+
+                                // Stop playhead at the end of animation
+                                LoopMode::Once if clock_ms >= d && is_forward => {
+                                    self.persistent.timeline_is_playing = false;
+                                    self.view.timeline_clock = Duration::from_millis(d as u64)
+                                }
+
+                                _ => (),
+                            };
                         }
 
                         // Reset playhead
@@ -109,6 +409,152 @@ impl Document {
         }
     }
 
+    // Loop regions wrap the clock around regardless of the animation loop mode
+    fn tick_loop_region(&mut self, delta: Duration, (start, end): (u64, u64)) {
+        let start = u128::from(start) * 1_000_000;
+        let end = u128::from(end) * 1_000_000;
+        let length = end - start;
+        let position = self.view.timeline_clock.as_nanos().max(start).min(end) - start;
+        let delta = delta.as_nanos() % length;
+        let position = match self.persistent.playback_direction {
+            PlaybackDirection::Forward => (position + delta) % length,
+            PlaybackDirection::Backward => (position + length - delta) % length,
+        };
+        self.view.timeline_clock = Duration::from_nanos((start + position) as u64);
+    }
+
+    // Direction the playhead moves in, which ping-pong animations reverse at each end
+    fn get_playhead_direction(&self) -> PlaybackDirection {
+        let direction = self.persistent.playback_direction;
+        match (direction, self.persistent.ping_pong_returning) {
+            (PlaybackDirection::Forward, true) => PlaybackDirection::Backward,
+            (PlaybackDirection::Backward, true) => PlaybackDirection::Forward,
+            (direction, false) => direction,
+        }
+    }
+
+    // Rewinding past the start wraps around for looping animations, bounces for ping-pong
+    // animations and stops playback otherwise
+    fn rewind_timeline_clock(&mut self, delta: Duration) {
+        if delta <= self.view.timeline_clock {
+            self.view.timeline_clock -= delta;
+            return;
+        }
+        let overshoot = (delta - self.view.timeline_clock).as_millis();
+        let (loop_mode, d) = match self.get_workbench_animation() {
+            Ok(animation) => (
+                animation.get_loop_mode(),
+                u128::from(animation.get_duration().unwrap_or(0)),
+            ),
+            Err(_) => (LoopMode::Once, 0),
+        };
+        match loop_mode {
+            LoopMode::Loop if d > 0 => {
+                self.view.timeline_clock = Duration::from_millis((d - overshoot % d) as u64);
+            }
+            LoopMode::PingPong if d > 0 => {
+                let period_time = overshoot % (2 * d);
+                if period_time <= d {
+                    self.persistent.ping_pong_returning =
+                        self.persistent.playback_direction == PlaybackDirection::Backward;
+                    self.view.timeline_clock = Duration::from_millis(period_time as u64);
+                } else {
+                    self.view.timeline_clock = Duration::from_millis((2 * d - period_time) as u64);
+                }
+            }
+            _ => {
+                self.persistent.timeline_is_playing = false;
+                self.view.timeline_clock = Duration::new(0, 0);
+            }
+        }
+    }
+
+    // Catches commands leaving the document in an inconsistent state
+    #[cfg(debug_assertions)]
+    fn check_integrity(&self) {
+        assert!(self.history_index < self.history.len());
+        assert!(self.persistent.disk_version <= self.next_version);
+
+        match &self.view.selection {
+            Some(Selection::Frame(f)) => {
+                assert!(
+                    f.items.iter().all(|p| self.sheet.get_frame(p).is_some()),
+                    "Selected frame is not in sheet"
+                );
+                assert!(
+                    f.contains(&f.last_touched),
+                    "Last touched frame is not selected"
+                );
+            }
+            Some(Selection::Animation(a)) => assert!(
+                self.sheet.get_animation(a).is_some(),
+                "Selected animation is not in sheet"
+            ),
+            Some(Selection::Hitbox(f, h)) => {
+                assert!(
+                    h.items.iter().all(|h| self
+                        .sheet
+                        .get_frame(f)
+                        .and_then(|f| f.get_hitbox(h))
+                        .is_some()),
+                    "Selected hitbox is not in sheet"
+                );
+                assert!(
+                    h.contains(&h.last_touched),
+                    "Last touched hitbox is not selected"
+                );
+            }
+            Some(Selection::AnimationFrame(a, af)) => {
+                assert!(
+                    af.items.iter().all(|af| self
+                        .sheet
+                        .get_animation(a)
+                        .and_then(|a| a.get_frame(*af))
+                        .is_some()),
+                    "Selected animation frame is not in sheet"
+                );
+                assert!(
+                    af.contains(&af.last_touched),
+                    "Last touched animation frame is not selected"
+                );
+            }
+            None => (),
+        }
+
+        assert_eq!(
+            self.transient.item_being_renamed.is_some(),
+            self.transient.rename_buffer.is_some(),
+            "Rename buffer and renamed item are out of sync"
+        );
+
+        if self.transient.workbench_hitbox_being_dragged.is_some()
+            || self.transient.workbench_hitbox_being_scaled.is_some()
+        {
+            match &self.view.workbench_item {
+                Some(WorkbenchItem::Frame(_)) => (),
+                _ => panic!("Editing a hitbox while no frame is in the workbench"),
+            }
+        }
+
+        if self.transient.workbench_anchor_being_dragged {
+            match &self.view.workbench_item {
+                Some(WorkbenchItem::Frame(_)) => (),
+                _ => panic!("Dragging an anchor while no frame is in the workbench"),
+            }
+        }
+
+        if self
+            .transient
+            .workbench_animation_frame_being_dragged
+            .is_some()
+        {
+            match &self.view.workbench_item {
+                Some(WorkbenchItem::Animation(_)) => (),
+                _ => panic!("Dragging an animation frame while no animation is in the workbench"),
+            }
+        }
+    }
+
     fn push_undo_state(&mut self, entry: HistoryEntry) {
         self.history.truncate(self.history_index + 1);
         self.history.push(entry);
@@ -119,7 +565,25 @@ impl Document {
         self.transient == Default::default()
     }
 
+    // The command ending an interaction does not always describe what the interaction changed
+    fn get_command_label(&self, command: &DocumentCommand) -> String {
+        match (command, &self.transient.item_being_renamed) {
+            (DocumentCommand::EndRenameSelection, Some(item)) => match item {
+                RenameItem::Animation(_) => "Rename Animation",
+                RenameItem::AnimationGroup(_) => "Change Animation Group",
+                RenameItem::AnimationDirection(_) => "Change Animation Direction",
+                RenameItem::AnimationTag(_) => "Add Animation Tag",
+                RenameItem::AnimationFrameSound(_, _) => "Change Sound",
+                RenameItem::AnimationFrameUserData(_, _) => "Change User Data",
+                RenameItem::Hitbox(_, _) => "Rename Hitbox",
+            }
+            .to_owned(),
+            _ => command.to_string(),
+        }
+    }
+
     fn record_command(&mut self, command: &DocumentCommand, new_document: Document) {
+        let label = self.get_command_label(command);
         self.sheet = new_document.sheet.clone();
         self.view = new_document.view.clone();
         self.transient = new_document.transient.clone();
@@ -135,7 +599,7 @@ impl Document {
             let new_undo_state = HistoryEntry {
                 sheet: new_document.sheet,
                 view: new_document.view,
-                last_command: Some(command.clone()),
+                last_command_label: Some(label),
                 version: self.next_version,
             };
 
@@ -180,13 +644,19 @@ impl Document {
         Ok(())
     }
 
-    pub fn get_undo_command(&self) -> Option<&DocumentCommand> {
-        self.history[self.history_index].last_command.as_ref()
+    pub fn get_undo_label(&self) -> Option<&str> {
+        self.history[self.history_index]
+            .last_command_label
+            .as_ref()
+            .map(|l| l.as_str())
     }
 
-    pub fn get_redo_command(&self) -> Option<&DocumentCommand> {
+    pub fn get_redo_label(&self) -> Option<&str> {
         if self.history_index < self.history.len() - 1 {
-            self.history[self.history_index + 1].last_command.as_ref()
+            self.history[self.history_index + 1]
+                .last_command_label
+                .as_ref()
+                .map(|l| l.as_str())
         } else {
             None
         }
@@ -220,11 +690,136 @@ impl Document {
         self.view.selection = None;
     }
 
+    fn get_all_frames_selection(&self, last_touched: Option<PathBuf>) -> Option<Selection> {
+        let paths = self
+            .sheet
+            .frames_iter()
+            .map(|f| f.get_source().to_owned())
+            .collect();
+        MultiSelection::with_items(paths, last_touched).map(Selection::Frame)
+    }
+
+    fn get_all_hitboxes_selection(
+        &self,
+        frame_path: &Path,
+        last_touched: Option<String>,
+    ) -> Result<Option<Selection>, Error> {
+        let frame = self
+            .sheet
+            .get_frame(frame_path)
+            .ok_or(StateError::FrameNotInDocument)?;
+        let names = frame
+            .hitboxes_iter()
+            .map(|h| h.get_name().to_owned())
+            .collect();
+        Ok(MultiSelection::with_items(names, last_touched)
+            .map(|h| Selection::Hitbox(frame_path.to_owned(), h)))
+    }
+
+    fn get_all_animation_frames_selection(
+        &self,
+        animation_name: &str,
+        last_touched: Option<usize>,
+    ) -> Result<Option<Selection>, Error> {
+        let animation = self
+            .sheet
+            .get_animation(animation_name)
+            .ok_or(StateError::AnimationNotInDocument)?;
+        let indices = (0..animation.get_num_frames()).collect();
+        Ok(MultiSelection::with_items(indices, last_touched)
+            .map(|af| Selection::AnimationFrame(animation_name.to_owned(), af)))
+    }
+
+    // Extends the selection to every item of its kind. Without such a selection, this selects
+    // the items of the workbench, or all frames from the content frames tab.
+    pub fn select_all(&mut self) -> Result<(), Error> {
+        let selection = match (&self.view.selection, &self.view.workbench_item) {
+            (Some(Selection::Frame(f)), _) => {
+                self.get_all_frames_selection(Some(f.last_touched.clone()))
+            }
+            (Some(Selection::Hitbox(p, h)), _) => {
+                self.get_all_hitboxes_selection(p, Some(h.last_touched.clone()))?
+            }
+            (Some(Selection::AnimationFrame(a, af)), _) => {
+                self.get_all_animation_frames_selection(a, Some(af.last_touched))?
+            }
+            (_, Some(WorkbenchItem::Frame(p))) => self.get_all_hitboxes_selection(p, None)?,
+            (_, Some(WorkbenchItem::Animation(a))) => {
+                self.get_all_animation_frames_selection(a, None)?
+            }
+            (_, None) if self.view.content_tab == ContentTab::Frames => {
+                self.get_all_frames_selection(None)
+            }
+            (_, None) => None,
+        };
+        if selection.is_some() {
+            self.view.selection = selection;
+        }
+        Ok(())
+    }
+
+    // Inverts frame or hitbox selections, the selection is cleared when it covered every item
+    pub fn invert_selection(&mut self) -> Result<(), Error> {
+        self.view.selection = match self.view.selection.take() {
+            Some(Selection::Frame(selected)) => {
+                let paths = self
+                    .sheet
+                    .frames_iter()
+                    .map(|f| f.get_source().to_owned())
+                    .collect();
+                selected.invert(paths).map(Selection::Frame)
+            }
+            Some(Selection::Hitbox(p, selected)) => {
+                let frame = self
+                    .sheet
+                    .get_frame(&p)
+                    .ok_or(StateError::FrameNotInDocument)?;
+                let names = frame
+                    .hitboxes_iter()
+                    .map(|h| h.get_name().to_owned())
+                    .collect();
+                selected.invert(names).map(|h| Selection::Hitbox(p, h))
+            }
+            s => s,
+        };
+        Ok(())
+    }
+
     pub fn select_frame<T: AsRef<Path>>(&mut self, path: T) -> Result<(), Error> {
         if !self.sheet.has_frame(&path) {
             return Err(StateError::FrameNotInDocument.into());
         }
-        self.view.selection = Some(Selection::Frame(path.as_ref().to_owned()));
+        self.view.selection = Some(Selection::Frame(MultiSelection::new(
+            path.as_ref().to_owned(),
+        )));
+        Ok(())
+    }
+
+    pub fn toggle_frame_selection<T: AsRef<Path>>(&mut self, path: T) -> Result<(), Error> {
+        if !self.sheet.has_frame(&path) {
+            return Err(StateError::FrameNotInDocument.into());
+        }
+        let path = path.as_ref().to_owned();
+        self.view.selection = match self.view.selection.take() {
+            Some(Selection::Frame(paths)) => paths.toggle(path).map(Selection::Frame),
+            _ => Some(Selection::Frame(MultiSelection::new(path))),
+        };
+        Ok(())
+    }
+
+    // The range is ordered by the UI, the previous anchor is kept when it is part of the range
+    pub fn select_frame_range(&mut self, paths: &[PathBuf]) -> Result<(), Error> {
+        if paths.iter().any(|p| !self.sheet.has_frame(p)) {
+            return Err(StateError::FrameNotInDocument.into());
+        }
+        let last_touched = match &self.view.selection {
+            Some(Selection::Frame(f)) if paths.contains(&f.last_touched) => f.last_touched.clone(),
+            _ => paths.last().ok_or(StateError::FrameNotInDocument)?.clone(),
+        };
+        self.view.selection = Some(Selection::Frame(MultiSelection {
+            last_touched,
+            items: paths.to_vec(),
+        }));
         Ok(())
     }
 
@@ -251,18 +846,66 @@ impl Document {
             .ok_or(StateError::InvalidHitboxIndex)?;
         self.view.selection = Some(Selection::Hitbox(
             frame_path,
-            hitbox_name.as_ref().to_owned(),
+            MultiSelection::new(hitbox_name.as_ref().to_owned()),
         ));
         Ok(())
     }
 
+    pub fn toggle_hitbox_selection<T: AsRef<str>>(&mut self, hitbox_name: T) -> Result<(), Error> {
+        let frame_path = self.get_workbench_frame_with_hitboxes(&[hitbox_name.as_ref()])?;
+        let name = hitbox_name.as_ref().to_owned();
+        self.view.selection = match &self.view.selection {
+            Some(Selection::Hitbox(p, names)) if *p == frame_path => names
+                .clone()
+                .toggle(name)
+                .map(|n| Selection::Hitbox(frame_path, n)),
+            _ => Some(Selection::Hitbox(frame_path, MultiSelection::new(name))),
+        };
+        Ok(())
+    }
+
+    // The range is ordered by the UI, the previous anchor is kept when it is part of the range
+    pub fn select_hitbox_range(&mut self, hitbox_names: &[String]) -> Result<(), Error> {
+        let frame_path = self.get_workbench_frame_with_hitboxes(hitbox_names)?;
+        let last_touched = match &self.view.selection {
+            Some(Selection::Hitbox(p, n)) if *p == frame_path => Some(n.last_touched.clone()),
+            _ => None,
+        };
+        let names = MultiSelection::with_items(hitbox_names.to_vec(), last_touched)
+            .ok_or(StateError::InvalidHitboxIndex)?;
+        self.view.selection = Some(Selection::Hitbox(frame_path, names));
+        Ok(())
+    }
+
+    fn get_workbench_frame_with_hitboxes<T: AsRef<str>>(
+        &self,
+        hitbox_names: &[T],
+    ) -> Result<PathBuf, Error> {
+        let frame_path = match &self.view.workbench_item {
+            Some(WorkbenchItem::Frame(p)) => Some(p.to_owned()),
+            _ => None,
+        }
+        .ok_or(StateError::NotEditingAnyFrame)?;
+        let frame = self
+            .sheet
+            .get_frame(&frame_path)
+            .ok_or(StateError::FrameNotInDocument)?;
+        if hitbox_names.iter().any(|n| frame.get_hitbox(n).is_none()) {
+            return Err(StateError::InvalidHitboxIndex.into());
+        }
+        Ok(frame_path)
+    }
+
     pub fn select_animation_frame(&mut self, frame_index: usize) -> Result<(), Error> {
         let animation_name = {
             let animation = self.get_workbench_animation()?;
             animation.get_name().to_owned()
         };
 
-        self.view.selection = Some(Selection::AnimationFrame(animation_name, frame_index));
+        self.view.selection = Some(Selection::AnimationFrame(
+            animation_name,
+            MultiSelection::new(frame_index),
+        ));
 
         let animation = self.get_workbench_animation()?;
 
@@ -274,7 +917,7 @@ impl Document {
         let animation_frame = animation
             .get_frame(frame_index)
             .ok_or(StateError::InvalidAnimationFrameIndex)?;
-        let duration = animation_frame.get_duration() as u64;
+        let duration = u64::from(animation.get_frame_duration(animation_frame));
 
         let clock = self.view.timeline_clock.as_millis() as u64;
         let is_playhead_in_frame = clock >= frame_start_time
@@ -297,10 +940,12 @@ impl Document {
                 frames.sort_unstable();
                 let current_index = frames
                     .iter()
-                    .position(|f| f.get_source() == p)
+                    .position(|f| f.get_source() == p.last_touched)
                     .ok_or(StateError::FrameNotInDocument)?;
                 if let Some(f) = frames.get(advance(current_index)) {
-                    self.view.selection = Some(Selection::Frame(f.get_source().to_owned()));
+                    self.view.selection = Some(Selection::Frame(MultiSelection::new(
+                        f.get_source().to_owned(),
+                    )));
                 }
             }
             Some(Selection::Animation(n)) => {
@@ -324,11 +969,13 @@ impl Document {
                 hitboxes.sort_unstable();
                 let current_index = hitboxes
                     .iter()
-                    .position(|h| h.get_name() == n)
+                    .position(|h| h.get_name() == n.last_touched)
                     .ok_or(StateError::InvalidHitboxIndex)?;
                 if let Some(h) = hitboxes.get(advance(current_index)) {
-                    self.view.selection =
-                        Some(Selection::Hitbox(p.to_owned(), h.get_name().to_owned()));
+                    self.view.selection = Some(Selection::Hitbox(
+                        p.to_owned(),
+                        MultiSelection::new(h.get_name().to_owned()),
+                    ));
                 }
             }
             Some(Selection::AnimationFrame(_, _)) | None => (),
@@ -360,7 +1007,9 @@ impl Document {
         self.view.workbench_item = Some(WorkbenchItem::Animation(name.as_ref().to_owned()));
         self.view.workbench_offset = Vector2D::zero();
         self.view.timeline_clock = Duration::new(0, 0);
+        self.clear_loop_region();
         self.persistent.timeline_is_playing = false;
+        self.persistent.ping_pong_returning = false;
         Ok(())
     }
 
@@ -375,6 +1024,78 @@ impl Document {
         Ok(())
     }
 
+    fn begin_animation_group_edit<T: AsRef<str>>(&mut self, name: T) -> Result<(), Error> {
+        let animation = self
+            .sheet
+            .get_animation(&name)
+            .ok_or(StateError::AnimationNotInDocument)?;
+        self.transient.rename_buffer = Some(animation.get_group().unwrap_or("").to_owned());
+        self.transient.item_being_renamed =
+            Some(RenameItem::AnimationGroup(name.as_ref().to_owned()));
+        Ok(())
+    }
+
+    fn begin_animation_direction_edit<T: AsRef<str>>(&mut self, name: T) -> Result<(), Error> {
+        let animation = self
+            .sheet
+            .get_animation(&name)
+            .ok_or(StateError::AnimationNotInDocument)?;
+        self.transient.rename_buffer = Some(animation.get_direction().unwrap_or("").to_owned());
+        self.transient.item_being_renamed =
+            Some(RenameItem::AnimationDirection(name.as_ref().to_owned()));
+        Ok(())
+    }
+
+    fn begin_animation_tag_edit<T: AsRef<str>>(&mut self, name: T) -> Result<(), Error> {
+        let _animation = self
+            .sheet
+            .get_animation(&name)
+            .ok_or(StateError::AnimationNotInDocument)?;
+        self.transient.rename_buffer = Some("".to_owned());
+        self.transient.item_being_renamed =
+            Some(RenameItem::AnimationTag(name.as_ref().to_owned()));
+        Ok(())
+    }
+
+    fn begin_animation_frame_sound_edit<T: AsRef<str>>(
+        &mut self,
+        animation_name: T,
+        animation_frame_index: usize,
+    ) -> Result<(), Error> {
+        let animation_frame = self
+            .sheet
+            .get_animation(&animation_name)
+            .ok_or(StateError::AnimationNotInDocument)?
+            .get_frame(animation_frame_index)
+            .ok_or(StateError::InvalidAnimationFrameIndex)?;
+        self.transient.rename_buffer = Some(animation_frame.get_sound().unwrap_or("").to_owned());
+        self.transient.item_being_renamed = Some(RenameItem::AnimationFrameSound(
+            animation_name.as_ref().to_owned(),
+            animation_frame_index,
+        ));
+        Ok(())
+    }
+
+    fn begin_animation_frame_user_data_edit<T: AsRef<str>>(
+        &mut self,
+        animation_name: T,
+        animation_frame_index: usize,
+    ) -> Result<(), Error> {
+        let animation_frame = self
+            .sheet
+            .get_animation(&animation_name)
+            .ok_or(StateError::AnimationNotInDocument)?
+            .get_frame(animation_frame_index)
+            .ok_or(StateError::InvalidAnimationFrameIndex)?;
+        self.transient.rename_buffer =
+            Some(animation_frame.get_user_data().unwrap_or("").to_owned());
+        self.transient.item_being_renamed = Some(RenameItem::AnimationFrameUserData(
+            animation_name.as_ref().to_owned(),
+            animation_frame_index,
+        ));
+        Ok(())
+    }
+
     fn begin_hitbox_rename<T: AsRef<Path>, U: AsRef<str>>(
         &mut self,
         frame_path: T,
@@ -394,18 +1115,375 @@ impl Document {
         Ok(())
     }
 
-    pub fn create_animation(&mut self) -> Result<(), Error> {
-        let animation_name = {
-            let animation = self.sheet.add_animation();
-            let animation_name = animation.get_name().to_owned();
-            self.begin_animation_rename(&animation_name)?;
-            animation_name
-        };
-        self.select_animation(&animation_name)?;
-        self.edit_animation(animation_name)
-    }
-
-    pub fn begin_frame_drag<T: AsRef<Path>>(&mut self, frame: T) -> Result<(), Error> {
+    // Frames are drawn centered on their keyframe offset. When the previous and new frame sizes
+    // are provided, offsets move by half the size difference so the top-left corner of the frame
+    // stays in place, which is where external editors grow or crop the canvas from.
+    pub fn refresh_frame<T: AsRef<Path>>(
+        &mut self,
+        frame: T,
+        resize: Option<(Vector2D<u32>, Vector2D<u32>)>,
+    ) -> Result<(), Error> {
+        if !self.sheet.has_frame(&frame) {
+            return Err(StateError::FrameNotInDocument.into());
+        }
+        if let Some((old_size, new_size)) = resize {
+            let delta = (new_size / 2).to_i32() - (old_size / 2).to_i32();
+            for animation in self.sheet.animations_iter_mut() {
+                for animation_frame in animation.frames_iter_mut() {
+                    if animation_frame.get_frame() == frame.as_ref() {
+                        let offset = animation_frame.get_offset();
+                        animation_frame.set_offset(offset + delta);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn begin_build_animations_from_frame_names(&mut self) {
+        let mut sequences: BTreeMap<String, Vec<(u32, PathBuf)>> = BTreeMap::new();
+        for frame in self.sheet.frames_iter() {
+            let file_stem = frame
+                .get_source()
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned());
+            if let Some((prefix, number)) = file_stem.as_ref().and_then(|s| split_numeric_suffix(s))
+            {
+                sequences
+                    .entry(prefix.to_owned())
+                    .or_insert_with(Vec::new)
+                    .push((number, frame.get_source().to_owned()));
+            }
+        }
+
+        let proposals = sequences
+            .into_iter()
+            .filter(|(name, frames)| {
+                frames.len() > 1
+                    && name.len() <= MAX_ANIMATION_NAME_LENGTH
+                    && !self.sheet.has_animation(name)
+            })
+            .map(|(name, mut frames)| {
+                frames.sort();
+                AnimationProposal {
+                    name,
+                    frames: frames.into_iter().map(|(_, f)| f).collect(),
+                }
+            })
+            .collect();
+
+        self.persistent.animation_proposals = Some(proposals);
+    }
+
+    fn end_build_animations_from_frame_names(&mut self, is_looping: bool) -> Result<(), Error> {
+        let proposals = self
+            .persistent
+            .animation_proposals
+            .take()
+            .ok_or(StateError::NotBuildingAnimations)?;
+        for proposal in proposals {
+            if self.sheet.has_animation(&proposal.name) {
+                continue;
+            }
+            let default_name = self.sheet.add_animation(is_looping).get_name().to_owned();
+            self.sheet.rename_animation(&default_name, &proposal.name)?;
+            let animation = self
+                .sheet
+                .get_animation_mut(&proposal.name)
+                .ok_or(StateError::AnimationNotInDocument)?;
+            for (index, frame) in proposal.frames.iter().enumerate() {
+                animation.insert_frame(frame, index)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn cancel_build_animations_from_frame_names(&mut self) {
+        self.persistent.animation_proposals = None;
+    }
+
+    fn begin_merge_duplicate_animations(&mut self) {
+        let mut originals: Vec<&Animation> = Vec::new();
+        let mut duplicates = Vec::new();
+        for animation in self.sheet.animations_iter() {
+            if animation.get_num_frames() == 0 {
+                continue;
+            }
+            match originals.iter().find(|o| o.has_same_content(animation)) {
+                Some(original) => duplicates.push(AnimationDuplicate {
+                    name: animation.get_name().to_owned(),
+                    original: original.get_name().to_owned(),
+                }),
+                None => originals.push(animation),
+            }
+        }
+        self.persistent.animation_duplicates = Some(duplicates);
+    }
+
+    fn end_merge_duplicate_animations(&mut self) -> Result<(), Error> {
+        let duplicates = self
+            .persistent
+            .animation_duplicates
+            .take()
+            .ok_or(StateError::NotMergingAnimations)?;
+        for duplicate in duplicates {
+            self.sheet.delete_animation(&duplicate.name);
+            match &self.view.selection {
+                Some(Selection::Animation(a)) if a == &duplicate.name => {
+                    self.view.selection = Some(Selection::Animation(duplicate.original.clone()));
+                }
+                Some(Selection::AnimationFrame(a, af)) if a == &duplicate.name => {
+                    self.view.selection = Some(Selection::AnimationFrame(
+                        duplicate.original.clone(),
+                        af.clone(),
+                    ));
+                }
+                _ => (),
+            }
+            if self.view.workbench_item == Some(WorkbenchItem::Animation(duplicate.name)) {
+                self.view.workbench_item = Some(WorkbenchItem::Animation(duplicate.original));
+            }
+        }
+        Ok(())
+    }
+
+    fn cancel_merge_duplicate_animations(&mut self) {
+        self.persistent.animation_duplicates = None;
+    }
+
+    fn end_import_aseprite(&mut self, frames: &[PathBuf], animations: &[Animation]) {
+        for frame in frames {
+            self.sheet.add_frame(frame);
+        }
+        for animation in animations {
+            self.sheet.insert_animation(animation.clone());
+        }
+    }
+
+    fn end_select_grid_image<T: AsRef<Path>>(&mut self, image: T, image_size: Vector2D<u32>) {
+        let default_tile_size = vec2(32, 32);
+        self.persistent.grid_import = Some(GridImport {
+            image: image.as_ref().to_owned(),
+            image_size,
+            tile_size: vec2(
+                std::cmp::min(default_tile_size.x, image_size.x),
+                std::cmp::min(default_tile_size.y, image_size.y),
+            ),
+        });
+    }
+
+    fn set_grid_import_tile_size(&mut self, tile_size: Vector2D<u32>) -> Result<(), Error> {
+        let grid_import = self
+            .persistent
+            .grid_import
+            .as_mut()
+            .ok_or(StateError::NotImportingGrid)?;
+        grid_import.tile_size = tile_size;
+        Ok(())
+    }
+
+    fn end_grid_import(&mut self) -> Result<(), Error> {
+        let grid_import = self
+            .persistent
+            .grid_import
+            .take()
+            .ok_or(StateError::NotImportingGrid)?;
+        self.sheet.add_sliced_frames(
+            &grid_import.image,
+            grid_import.image_size,
+            grid_import.tile_size,
+        )
+    }
+
+    fn end_recover_autosave(&mut self) -> Result<(), Error> {
+        self.sheet = self
+            .persistent
+            .autosave_recovery
+            .take()
+            .ok_or(StateError::NoAutosaveToRecover)?;
+        self.view = Default::default();
+        self.check_missing_images();
+        Ok(())
+    }
+
+    fn cancel_recover_autosave(&mut self) {
+        self.persistent.autosave_recovery = None;
+    }
+
+    fn check_missing_images(&mut self) {
+        let missing_images = self.sheet.get_missing_images();
+        self.persistent.missing_images = if missing_images.is_empty() {
+            None
+        } else {
+            Some(missing_images)
+        };
+    }
+
+    // Images which could not be relinked stay listed so they can be looked for elsewhere
+    fn end_relink_frames(&mut self, candidates: &[PathBuf]) {
+        self.sheet.relink_frames(candidates);
+        self.check_missing_images();
+    }
+
+    fn cancel_relink_frames(&mut self) {
+        self.persistent.missing_images = None;
+    }
+
+    // Existing files this document did not export since it was opened
+    pub fn get_export_overwrite_conflicts(&self, sheet: &Sheet) -> Vec<PathBuf> {
+        if self.persistent.allow_export_overwrites {
+            return Vec::new();
+        }
+        get_export_destinations(sheet)
+            .into_iter()
+            .filter(|p| p.exists() && !self.persistent.exported_files.contains(p))
+            .collect()
+    }
+
+    fn record_export_destinations(&mut self) {
+        for destination in get_export_destinations(&self.sheet) {
+            if !self.persistent.exported_files.contains(&destination) {
+                self.persistent.exported_files.push(destination);
+            }
+        }
+    }
+
+    fn end_export_overwrite(&mut self, always_overwrite: bool) {
+        self.persistent.export_overwrite_conflicts = None;
+        self.persistent.allow_export_overwrites |= always_overwrite;
+        self.record_export_destinations();
+    }
+
+    pub fn create_animation(&mut self, is_looping: bool) -> Result<(), Error> {
+        let animation_name = {
+            let animation = self.sheet.add_animation(is_looping);
+            let animation_name = animation.get_name().to_owned();
+            self.begin_animation_rename(&animation_name)?;
+            animation_name
+        };
+        self.select_animation(&animation_name)?;
+        self.edit_animation(animation_name)
+    }
+
+    // Copies every selected item, keyframes are copied in timeline order
+    pub fn copy_selection(&self) -> Result<ClipboardContent, Error> {
+        Ok(match &self.view.selection {
+            Some(Selection::Frame(paths)) => {
+                let mut frames = Vec::new();
+                for path in &paths.items {
+                    let frame = self
+                        .sheet
+                        .get_frame(path)
+                        .ok_or(StateError::FrameNotInDocument)?;
+                    frames.push(frame.clone());
+                }
+                ClipboardContent::Frames(frames)
+            }
+            Some(Selection::Animation(name)) => ClipboardContent::Animation(
+                self.sheet
+                    .get_animation(name)
+                    .ok_or(StateError::AnimationNotInDocument)?
+                    .clone(),
+            ),
+            Some(Selection::Hitbox(path, names)) => {
+                let frame = self
+                    .sheet
+                    .get_frame(path)
+                    .ok_or(StateError::FrameNotInDocument)?;
+                let mut hitboxes = Vec::new();
+                for name in &names.items {
+                    let hitbox = frame.get_hitbox(name).ok_or(StateError::HitboxNotInFrame)?;
+                    hitboxes.push(hitbox.clone());
+                }
+                ClipboardContent::Hitboxes(hitboxes)
+            }
+            Some(Selection::AnimationFrame(name, indices)) => {
+                let animation = self
+                    .sheet
+                    .get_animation(name)
+                    .ok_or(StateError::AnimationNotInDocument)?;
+                let mut sorted_indices = indices.items.clone();
+                sorted_indices.sort();
+                let mut animation_frames = Vec::new();
+                for index in sorted_indices {
+                    let animation_frame = animation
+                        .get_frame(index)
+                        .ok_or(StateError::InvalidAnimationFrameIndex)?;
+                    animation_frames.push(animation_frame.clone());
+                }
+                ClipboardContent::AnimationFrames(animation_frames)
+            }
+            None => return Err(StateError::NothingSelected.into()),
+        })
+    }
+
+    // Pasted items replace the selection
+    pub fn paste(&mut self, content: &ClipboardContent) -> Result<(), Error> {
+        match content {
+            ClipboardContent::Frames(frames) => {
+                let mut paths = Vec::new();
+                for frame in frames {
+                    self.sheet.insert_frame(frame.clone());
+                    paths.push(frame.get_source().to_owned());
+                }
+                self.view.selection = MultiSelection::with_items(paths, None).map(Selection::Frame);
+                Ok(())
+            }
+            ClipboardContent::Animation(animation) => {
+                let animation_name = self
+                    .sheet
+                    .insert_animation(animation.clone())
+                    .get_name()
+                    .to_owned();
+                self.select_animation(&animation_name)
+            }
+            ClipboardContent::Hitboxes(hitboxes) => {
+                let frame_path = match &self.view.workbench_item {
+                    Some(WorkbenchItem::Frame(s)) => Some(s.to_owned()),
+                    _ => None,
+                }
+                .ok_or(StateError::NotEditingAnyFrame)?;
+                let frame = self
+                    .sheet
+                    .get_frame_mut(&frame_path)
+                    .ok_or(StateError::FrameNotInDocument)?;
+                let names = hitboxes
+                    .iter()
+                    .map(|h| frame.insert_hitbox(h.clone()).get_name().to_owned())
+                    .collect();
+                self.view.selection = MultiSelection::with_items(names, None)
+                    .map(|h| Selection::Hitbox(frame_path, h));
+                Ok(())
+            }
+            ClipboardContent::AnimationFrames(animation_frames) => {
+                let clock = self.view.timeline_clock;
+                let index = {
+                    let animation = self.get_workbench_animation_mut()?;
+                    let index = match animation.get_frame_at(clock) {
+                        Some((index, _)) => index,
+                        None => animation.get_num_frames(),
+                    };
+                    for (offset, animation_frame) in animation_frames.iter().enumerate() {
+                        let frame_index = index + offset;
+                        animation.insert_animation_frame(animation_frame.clone(), frame_index)?;
+                    }
+                    index
+                };
+                for animation_frame in animation_frames {
+                    self.sheet.add_frame(animation_frame.get_frame());
+                }
+                self.select_animation_frame(index)?;
+                if let Some(Selection::AnimationFrame(name, _)) = self.view.selection.take() {
+                    let indices = (index..index + animation_frames.len()).collect();
+                    self.view.selection = MultiSelection::with_items(indices, Some(index))
+                        .map(|af| Selection::AnimationFrame(name, af));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub fn begin_frame_drag<T: AsRef<Path>>(&mut self, frame: T) -> Result<(), Error> {
         // TODO Validate that frame is in sheet
         self.transient.content_frame_being_dragged = Some(frame.as_ref().to_owned());
         Ok(())
@@ -421,13 +1499,45 @@ impl Document {
             _ => None,
         }
         .ok_or(StateError::NotEditingAnyAnimation)?;
-        self.sheet
+        // Dragging one of several selected frames inserts all of them
+        let frames = match &self.view.selection {
+            Some(Selection::Frame(paths)) if paths.contains(&frame.as_ref().to_owned()) => {
+                paths.items.clone()
+            }
+            _ => vec![frame.as_ref().to_owned()],
+        };
+        let animation = self
+            .sheet
             .get_animation_mut(animation_name)
-            .ok_or(StateError::AnimationNotInDocument)?
-            .insert_frame(frame, next_frame_index)?;
+            .ok_or(StateError::AnimationNotInDocument)?;
+        for (i, frame) in frames.iter().enumerate() {
+            animation.insert_frame(frame, next_frame_index + i)?;
+        }
         Ok(())
     }
 
+    // Selected frames go before the keyframe under the playhead, or after the last keyframe
+    // once the playhead reached the end of the animation
+    pub fn insert_frames_at_playhead(&mut self) -> Result<(), Error> {
+        let frame = match &self.view.selection {
+            Some(Selection::Frame(paths)) => paths.last_touched.clone(),
+            _ => return Ok(()),
+        };
+        let index = {
+            let animation = self.get_workbench_animation()?;
+            let duration = u128::from(animation.get_duration().unwrap_or(0));
+            if self.view.timeline_clock.as_millis() >= duration {
+                animation.get_num_frames()
+            } else {
+                animation
+                    .get_frame_at(self.view.timeline_clock)
+                    .map(|(i, _)| i)
+                    .unwrap_or(0)
+            }
+        };
+        self.insert_animation_frame_before(frame, index)
+    }
+
     pub fn reorder_animation_frame(
         &mut self,
         old_index: usize,
@@ -448,18 +1558,20 @@ impl Document {
             .ok_or(StateError::AnimationNotInDocument)?
             .reorder_frame(old_index, new_index)?;
 
-        match self.view.selection {
-            Some(Selection::AnimationFrame(ref n, i)) if n == &animation_name => {
-                if i == old_index {
-                    self.view.selection = Some(Selection::AnimationFrame(
-                        n.clone(),
-                        new_index - if old_index < new_index { 1 } else { 0 },
-                    ));
-                } else if i > old_index && i < new_index {
-                    self.view.selection = Some(Selection::AnimationFrame(n.clone(), i - 1));
-                } else if i >= new_index && i < old_index {
-                    self.view.selection = Some(Selection::AnimationFrame(n.clone(), i + 1));
-                }
+        match &self.view.selection {
+            Some(Selection::AnimationFrame(n, indices)) if n == &animation_name => {
+                let indices = indices.map(|&i| {
+                    if i == old_index {
+                        new_index - if old_index < new_index { 1 } else { 0 }
+                    } else if i > old_index && i < new_index {
+                        i - 1
+                    } else if i >= new_index && i < old_index {
+                        i + 1
+                    } else {
+                        i
+                    }
+                });
+                self.view.selection = Some(Selection::AnimationFrame(n.clone(), indices));
             }
             _ => (),
         }
@@ -594,10 +1706,13 @@ impl Document {
         self.select_animation_frame(index)
     }
 
+    // Each axis of the offset snaps to the grid, and to zero when it lands within a few
+    // screen pixels of the origin
     pub fn update_animation_frame_offset_drag(
         &mut self,
         mut mouse_delta: Vector2D<f32>,
         both_axis: bool,
+        allow_snapping: bool,
     ) -> Result<(), Error> {
         let zoom = self.view.get_workbench_zoom_factor();
         let animation_name = match &self.view.workbench_item {
@@ -619,7 +1734,19 @@ impl Document {
                 mouse_delta.x = 0.0;
             }
         }
-        let new_offset = (old_offset.to_f32() + mouse_delta / zoom).floor().to_i32();
+        let mut new_offset = (old_offset.to_f32() + mouse_delta / zoom).floor().to_i32();
+        if allow_snapping {
+            if self.view.snap_to_grid {
+                new_offset = vec2(self.view.snap(new_offset.x), self.view.snap(new_offset.y));
+            }
+            let snap_distance = KEYFRAME_ORIGIN_SNAP_DISTANCE / zoom;
+            if (new_offset.x as f32).abs() <= snap_distance {
+                new_offset.x = 0;
+            }
+            if (new_offset.y as f32).abs() <= snap_distance {
+                new_offset.y = 0;
+            }
+        }
 
         let animation_frame = self
             .sheet
@@ -658,6 +1785,50 @@ impl Document {
         self.select_hitbox(&hitbox_name)
     }
 
+    // The copy is offset so it does not hide the original, and picked up by the mouse
+    pub fn duplicate_hitbox(&mut self) -> Result<(), Error> {
+        let (frame_path, hitbox_name) = match &self.view.selection {
+            Some(Selection::Hitbox(f, h)) => Some((f.clone(), h.last_touched.clone())),
+            _ => None,
+        }
+        .ok_or(StateError::NothingSelected)?;
+        let frame = self
+            .sheet
+            .get_frame_mut(&frame_path)
+            .ok_or(StateError::FrameNotInDocument)?;
+        let mut hitbox = frame
+            .get_hitbox(&hitbox_name)
+            .ok_or(StateError::HitboxNotInFrame)?
+            .clone();
+        hitbox.set_position(hitbox.get_position() + vec2(4, 4));
+        let new_hitbox_name = frame.insert_hitbox(hitbox).get_name().to_owned();
+        self.begin_hitbox_drag(new_hitbox_name)
+    }
+
+    // Frame centers follow the same convention as `AnimationFrame::flip_point`
+    pub fn mirror_hitbox(&mut self, frame_size: Vector2D<u32>) -> Result<(), Error> {
+        let (frame_path, hitbox_name) = match &self.view.selection {
+            Some(Selection::Hitbox(f, h)) => Some((f.clone(), h.last_touched.clone())),
+            _ => None,
+        }
+        .ok_or(StateError::NothingSelected)?;
+        let frame = self
+            .sheet
+            .get_frame_mut(&frame_path)
+            .ok_or(StateError::FrameNotInDocument)?;
+        let mut hitbox = frame
+            .get_hitbox(&hitbox_name)
+            .ok_or(StateError::HitboxNotInFrame)?
+            .clone();
+        // Mirrors about the frame anchor, which is the frame center unless moved
+        let rectangle = hitbox.get_rectangle();
+        let anchor = frame.get_anchor();
+        let mirrored_x = 2 * anchor.x + frame_size.x as i32 % 2 - rectangle.max_x();
+        hitbox.set_position(vec2(mirrored_x, rectangle.min_y()));
+        let new_hitbox_name = frame.insert_hitbox(hitbox).get_name().to_owned();
+        self.select_hitbox(new_hitbox_name)
+    }
+
     pub fn begin_hitbox_scale<T: AsRef<str>>(
         &mut self,
         hitbox_name: T,
@@ -696,6 +1867,7 @@ impl Document {
         &mut self,
         mut mouse_delta: Vector2D<f32>,
         preserve_aspect_ratio: bool,
+        allow_snapping: bool,
     ) -> Result<(), Error> {
         use ResizeAxis::*;
 
@@ -736,7 +1908,7 @@ impl Document {
         let zoom = self.view.get_workbench_zoom_factor();
         let mouse_delta = (mouse_delta / zoom).round().to_i32();
 
-        let new_hitbox = Rect::from_points(match axis {
+        let mut corners = match axis {
             NW => vec![
                 initial_hitbox.bottom_right(),
                 initial_hitbox.origin + mouse_delta,
@@ -781,7 +1953,18 @@ impl Document {
                     initial_hitbox.max_y(),
                 ),
             ],
-        });
+        };
+
+        // Only the coordinates moved by the handle are snapped
+        if allow_snapping && self.view.snap_to_grid {
+            if axis != N && axis != S {
+                corners[1].x = self.view.snap(corners[1].x);
+            }
+            if axis != W && axis != E {
+                corners[1].y = self.view.snap(corners[1].y);
+            }
+        }
+        let new_hitbox = Rect::from_points(corners);
 
         let hitbox_name = self
             .transient
@@ -796,6 +1979,26 @@ impl Document {
             .get_hitbox_mut(&hitbox_name)
             .ok_or(StateError::InvalidHitboxIndex)?;
 
+        let new_hitbox = if hitbox.is_circle() {
+            // Circles stay anchored to the side or corner opposite to the handle
+            let diameter = match axis {
+                N | S => new_hitbox.size.height,
+                W | E => new_hitbox.size.width,
+                _ => std::cmp::max(new_hitbox.size.width, new_hitbox.size.height),
+            };
+            let left = match axis {
+                NW | SW | W => new_hitbox.max_x() - diameter,
+                _ => new_hitbox.min_x(),
+            };
+            let top = match axis {
+                NW | NE | N => new_hitbox.max_y() - diameter,
+                _ => new_hitbox.min_y(),
+            };
+            rect(left, top, diameter, diameter)
+        } else {
+            new_hitbox
+        };
+
         hitbox.set_position(new_hitbox.origin.to_vector());
         hitbox.set_size(new_hitbox.size.to_u32().to_vector());
 
@@ -823,21 +2026,34 @@ impl Document {
         }
         .ok_or(StateError::NotEditingAnyFrame)?;
 
-        let hitbox_position;
+        // Dragging a selected hitbox moves the whole selection along with it
+        let hitbox_name = hitbox_name.as_ref().to_owned();
+        let dragged_hitboxes = match &self.view.selection {
+            Some(Selection::Hitbox(p, n)) if *p == frame_path && n.contains(&hitbox_name) => {
+                n.items.clone()
+            }
+            _ => vec![hitbox_name.clone()],
+        };
+
+        let mut initial_offsets = Vec::new();
         {
             let frame = self
                 .sheet
                 .get_frame(&frame_path)
                 .ok_or(StateError::FrameNotInDocument)?;
-            let hitbox = frame
-                .get_hitbox(&hitbox_name)
-                .ok_or(StateError::InvalidHitboxIndex)?;
-            hitbox_position = hitbox.get_position();
+            for name in dragged_hitboxes {
+                let hitbox = frame
+                    .get_hitbox(&name)
+                    .ok_or(StateError::InvalidHitboxIndex)?;
+                initial_offsets.push((name, hitbox.get_position()));
+            }
         }
 
-        self.transient.workbench_hitbox_being_dragged = Some(hitbox_name.as_ref().to_owned());
-        self.transient.workbench_hitbox_drag_initial_offset = hitbox_position;
-        self.select_hitbox(hitbox_name)?;
+        if initial_offsets.len() == 1 {
+            self.select_hitbox(&hitbox_name)?;
+        }
+        self.transient.workbench_hitbox_being_dragged = Some(hitbox_name);
+        self.transient.workbench_hitbox_drag_initial_offsets = initial_offsets;
 
         Ok(())
     }
@@ -846,6 +2062,7 @@ impl Document {
         &mut self,
         mut mouse_delta: Vector2D<f32>,
         both_axis: bool,
+        allow_snapping: bool,
     ) -> Result<(), Error> {
         let zoom = self.view.get_workbench_zoom_factor();
 
@@ -862,7 +2079,13 @@ impl Document {
             .cloned()
             .ok_or(StateError::NotDraggingAHitbox)?;
 
-        let old_offset = self.transient.workbench_hitbox_drag_initial_offset;
+        let old_offset = self
+            .transient
+            .workbench_hitbox_drag_initial_offsets
+            .iter()
+            .find(|(n, _)| *n == hitbox_name)
+            .map(|(_, o)| *o)
+            .ok_or(StateError::NotDraggingAHitbox)?;
 
         if !both_axis {
             if mouse_delta.x.abs() > mouse_delta.y.abs() {
@@ -872,73 +2095,167 @@ impl Document {
             }
         }
 
-        let new_offset = (old_offset.to_f32() + mouse_delta / zoom).floor().to_i32();
+        let mut new_offset = (old_offset.to_f32() + mouse_delta / zoom).floor().to_i32();
+        if allow_snapping && self.view.snap_to_grid {
+            new_offset = vec2(self.view.snap(new_offset.x), self.view.snap(new_offset.y));
+        }
 
-        let hitbox = self
+        // Snapping applies to the dragged hitbox, others keep their relative position
+        let delta = new_offset - old_offset;
+        let frame = self
             .sheet
             .get_frame_mut(frame_path)
-            .ok_or(StateError::FrameNotInDocument)?
-            .get_hitbox_mut(&hitbox_name)
-            .ok_or(StateError::InvalidHitboxIndex)?;
-        hitbox.set_position(new_offset);
+            .ok_or(StateError::FrameNotInDocument)?;
+        for (name, initial_offset) in &self.transient.workbench_hitbox_drag_initial_offsets {
+            let hitbox = frame
+                .get_hitbox_mut(name)
+                .ok_or(StateError::InvalidHitboxIndex)?;
+            hitbox.set_position(*initial_offset + delta);
+        }
 
         Ok(())
     }
 
+    // Releasing the mouse also clicks the dragged hitbox, so the dragged set is selected again
     pub fn end_hitbox_drag(&mut self) {
-        self.transient.workbench_hitbox_drag_initial_offset = Vector2D::<i32>::zero();
+        if let (Some(WorkbenchItem::Frame(p)), Some(dragged)) = (
+            &self.view.workbench_item,
+            &self.transient.workbench_hitbox_being_dragged,
+        ) {
+            let names = self
+                .transient
+                .workbench_hitbox_drag_initial_offsets
+                .iter()
+                .map(|(n, _)| n.clone())
+                .collect();
+            if let Some(h) = MultiSelection::with_items(names, Some(dragged.clone())) {
+                self.view.selection = Some(Selection::Hitbox(p.clone(), h));
+            }
+        }
+        self.transient.workbench_hitbox_drag_initial_offsets = Vec::new();
         self.transient.workbench_hitbox_being_dragged = None;
     }
 
-    pub fn toggle_playback(&mut self) -> Result<(), Error> {
-        let mut new_timeline_clock = self.view.timeline_clock;
-        {
-            let animation = self.get_workbench_animation()?;
-
-            if !self.persistent.timeline_is_playing {
-                if let Some(d) = animation.get_duration() {
-                    if d > 0
-                        && !animation.is_looping()
-                        && self.view.timeline_clock.as_millis() >= u128::from(d)
-                    {
-                        new_timeline_clock = Duration::new(0, 0);
-                    }
-                }
-            }
+    pub fn begin_anchor_drag(&mut self) -> Result<(), Error> {
+        let frame_path = match &self.view.workbench_item {
+            Some(WorkbenchItem::Frame(s)) => Some(s.to_owned()),
+            _ => None,
         }
+        .ok_or(StateError::NotEditingAnyFrame)?;
 
-        self.persistent.timeline_is_playing = !self.persistent.timeline_is_playing;
-        self.view.timeline_clock = new_timeline_clock;
+        let anchor = self
+            .sheet
+            .get_frame(&frame_path)
+            .ok_or(StateError::FrameNotInDocument)?
+            .get_anchor();
+
+        self.transient.workbench_anchor_being_dragged = true;
+        self.transient.workbench_anchor_drag_initial_offset = anchor;
 
         Ok(())
     }
 
-    pub fn snap_to_previous_frame(&mut self) -> Result<(), Error> {
-        let clock = {
-            let animation = self.get_workbench_animation()?;
+    pub fn update_anchor_drag(
+        &mut self,
+        mut mouse_delta: Vector2D<f32>,
+        both_axis: bool,
+    ) -> Result<(), Error> {
+        let zoom = self.view.get_workbench_zoom_factor();
 
-            if animation.get_num_frames() == 0 {
-                return Ok(());
-            }
+        let frame_path = match &self.view.workbench_item {
+            Some(WorkbenchItem::Frame(p)) => Some(p.to_owned()),
+            _ => None,
+        }
+        .ok_or(StateError::NotEditingAnyFrame)?;
 
-            let mut cursor = 0 as u64;
-            let now = self.view.timeline_clock.as_millis() as u64;
-            let frame_times: Vec<u64> = animation
-                .frames_iter()
-                .map(|f| {
-                    let t = cursor;
-                    cursor += u64::from(f.get_duration());
-                    t
-                })
-                .collect();
+        if !self.transient.workbench_anchor_being_dragged {
+            return Err(StateError::NotDraggingAnAnchor.into());
+        }
 
-            match frame_times.iter().rev().find(|t1| **t1 < now) {
-                Some(t1) => *t1,
-                None => match frame_times.iter().next() {
-                    Some(t) => *t,
-                    None => 0,
-                },
-            }
+        let old_offset = self.transient.workbench_anchor_drag_initial_offset;
+
+        if !both_axis {
+            if mouse_delta.x.abs() > mouse_delta.y.abs() {
+                mouse_delta.y = 0.0;
+            } else {
+                mouse_delta.x = 0.0;
+            }
+        }
+
+        let new_offset = (old_offset.to_f32() + mouse_delta / zoom).floor().to_i32();
+
+        self.sheet
+            .get_frame_mut(frame_path)
+            .ok_or(StateError::FrameNotInDocument)?
+            .set_anchor(new_offset);
+
+        Ok(())
+    }
+
+    pub fn end_anchor_drag(&mut self) {
+        self.transient.workbench_anchor_drag_initial_offset = Vector2D::<i32>::zero();
+        self.transient.workbench_anchor_being_dragged = false;
+    }
+
+    pub fn toggle_playback(&mut self) -> Result<(), Error> {
+        let mut new_timeline_clock = self.view.timeline_clock;
+        {
+            let animation = self.get_workbench_animation()?;
+
+            if !self.persistent.timeline_is_playing {
+                if let Some(d) = animation.get_duration() {
+                    if d > 0 && animation.get_loop_mode() == LoopMode::Once {
+                        let clock_ms = self.view.timeline_clock.as_millis();
+                        match self.persistent.playback_direction {
+                            PlaybackDirection::Forward if clock_ms >= u128::from(d) => {
+                                new_timeline_clock = Duration::new(0, 0);
+                            }
+                            PlaybackDirection::Backward if clock_ms == 0 => {
+                                new_timeline_clock = Duration::from_millis(u64::from(d));
+                            }
+                            _ => (),
+                        }
+                    }
+                }
+            }
+        }
+
+        self.persistent.timeline_is_playing = !self.persistent.timeline_is_playing;
+        self.view.timeline_clock = new_timeline_clock;
+
+        Ok(())
+    }
+
+    pub fn toggle_playback_direction(&mut self) {
+        self.persistent.playback_direction = match self.persistent.playback_direction {
+            PlaybackDirection::Forward => PlaybackDirection::Backward,
+            PlaybackDirection::Backward => PlaybackDirection::Forward,
+        };
+        self.persistent.ping_pong_returning = false;
+    }
+
+    pub fn set_playback_speed(&mut self, speed: f32) {
+        self.persistent.playback_speed = speed.max(MIN_PLAYBACK_SPEED).min(MAX_PLAYBACK_SPEED);
+    }
+
+    pub fn snap_to_previous_frame(&mut self) -> Result<(), Error> {
+        let clock = {
+            let animation = self.get_workbench_animation()?;
+
+            if animation.get_num_frames() == 0 {
+                return Ok(());
+            }
+
+            let now = self.view.timeline_clock.as_millis() as u64;
+            let frame_times = animation.get_frame_times();
+
+            match frame_times.iter().rev().find(|t1| **t1 < now) {
+                Some(t1) => *t1,
+                None => match frame_times.iter().next() {
+                    Some(t) => *t,
+                    None => 0,
+                },
+            }
         };
 
         self.update_timeline_scrub(Duration::from_millis(clock))
@@ -952,16 +2269,8 @@ impl Document {
                 return Ok(());
             }
 
-            let mut cursor = 0 as u64;
             let now = self.view.timeline_clock.as_millis() as u64;
-            let frame_times: Vec<u64> = animation
-                .frames_iter()
-                .map(|f| {
-                    let t = cursor;
-                    cursor += u64::from(f.get_duration());
-                    t
-                })
-                .collect();
+            let frame_times = animation.get_frame_times();
 
             match frame_times.iter().find(|t1| **t1 > now) {
                 Some(t1) => *t1,
@@ -975,13 +2284,148 @@ impl Document {
         self.update_timeline_scrub(Duration::from_millis(clock))
     }
 
-    pub fn toggle_looping(&mut self) -> Result<(), Error> {
+    // Stops at the first and last keyframes, even for looping animations
+    pub fn step_keyframe(&mut self, delta: i32) -> Result<(), Error> {
+        let clock = {
+            let animation = self.get_workbench_animation()?;
+
+            let (index, _) = match animation.get_frame_at(self.view.timeline_clock) {
+                Some(f) => f,
+                None => return Ok(()),
+            };
+
+            let last_index = animation.get_num_frames() as i64 - 1;
+            let new_index = (index as i64 + i64::from(delta)).max(0).min(last_index);
+            animation.get_frame_times()[new_index as usize]
+        };
+
+        self.persistent.timeline_is_playing = false;
+        self.update_timeline_scrub(Duration::from_millis(clock))
+    }
+
+    pub fn step_timeline_clock(&mut self, delta_ms: i64) -> Result<(), Error> {
+        let duration = {
+            let animation = self.get_workbench_animation()?;
+            match animation.get_duration() {
+                Some(d) if d > 0 => i64::from(d),
+                _ => return Ok(()),
+            }
+        };
+        // The end of a looping animation is the start of its next loop, so the last
+        // millisecond is as far as stepping goes
+        let now = self.view.timeline_clock.as_millis() as i64;
+        let clock = (now + delta_ms).max(0).min(duration - 1);
+
+        self.persistent.timeline_is_playing = false;
+        self.update_timeline_scrub(Duration::from_millis(clock as u64))
+    }
+
+    pub fn set_animation_frame_rate(&mut self, frame_rate: Option<f32>) -> Result<(), Error> {
         let animation = self.get_workbench_animation_mut()?;
-        animation.set_is_looping(!animation.is_looping());
+        animation.set_frame_rate(frame_rate);
+        Ok(())
+    }
+
+    pub fn set_loop_mode(&mut self, loop_mode: LoopMode) -> Result<(), Error> {
+        let animation = self.get_workbench_animation_mut()?;
+        animation.set_loop_mode(loop_mode);
+        Ok(())
+    }
+
+    // Preview range of the current animation in milliseconds, missing markers default to
+    // the start and end of the animation
+    pub fn get_loop_region(&self) -> Option<(u64, u64)> {
+        if self.view.timeline_loop_in.is_none() && self.view.timeline_loop_out.is_none() {
+            return None;
+        }
+        let animation = self.get_workbench_animation().ok()?;
+        let duration = u64::from(animation.get_duration()?);
+        let start = self
+            .view
+            .timeline_loop_in
+            .map(|t| t.as_millis() as u64)
+            .unwrap_or(0);
+        let end = self
+            .view
+            .timeline_loop_out
+            .map(|t| t.as_millis() as u64)
+            .unwrap_or(duration)
+            .min(duration);
+        if start < end {
+            Some((start, end))
+        } else {
+            None
+        }
+    }
+
+    // Markers are placed at the playhead, a marker crossing the other one removes it
+    pub fn set_loop_marker(&mut self, marker: LoopMarker) -> Result<(), Error> {
+        self.get_workbench_animation()?;
+        let now = self.view.timeline_clock;
+        match marker {
+            LoopMarker::In => {
+                self.view.timeline_loop_in = Some(now);
+                if self.view.timeline_loop_out.map_or(false, |t| t <= now) {
+                    self.view.timeline_loop_out = None;
+                }
+            }
+            LoopMarker::Out => {
+                self.view.timeline_loop_out = Some(now);
+                if self.view.timeline_loop_in.map_or(false, |t| t >= now) {
+                    self.view.timeline_loop_in = None;
+                }
+            }
+        };
+        Ok(())
+    }
+
+    pub fn clear_loop_region(&mut self) {
+        self.view.timeline_loop_in = None;
+        self.view.timeline_loop_out = None;
+    }
+
+    pub fn begin_loop_marker_drag(&mut self, marker: LoopMarker) -> Result<(), Error> {
+        self.get_workbench_animation()?;
+        self.transient.timeline_loop_marker_being_dragged = Some(marker);
         Ok(())
     }
 
-    pub fn update_timeline_scrub(&mut self, new_time: Duration) -> Result<(), Error> {
+    // Markers cannot be dragged past each other or past the end of the animation
+    pub fn update_loop_marker_drag(&mut self, new_time: Duration) -> Result<(), Error> {
+        let marker = self
+            .transient
+            .timeline_loop_marker_being_dragged
+            .ok_or(StateError::NotDraggingALoopMarker)?;
+        let duration = u64::from(self.get_workbench_animation()?.get_duration().unwrap_or(0));
+        let new_time = (new_time.as_millis() as u64).min(duration);
+        match marker {
+            LoopMarker::In => {
+                let end = self
+                    .view
+                    .timeline_loop_out
+                    .map(|t| t.as_millis() as u64)
+                    .unwrap_or(duration);
+                let new_time = new_time.min(end.saturating_sub(1));
+                self.view.timeline_loop_in = Some(Duration::from_millis(new_time));
+            }
+            LoopMarker::Out => {
+                let start = self
+                    .view
+                    .timeline_loop_in
+                    .map(|t| t.as_millis() as u64)
+                    .unwrap_or(0);
+                let new_time = new_time.max(start + 1);
+                self.view.timeline_loop_out = Some(Duration::from_millis(new_time));
+            }
+        };
+        Ok(())
+    }
+
+    pub fn update_timeline_scrub(&mut self, mut new_time: Duration) -> Result<(), Error> {
+        if let Some((start, end)) = self.get_loop_region() {
+            let new_time_ms = (new_time.as_millis() as u64).max(start).min(end - 1);
+            new_time = Duration::from_millis(new_time_ms);
+        }
         let animation = self.get_workbench_animation()?;
         let (index, _) = animation
             .get_frame_at(new_time)
@@ -991,6 +2435,51 @@ impl Document {
         Ok(())
     }
 
+    pub fn begin_workbench_scrub(&mut self) -> Result<(), Error> {
+        self.get_workbench_animation()?;
+        self.transient.workbench_scrubbing = true;
+        self.transient.workbench_scrub_initial_clock = self.view.timeline_clock;
+        Ok(())
+    }
+
+    pub fn end_workbench_scrub(&mut self) {
+        self.transient.workbench_scrubbing = false;
+        self.transient.workbench_scrub_initial_clock = Duration::new(0, 0);
+    }
+
+    // Measurements only live in transient state, they never reach the sheet or the history
+    pub fn begin_ruler(&mut self, start: Vector2D<i32>) {
+        self.transient.workbench_ruler_start = Some(start);
+        self.transient.workbench_ruler_end = start;
+    }
+
+    pub fn update_ruler(&mut self, end: Vector2D<i32>) {
+        if self.transient.workbench_ruler_start.is_some() {
+            self.transient.workbench_ruler_end = end;
+        }
+    }
+
+    pub fn end_ruler(&mut self) {
+        self.transient.workbench_ruler_start = None;
+        self.transient.workbench_ruler_end = vec2(0, 0);
+    }
+
+    pub fn toggle_hitbox_shape(&mut self) -> Result<(), Error> {
+        let (frame_path, hitbox_name) = match &self.view.selection {
+            Some(Selection::Hitbox(f, h)) => (f.clone(), h.last_touched.clone()),
+            _ => return Ok(()),
+        };
+        let hitbox = self
+            .sheet
+            .get_frame_mut(frame_path)
+            .ok_or(StateError::FrameNotInDocument)?
+            .get_hitbox_mut(&hitbox_name)
+            .ok_or(StateError::HitboxNotInFrame)?;
+        let is_circle = hitbox.is_circle();
+        hitbox.set_is_circle(!is_circle);
+        Ok(())
+    }
+
     pub fn nudge_selection(&mut self, direction: Vector2D<i32>, large: bool) -> Result<(), Error> {
         let amplitude = if large { 10 } else { 1 };
         let offset = direction * amplitude;
@@ -998,25 +2487,195 @@ impl Document {
             Some(Selection::Animation(_)) => {}
             Some(Selection::Frame(_)) => {}
             Some(Selection::Hitbox(f, h)) => {
-                let hitbox = self
+                let frame = self
                     .sheet
                     .get_frame_mut(f)
-                    .ok_or(StateError::FrameNotInDocument)?
-                    .get_hitbox_mut(&h)
-                    .ok_or(StateError::InvalidHitboxIndex)?;
-                hitbox.set_position(hitbox.get_position() + offset);
+                    .ok_or(StateError::FrameNotInDocument)?;
+                for h in &h.items {
+                    let hitbox = frame
+                        .get_hitbox_mut(h)
+                        .ok_or(StateError::InvalidHitboxIndex)?;
+                    hitbox.set_position(hitbox.get_position() + offset);
+                }
             }
             Some(Selection::AnimationFrame(a, af)) => {
-                let animation_frame = self
+                let animation = self
                     .sheet
+                    .get_animation_mut(a)
+                    .ok_or(StateError::AnimationNotInDocument)?;
+                for af in &af.items {
+                    let animation_frame = animation
+                        .get_frame_mut(*af)
+                        .ok_or(StateError::InvalidAnimationFrameIndex)?;
+                    animation_frame.set_offset(animation_frame.get_offset() + offset);
+                }
+            }
+            None => {}
+        };
+        Ok(())
+    }
+
+    // Returns None when fewer than two hitboxes are selected
+    fn get_selected_hitbox_rectangles(&self) -> Result<Option<Vec<(String, Rect<i32>)>>, Error> {
+        let (frame_path, hitboxes) = match &self.view.selection {
+            Some(Selection::Hitbox(f, h)) if h.items.len() > 1 => (f, h),
+            _ => return Ok(None),
+        };
+        let frame = self
+            .sheet
+            .get_frame(frame_path)
+            .ok_or(StateError::FrameNotInDocument)?;
+        let mut rectangles = Vec::new();
+        for name in &hitboxes.items {
+            let hitbox = frame
+                .get_hitbox(name)
+                .ok_or(StateError::InvalidHitboxIndex)?;
+            rectangles.push((name.clone(), hitbox.get_rectangle()));
+        }
+        Ok(Some(rectangles))
+    }
+
+    fn set_selected_hitbox_positions(
+        &mut self,
+        positions: Vec<(String, Vector2D<i32>)>,
+    ) -> Result<(), Error> {
+        if let Some(Selection::Hitbox(f, _)) = &self.view.selection {
+            let frame = self
+                .sheet
+                .get_frame_mut(f)
+                .ok_or(StateError::FrameNotInDocument)?;
+            for (name, position) in positions {
+                frame
+                    .get_hitbox_mut(&name)
+                    .ok_or(StateError::InvalidHitboxIndex)?
+                    .set_position(position);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn align_hitboxes(&mut self, alignment: HitboxAlignment) -> Result<(), Error> {
+        if let Some(rectangles) = self.get_selected_hitbox_rectangles()? {
+            let positions = align_rectangles(&rectangles, alignment);
+            self.set_selected_hitbox_positions(positions)?;
+        }
+        Ok(())
+    }
+
+    pub fn distribute_hitboxes(&mut self, distribution: HitboxDistribution) -> Result<(), Error> {
+        if let Some(rectangles) = self.get_selected_hitbox_rectangles()? {
+            let positions = distribute_rectangles(&rectangles, distribution);
+            self.set_selected_hitbox_positions(positions)?;
+        }
+        Ok(())
+    }
+
+    // Only applies to the last touched item, which is the one the selection window shows
+    pub fn set_selection_position(&mut self, position: Vector2D<i32>) -> Result<(), Error> {
+        match &self.view.selection {
+            Some(Selection::Hitbox(f, h)) => {
+                self.sheet
+                    .get_frame_mut(f)
+                    .ok_or(StateError::FrameNotInDocument)?
+                    .get_hitbox_mut(&h.last_touched)
+                    .ok_or(StateError::InvalidHitboxIndex)?
+                    .set_position(position);
+            }
+            Some(Selection::AnimationFrame(a, af)) => {
+                self.sheet
                     .get_animation_mut(a)
                     .ok_or(StateError::AnimationNotInDocument)?
+                    .get_frame_mut(af.last_touched)
+                    .ok_or(StateError::InvalidAnimationFrameIndex)?
+                    .set_offset(position);
+            }
+            _ => {}
+        };
+        Ok(())
+    }
+
+    // Applies to every selected keyframe, durations are at least 1ms like when dragging them
+    pub fn set_animation_frame_duration(&mut self, duration: u32) -> Result<(), Error> {
+        if let Some(Selection::AnimationFrame(a, af)) = &self.view.selection {
+            let animation = self
+                .sheet
+                .get_animation_mut(a)
+                .ok_or(StateError::AnimationNotInDocument)?;
+            if animation.get_frame_rate().is_some() {
+                return Err(StateError::DurationsSetByFrameRate.into());
+            }
+            for index in &af.items {
+                animation
+                    .get_frame_mut(*index)
+                    .ok_or(StateError::InvalidAnimationFrameIndex)?
+                    .set_duration(std::cmp::max(1, duration));
+            }
+        }
+        Ok(())
+    }
+
+    // The playhead is kept within the shortened animation
+    pub fn set_all_keyframe_durations(&mut self, duration: u32) -> Result<(), Error> {
+        let animation = self.get_workbench_animation_mut()?;
+        if animation.get_frame_rate().is_some() {
+            return Err(StateError::DurationsSetByFrameRate.into());
+        }
+        for animation_frame in animation.frames_iter_mut() {
+            animation_frame.set_duration(std::cmp::max(1, duration));
+        }
+        let total_duration = u64::from(animation.get_duration().unwrap_or(0));
+        if self.view.timeline_clock.as_millis() > u128::from(total_duration) {
+            self.view.timeline_clock = Duration::from_millis(total_duration);
+        }
+        Ok(())
+    }
+
+    // Selected keyframes stay selected at their new index
+    pub fn reverse_animation(&mut self) -> Result<(), Error> {
+        let animation = self.get_workbench_animation_mut()?;
+        animation.reverse();
+        let animation_name = animation.get_name().to_owned();
+        let last_index = animation.get_num_frames().saturating_sub(1);
+
+        if let Some(Selection::AnimationFrame(a, af)) = &mut self.view.selection {
+            if *a == animation_name {
+                af.last_touched = last_index - af.last_touched;
+                for index in af.items.iter_mut() {
+                    *index = last_index - *index;
+                }
+            }
+        }
+
+        self.view.timeline_clock = Duration::new(0, 0);
+        Ok(())
+    }
+
+    pub fn set_hitbox_size(&mut self, size: Vector2D<u32>) -> Result<(), Error> {
+        if let Some(Selection::Hitbox(f, h)) = &self.view.selection {
+            self.sheet
+                .get_frame_mut(f)
+                .ok_or(StateError::FrameNotInDocument)?
+                .get_hitbox_mut(&h.last_touched)
+                .ok_or(StateError::InvalidHitboxIndex)?
+                .set_size(size);
+        }
+        Ok(())
+    }
+
+    pub fn flip_selection(&mut self, flip_x: bool, flip_y: bool) -> Result<(), Error> {
+        if let Some(Selection::AnimationFrame(a, af)) = &self.view.selection {
+            let animation = self
+                .sheet
+                .get_animation_mut(a)
+                .ok_or(StateError::AnimationNotInDocument)?;
+            for af in &af.items {
+                let animation_frame = animation
                     .get_frame_mut(*af)
                     .ok_or(StateError::InvalidAnimationFrameIndex)?;
-                animation_frame.set_offset(animation_frame.get_offset() + offset);
+                animation_frame.set_flip_x(animation_frame.get_flip_x() != flip_x);
+                animation_frame.set_flip_y(animation_frame.get_flip_y() != flip_y);
             }
-            None => {}
-        };
+        }
         Ok(())
     }
 
@@ -1024,34 +2683,62 @@ impl Document {
         match &self.view.selection {
             Some(Selection::Animation(a)) => {
                 self.sheet.delete_animation(&a);
-                if self.transient.item_being_renamed == Some(RenameItem::Animation(a.clone())) {
+                if self.transient.item_being_renamed == Some(RenameItem::Animation(a.clone()))
+                    || self.transient.item_being_renamed
+                        == Some(RenameItem::AnimationGroup(a.clone()))
+                    || self.transient.item_being_renamed
+                        == Some(RenameItem::AnimationDirection(a.clone()))
+                    || self.transient.item_being_renamed
+                        == Some(RenameItem::AnimationTag(a.clone()))
+                {
                     self.transient.item_being_renamed = None;
                     self.transient.rename_buffer = None;
                 }
             }
-            Some(Selection::Frame(f)) => {
-                self.sheet.delete_frame(&f);
-                if self.transient.content_frame_being_dragged == Some(f.clone()) {
-                    self.transient.content_frame_being_dragged = None;
+            Some(Selection::Frame(paths)) => {
+                for f in &paths.items {
+                    self.sheet.delete_frame(f);
+                    if self.transient.content_frame_being_dragged == Some(f.clone()) {
+                        self.transient.content_frame_being_dragged = None;
+                    }
                 }
             }
-            Some(Selection::Hitbox(f, h)) => {
-                self.sheet.delete_hitbox(&f, &h);
-                if self.view.workbench_item == Some(WorkbenchItem::Frame(f.clone())) {
-                    if self.transient.workbench_hitbox_being_dragged == Some(h.to_owned()) {
-                        self.transient.workbench_hitbox_being_dragged = None;
-                    }
-                    if self.transient.workbench_hitbox_being_scaled == Some(h.to_owned()) {
-                        self.transient.workbench_hitbox_being_scaled = None;
+            Some(Selection::Hitbox(f, hitboxes)) => {
+                for h in &hitboxes.items {
+                    self.sheet.delete_hitbox(&f, &h);
+                    if self.view.workbench_item == Some(WorkbenchItem::Frame(f.clone())) {
+                        if self.transient.workbench_hitbox_being_dragged == Some(h.to_owned()) {
+                            self.transient.workbench_hitbox_being_dragged = None;
+                            self.transient.workbench_hitbox_drag_initial_offsets = Vec::new();
+                        }
+                        self.transient
+                            .workbench_hitbox_drag_initial_offsets
+                            .retain(|(n, _)| n != h);
+                        if self.transient.workbench_hitbox_being_scaled == Some(h.to_owned()) {
+                            self.transient.workbench_hitbox_being_scaled = None;
+                        }
                     }
                 }
             }
-            Some(Selection::AnimationFrame(a, af)) => {
-                self.sheet.delete_animation_frame(a, *af);
-                if self.view.workbench_item == Some(WorkbenchItem::Animation(a.clone()))
-                    && self.transient.workbench_animation_frame_being_dragged == Some(*af)
-                {
-                    self.transient.workbench_animation_frame_being_dragged = None;
+            Some(Selection::AnimationFrame(a, animation_frames)) => {
+                // Deleting from the back keeps the remaining indices valid
+                let mut indices = animation_frames.items.clone();
+                indices.sort_unstable();
+                for af in indices.into_iter().rev() {
+                    self.sheet.delete_animation_frame(a, af);
+                    if self.view.workbench_item == Some(WorkbenchItem::Animation(a.clone()))
+                        && self.transient.workbench_animation_frame_being_dragged == Some(af)
+                    {
+                        self.transient.workbench_animation_frame_being_dragged = None;
+                    }
+                    if self.transient.item_being_renamed
+                        == Some(RenameItem::AnimationFrameSound(a.clone(), af))
+                        || self.transient.item_being_renamed
+                            == Some(RenameItem::AnimationFrameUserData(a.clone(), af))
+                    {
+                        self.transient.item_being_renamed = None;
+                        self.transient.rename_buffer = None;
+                    }
                 }
             }
             None => {}
@@ -1062,7 +2749,9 @@ impl Document {
     pub fn begin_rename_selection(&mut self) -> Result<(), Error> {
         match &self.view.selection {
             Some(Selection::Animation(a)) => self.begin_animation_rename(a.clone())?,
-            Some(Selection::Hitbox(f, h)) => self.begin_hitbox_rename(f.clone(), h.clone())?,
+            Some(Selection::Hitbox(f, h)) => {
+                self.begin_hitbox_rename(f.clone(), h.last_touched.clone())?
+            }
             Some(Selection::Frame(_f)) => (),
             Some(Selection::AnimationFrame(_a, _af)) => (),
             None => {}
@@ -1070,6 +2759,60 @@ impl Document {
         Ok(())
     }
 
+    // Animations can only be selected one at a time, so groups are assigned one animation at a
+    // time as well
+    pub fn begin_set_animation_group(&mut self) -> Result<(), Error> {
+        match &self.view.selection {
+            Some(Selection::Animation(a)) => self.begin_animation_group_edit(a.clone())?,
+            _ => (),
+        };
+        Ok(())
+    }
+
+    pub fn begin_set_animation_direction(&mut self) -> Result<(), Error> {
+        match &self.view.selection {
+            Some(Selection::Animation(a)) => self.begin_animation_direction_edit(a.clone())?,
+            _ => (),
+        };
+        Ok(())
+    }
+
+    pub fn begin_add_animation_tag(&mut self) -> Result<(), Error> {
+        match &self.view.selection {
+            Some(Selection::Animation(a)) => self.begin_animation_tag_edit(a.clone())?,
+            _ => (),
+        };
+        Ok(())
+    }
+
+    pub fn remove_animation_tag<T: AsRef<str>>(&mut self, tag: T) -> Result<(), Error> {
+        match &self.view.selection {
+            Some(Selection::Animation(a)) => self.sheet.remove_animation_tag(a, tag)?,
+            _ => (),
+        };
+        Ok(())
+    }
+
+    pub fn begin_set_animation_frame_sound(&mut self) -> Result<(), Error> {
+        match &self.view.selection {
+            Some(Selection::AnimationFrame(a, af)) => {
+                self.begin_animation_frame_sound_edit(a.clone(), af.last_touched)?
+            }
+            _ => (),
+        };
+        Ok(())
+    }
+
+    pub fn begin_set_animation_frame_user_data(&mut self) -> Result<(), Error> {
+        match &self.view.selection {
+            Some(Selection::AnimationFrame(a, af)) => {
+                self.begin_animation_frame_user_data_edit(a.clone(), af.last_touched)?
+            }
+            _ => (),
+        };
+        Ok(())
+    }
+
     pub fn end_rename_selection(&mut self) -> Result<(), Error> {
         let new_name = self
             .transient
@@ -1093,6 +2836,59 @@ impl Document {
                     }
                 }
             }
+            Some(RenameItem::AnimationGroup(animation_name)) => {
+                let group = new_name.trim();
+                let group = if group.is_empty() {
+                    None
+                } else {
+                    Some(group.to_owned())
+                };
+                self.sheet.set_animation_group(&animation_name, group)?;
+            }
+            Some(RenameItem::AnimationDirection(animation_name)) => {
+                let direction = new_name.trim();
+                let direction = if direction.is_empty() {
+                    None
+                } else {
+                    Some(direction.to_owned())
+                };
+                self.sheet
+                    .set_animation_direction(&animation_name, direction)?;
+            }
+            Some(RenameItem::AnimationTag(animation_name)) => {
+                let tag = new_name.trim();
+                if !tag.is_empty() {
+                    self.sheet
+                        .add_animation_tag(&animation_name, tag.to_owned())?;
+                }
+            }
+            Some(RenameItem::AnimationFrameSound(animation_name, animation_frame_index)) => {
+                let sound = new_name.trim();
+                let sound = if sound.is_empty() {
+                    None
+                } else {
+                    Some(sound.to_owned())
+                };
+                self.sheet
+                    .get_animation_mut(&animation_name)
+                    .ok_or(StateError::AnimationNotInDocument)?
+                    .get_frame_mut(animation_frame_index)
+                    .ok_or(StateError::InvalidAnimationFrameIndex)?
+                    .set_sound(sound)?;
+            }
+            Some(RenameItem::AnimationFrameUserData(animation_name, animation_frame_index)) => {
+                let user_data = if new_name.trim().is_empty() {
+                    None
+                } else {
+                    Some(new_name)
+                };
+                self.sheet
+                    .get_animation_mut(&animation_name)
+                    .ok_or(StateError::AnimationNotInDocument)?
+                    .get_frame_mut(animation_frame_index)
+                    .ok_or(StateError::InvalidAnimationFrameIndex)?
+                    .set_user_data(user_data)?;
+            }
             Some(RenameItem::Hitbox(frame_path, old_name)) => {
                 if old_name != new_name {
                     if self
@@ -1107,11 +2903,17 @@ impl Document {
                         .get_frame_mut(&frame_path)
                         .ok_or(StateError::FrameNotInDocument)?
                         .rename_hitbox(&old_name, &new_name)?;
-                    if Some(Selection::Hitbox(frame_path.clone(), old_name.clone()))
-                        == self.view.selection
-                    {
-                        self.view.selection =
-                            Some(Selection::Hitbox(frame_path.clone(), new_name.clone()));
+                    if let Some(Selection::Hitbox(f, h)) = &self.view.selection {
+                        if *f == frame_path {
+                            let h = h.map(|h| {
+                                if *h == old_name {
+                                    new_name.clone()
+                                } else {
+                                    h.clone()
+                                }
+                            });
+                            self.view.selection = Some(Selection::Hitbox(f.clone(), h));
+                        }
                     }
                 }
             }
@@ -1176,6 +2978,75 @@ impl Document {
         Ok(())
     }
 
+    fn set_export_absolute_image_paths(&mut self, absolute_image_paths: bool) -> Result<(), Error> {
+        self.get_export_settings_edit_mut()?.absolute_image_paths = absolute_image_paths;
+        Ok(())
+    }
+
+    fn set_export_skip_texture(&mut self, skip_texture: bool) -> Result<(), Error> {
+        self.get_export_settings_edit_mut()?.skip_texture = skip_texture;
+        Ok(())
+    }
+
+    fn set_export_atlas_size(&mut self, atlas_size: Option<(u32, u32)>) -> Result<(), Error> {
+        self.get_export_settings_edit_mut()?.atlas_size = atlas_size;
+        Ok(())
+    }
+
+    fn set_export_max_size(&mut self, max_size: Option<(u32, u32)>) -> Result<(), Error> {
+        self.get_export_settings_edit_mut()?.max_size = max_size;
+        Ok(())
+    }
+
+    fn set_export_sort_frames_by_size(&mut self, sort: bool) -> Result<(), Error> {
+        self.get_export_settings_edit_mut()?.sort_frames_by_size = sort;
+        Ok(())
+    }
+
+    fn set_export_padding(&mut self, padding: u32) -> Result<(), Error> {
+        self.get_export_settings_edit_mut()?.padding = padding;
+        Ok(())
+    }
+
+    fn set_export_extrude(&mut self, extrude: u32) -> Result<(), Error> {
+        self.get_export_settings_edit_mut()?.extrude = extrude;
+        Ok(())
+    }
+
+    fn add_export_template_variable(&mut self) -> Result<(), Error> {
+        self.get_export_settings_edit_mut()?
+            .template_variables
+            .push(Default::default());
+        Ok(())
+    }
+
+    fn set_export_template_variable(
+        &mut self,
+        index: usize,
+        variable: TemplateVariable,
+    ) -> Result<(), Error> {
+        *self
+            .get_export_settings_edit_mut()?
+            .template_variables
+            .get_mut(index)
+            .ok_or(StateError::InvalidTemplateVariableIndex)? = variable;
+        Ok(())
+    }
+
+    fn remove_export_template_variable(&mut self, index: usize) -> Result<(), Error> {
+        let variables = &mut self.get_export_settings_edit_mut()?.template_variables;
+        if index >= variables.len() {
+            return Err(StateError::InvalidTemplateVariableIndex.into());
+        }
+        variables.remove(index);
+        Ok(())
+    }
+
+    fn set_export_pack_strategy(&mut self, strategy: PackStrategy) -> Result<(), Error> {
+        self.get_export_settings_edit_mut()?.pack_strategy = strategy;
+        Ok(())
+    }
+
     fn end_export_as(&mut self) -> Result<(), Error> {
         let export_settings = self.get_export_settings_edit_mut()?.clone();
         self.sheet.set_export_settings(export_settings);
@@ -1191,6 +3062,11 @@ impl Document {
         match command {
             MarkAsSaved(_, v) => new_document.persistent.disk_version = *v,
             EndImport(_, f) => new_document.sheet.add_frame(f),
+            EndSelectGridImage(_, i, s) => new_document.end_select_grid_image(i, *s),
+            EndImportAseprite(_, f, a) => new_document.end_import_aseprite(f, a),
+            SetGridImportTileSize(s) => new_document.set_grid_import_tile_size(*s)?,
+            EndGridImport => new_document.end_grid_import()?,
+            CancelGridImport => new_document.persistent.grid_import = None,
             BeginExportAs => new_document.begin_export_as(),
             CancelExportAs => new_document.cancel_export_as(),
             EndSetExportTextureDestination(_, d) => {
@@ -1203,20 +3079,41 @@ impl Document {
                 new_document.end_set_export_metadata_paths_root(d)?
             }
             EndSetExportFormat(_, f) => new_document.end_set_export_format(f.clone())?,
+            SetExportSkipTexture(s) => new_document.set_export_skip_texture(*s)?,
+            SetExportAbsoluteImagePaths(a) => new_document.set_export_absolute_image_paths(*a)?,
+            SetExportAtlasSize(s) => new_document.set_export_atlas_size(*s)?,
+            SetExportMaxSize(s) => new_document.set_export_max_size(*s)?,
+            SetExportSortFramesBySize(s) => new_document.set_export_sort_frames_by_size(*s)?,
+            SetExportPadding(p) => new_document.set_export_padding(*p)?,
+            SetExportExtrude(e) => new_document.set_export_extrude(*e)?,
+            SetExportPackStrategy(s) => new_document.set_export_pack_strategy(*s)?,
+            AddExportTemplateVariable => new_document.add_export_template_variable()?,
+            SetExportTemplateVariable(i, v) => {
+                new_document.set_export_template_variable(*i, v.clone())?
+            }
+            RemoveExportTemplateVariable(i) => new_document.remove_export_template_variable(*i)?,
             EndExportAs => new_document.end_export_as()?,
             SwitchToContentTab(t) => new_document.view.content_tab = *t,
+            SetAnimationTagFilter(t) => new_document.view.animation_tag_filter = t.clone(),
             ClearSelection => new_document.clear_selection(),
+            SelectAll => new_document.select_all()?,
+            InvertSelection => new_document.invert_selection()?,
             SelectFrame(p) => new_document.select_frame(&p)?,
+            ToggleFrameSelection(p) => new_document.toggle_frame_selection(&p)?,
+            SelectFrameRange(p) => new_document.select_frame_range(&p)?,
             SelectAnimation(a) => new_document.select_animation(&a)?,
             SelectHitbox(h) => new_document.select_hitbox(&h)?,
+            ToggleHitboxSelection(h) => new_document.toggle_hitbox_selection(&h)?,
+            SelectHitboxRange(h) => new_document.select_hitbox_range(&h)?,
             SelectAnimationFrame(af) => new_document.select_animation_frame(*af)?,
             SelectPrevious => new_document.select_previous()?,
             SelectNext => new_document.select_next()?,
             EditFrame(p) => new_document.edit_frame(&p)?,
             EditAnimation(a) => new_document.edit_animation(&a)?,
-            CreateAnimation => new_document.create_animation()?,
+            CreateAnimation(l) => new_document.create_animation(*l)?,
             BeginFrameDrag(f) => new_document.begin_frame_drag(f)?,
             EndFrameDrag => new_document.transient.content_frame_being_dragged = None,
+            InsertFramesAtPlayhead => new_document.insert_frames_at_playhead()?,
             InsertAnimationFrameBefore(f, n) => {
                 new_document.insert_animation_frame_before(f, *n)?
             }
@@ -1233,41 +3130,670 @@ impl Document {
             BeginAnimationFrameOffsetDrag(a) => {
                 new_document.begin_animation_frame_offset_drag(*a)?
             }
-            UpdateAnimationFrameOffsetDrag(o, b) => {
-                new_document.update_animation_frame_offset_drag(*o, *b)?
+            UpdateAnimationFrameOffsetDrag(o, b, s) => {
+                new_document.update_animation_frame_offset_drag(*o, *b, *s)?
             }
             EndAnimationFrameOffsetDrag => new_document.end_animation_frame_offset_drag(),
             WorkbenchZoomIn => new_document.view.workbench_zoom_in(),
             WorkbenchZoomOut => new_document.view.workbench_zoom_out(),
+            WorkbenchZoomInAt(c) => new_document.view.workbench_zoom_in_at(*c),
+            WorkbenchZoomOutAt(c) => new_document.view.workbench_zoom_out_at(*c),
+            WorkbenchFit(b, s) => new_document.view.workbench_fit(*b, *s),
             WorkbenchResetZoom => new_document.view.workbench_reset_zoom(),
             WorkbenchCenter => new_document.view.workbench_center(),
+            ToggleOnionSkin => new_document.view.toggle_onion_skin(),
+            ToggleSnapToGrid => new_document.view.snap_to_grid = !self.view.snap_to_grid,
+            ToggleAnimationHitboxes => {
+                new_document.view.show_animation_hitboxes = !self.view.show_animation_hitboxes
+            }
+            SetSnapGridSize(s) => new_document.view.snap_grid_size = *s,
             Pan(delta) => new_document.view.pan(*delta),
+            Paste(content) => new_document.paste(content)?,
             CreateHitbox(p) => new_document.create_hitbox(*p)?,
             BeginHitboxScale(h, a) => new_document.begin_hitbox_scale(&h, *a)?,
-            UpdateHitboxScale(delta, ar) => new_document.update_hitbox_scale(*delta, *ar)?,
+            UpdateHitboxScale(delta, ar, s) => new_document.update_hitbox_scale(*delta, *ar, *s)?,
             EndHitboxScale => new_document.end_hitbox_scale()?,
             BeginHitboxDrag(a) => new_document.begin_hitbox_drag(&a)?,
-            UpdateHitboxDrag(delta, b) => new_document.update_hitbox_drag(*delta, *b)?,
+            UpdateHitboxDrag(delta, b, s) => new_document.update_hitbox_drag(*delta, *b, *s)?,
             EndHitboxDrag => new_document.end_hitbox_drag(),
+            BeginAnchorDrag => new_document.begin_anchor_drag()?,
+            UpdateAnchorDrag(delta, b) => new_document.update_anchor_drag(*delta, *b)?,
+            EndAnchorDrag => new_document.end_anchor_drag(),
             TogglePlayback => new_document.toggle_playback()?,
+            SetPlaybackSpeed(s) => new_document.set_playback_speed(*s),
+            TogglePlaybackDirection => new_document.toggle_playback_direction(),
             SnapToPreviousFrame => new_document.snap_to_previous_frame()?,
             SnapToNextFrame => new_document.snap_to_next_frame()?,
-            ToggleLooping => new_document.toggle_looping()?,
+            StepKeyframe(d) => new_document.step_keyframe(*d)?,
+            StepTimelineClock(d) => new_document.step_timeline_clock(*d)?,
+            SetLoopMode(m) => new_document.set_loop_mode(*m)?,
+            SetAnimationFrameRate(r) => new_document.set_animation_frame_rate(*r)?,
+            ToggleHitboxShape => new_document.toggle_hitbox_shape()?,
+            DuplicateHitbox => new_document.duplicate_hitbox()?,
+            MirrorHitbox(s) => new_document.mirror_hitbox(*s)?,
             TimelineZoomIn => new_document.view.timeline_zoom_in(),
             TimelineZoomOut => new_document.view.timeline_zoom_out(),
             TimelineResetZoom => new_document.view.timeline_reset_zoom(),
             BeginScrub => new_document.transient.timeline_scrubbing = true,
             UpdateScrub(t) => new_document.update_timeline_scrub(*t)?,
             EndScrub => new_document.transient.timeline_scrubbing = false,
+            SetLoopIn => new_document.set_loop_marker(LoopMarker::In)?,
+            SetLoopOut => new_document.set_loop_marker(LoopMarker::Out)?,
+            ClearLoopRegion => new_document.clear_loop_region(),
+            BeginLoopMarkerDrag(m) => new_document.begin_loop_marker_drag(*m)?,
+            UpdateLoopMarkerDrag(t) => new_document.update_loop_marker_drag(*t)?,
+            EndLoopMarkerDrag => new_document.transient.timeline_loop_marker_being_dragged = None,
+            BeginWorkbenchScrub => new_document.begin_workbench_scrub()?,
+            EndWorkbenchScrub => new_document.end_workbench_scrub(),
+            BeginRuler(p) => new_document.begin_ruler(*p),
+            UpdateRuler(p) => new_document.update_ruler(*p),
+            EndRuler => new_document.end_ruler(),
             NudgeSelection(d, l) => new_document.nudge_selection(*d, *l)?,
+            SetSelectionPosition(p) => new_document.set_selection_position(*p)?,
+            SetHitboxSize(s) => new_document.set_hitbox_size(*s)?,
+            SetAnimationFrameDuration(d) => new_document.set_animation_frame_duration(*d)?,
+            SetAllKeyframeDurations(d) => new_document.set_all_keyframe_durations(*d)?,
+            ReverseAnimation => new_document.reverse_animation()?,
+            FlipSelection(x, y) => new_document.flip_selection(*x, *y)?,
+            AlignHitboxes(a) => new_document.align_hitboxes(*a)?,
+            DistributeHitboxes(d) => new_document.distribute_hitboxes(*d)?,
             DeleteSelection => new_document.delete_selection(),
             BeginRenameSelection => new_document.begin_rename_selection()?,
             UpdateRenameSelection(n) => new_document.transient.rename_buffer = Some(n.to_owned()),
             EndRenameSelection => new_document.end_rename_selection()?,
+            BeginSetAnimationGroup => new_document.begin_set_animation_group()?,
+            BeginSetAnimationDirection => new_document.begin_set_animation_direction()?,
+            BeginAddAnimationTag => new_document.begin_add_animation_tag()?,
+            RemoveAnimationTag(t) => new_document.remove_animation_tag(t)?,
+            BeginSetAnimationFrameSound => new_document.begin_set_animation_frame_sound()?,
+            BeginSetAnimationFrameUserData => new_document.begin_set_animation_frame_user_data()?,
+            RefreshFrame(p, r) => new_document.refresh_frame(p, *r)?,
+            BeginBuildAnimationsFromFrameNames => {
+                new_document.begin_build_animations_from_frame_names()
+            }
+            EndBuildAnimationsFromFrameNames(l) => {
+                new_document.end_build_animations_from_frame_names(*l)?
+            }
+            CancelBuildAnimationsFromFrameNames => {
+                new_document.cancel_build_animations_from_frame_names()
+            }
+            BeginMergeDuplicateAnimations => new_document.begin_merge_duplicate_animations(),
+            EndMergeDuplicateAnimations => new_document.end_merge_duplicate_animations()?,
+            CancelMergeDuplicateAnimations => new_document.cancel_merge_duplicate_animations(),
+            EndRecoverAutosave => new_document.end_recover_autosave()?,
+            CancelRecoverAutosave => new_document.cancel_recover_autosave(),
+            EndRelinkFrames(_, c) => new_document.end_relink_frames(c),
+            CancelRelinkFrames => new_document.cancel_relink_frames(),
+            RecordExportDestinations(_) => new_document.record_export_destinations(),
+            BeginExportOverwrite(_, c) => {
+                new_document.persistent.export_overwrite_conflicts = Some(c.clone())
+            }
+            EndExportOverwrite(_, a) => new_document.end_export_overwrite(*a),
+            CancelExportOverwrite(_) => new_document.persistent.export_overwrite_conflicts = None,
+            EndEstimatePacking(_, e) => new_document.persistent.pack_estimate = Some(e.clone()),
+            EndValidateExport(_, v) => new_document.persistent.export_validation = Some(v.clone()),
+            DismissExportValidation => new_document.persistent.export_validation = None,
         };
 
+        // Validation parses the template, so it only runs when export settings change
+        if new_document.persistent.export_settings_edit != self.persistent.export_settings_edit {
+            new_document.persistent.export_settings_error = new_document
+                .persistent
+                .export_settings_edit
+                .as_ref()
+                .and_then(|s| validate_export_settings(s).err())
+                .map(|e| e.to_string());
+        }
+
         self.record_command(command, new_document);
 
         Ok(())
     }
 }
+
+// Document editing "New Animation", whose keyframes show the given frames
+#[cfg(test)]
+fn document_with_animation(frames: &[&str]) -> Document {
+    let mut document = Document::new("sheet.tiger");
+    for frame in frames {
+        document.sheet.add_frame(frame);
+    }
+    {
+        let animation = document.sheet.add_animation(true);
+        for (index, frame) in frames.iter().enumerate() {
+            animation.insert_frame(frame, index).unwrap();
+        }
+    }
+    document.history[0].sheet = document.sheet.clone();
+    document
+        .process_command(&DocumentCommand::EditAnimation("New Animation".to_owned()))
+        .unwrap();
+    document
+}
+
+// Document editing the frame `walk.png`, which has hitboxes named "New Hitbox", "New Hitbox 2"...
+#[cfg(test)]
+fn document_with_hitboxes(num_hitboxes: usize) -> Document {
+    let mut document = Document::new("sheet.tiger");
+    document.sheet.add_frame("walk.png");
+    {
+        let frame = document.sheet.get_frame_mut("walk.png").unwrap();
+        for _ in 0..num_hitboxes {
+            frame.add_hitbox();
+        }
+    }
+    document.history[0].sheet = document.sheet.clone();
+    document
+        .process_command(&DocumentCommand::EditFrame(PathBuf::from("walk.png")))
+        .unwrap();
+    document
+}
+
+#[test]
+fn test_hitbox_drag_is_a_single_undo_step() {
+    use DocumentCommand::*;
+    let mut document = document_with_hitboxes(1);
+    let history_length = document.history.len();
+
+    let commands = vec![
+        BeginHitboxDrag("New Hitbox".to_owned()),
+        UpdateHitboxDrag(vec2(4.0, 0.0), true, false),
+        UpdateHitboxDrag(vec2(8.0, 2.0), true, false),
+        UpdateHitboxDrag(vec2(12.0, 5.0), true, false),
+        EndHitboxDrag,
+    ];
+    for command in &commands {
+        document.process_command(command).unwrap();
+    }
+    assert_eq!(document.history.len(), history_length + 1);
+    assert_eq!(document.get_undo_label(), Some("Move Hitbox"));
+
+    let hitbox_position = |document: &Document| {
+        let frame = document.sheet.get_frame("walk.png").unwrap();
+        frame.get_hitbox("New Hitbox").unwrap().get_position()
+    };
+    assert_eq!(hitbox_position(&document), vec2(12, 5));
+    document.undo().unwrap();
+    assert_eq!(hitbox_position(&document), vec2(0, 0));
+}
+
+#[test]
+fn test_animation_frame_offset_drag_is_a_single_undo_step() {
+    use DocumentCommand::*;
+    let mut document = document_with_animation(&["walk.png"]);
+    let history_length = document.history.len();
+
+    let commands = vec![
+        BeginAnimationFrameOffsetDrag(0),
+        UpdateAnimationFrameOffsetDrag(vec2(3.0, 1.0), true, false),
+        UpdateAnimationFrameOffsetDrag(vec2(6.0, -2.0), true, false),
+        EndAnimationFrameOffsetDrag,
+    ];
+    for command in &commands {
+        document.process_command(command).unwrap();
+    }
+    assert_eq!(document.history.len(), history_length + 1);
+    assert_eq!(document.get_undo_label(), Some("Move Keyframe"));
+
+    let offset = |document: &Document| {
+        let animation = document.sheet.get_animation("New Animation").unwrap();
+        animation.get_frame(0).unwrap().get_offset()
+    };
+    assert_eq!(offset(&document), vec2(6, -2));
+    document.undo().unwrap();
+    assert_eq!(offset(&document), vec2(0, 0));
+}
+
+#[test]
+fn test_animation_frame_offset_drag_snapping() {
+    use DocumentCommand::*;
+    let mut document = document_with_animation(&["walk.png"]);
+    document
+        .process_command(&BeginAnimationFrameOffsetDrag(0))
+        .unwrap();
+
+    let offset = |document: &Document| {
+        let animation = document.sheet.get_animation("New Animation").unwrap();
+        animation.get_frame(0).unwrap().get_offset()
+    };
+
+    document
+        .process_command(&UpdateAnimationFrameOffsetDrag(vec2(5.0, 20.0), true, true))
+        .unwrap();
+    assert_eq!(offset(&document), vec2(0, 20));
+
+    document
+        .process_command(&UpdateAnimationFrameOffsetDrag(
+            vec2(5.0, 20.0),
+            true,
+            false,
+        ))
+        .unwrap();
+    assert_eq!(offset(&document), vec2(5, 20));
+
+    document.view.snap_to_grid = true;
+    document
+        .process_command(&UpdateAnimationFrameOffsetDrag(
+            vec2(13.0, 20.0),
+            true,
+            true,
+        ))
+        .unwrap();
+    assert_eq!(offset(&document), vec2(16, 24));
+}
+
+#[test]
+fn test_select_all_animation_frames_and_delete() {
+    use DocumentCommand::*;
+    let mut document = document_with_animation(&["walk_0.png", "walk_1.png", "walk_0.png"]);
+    document.process_command(&SelectAll).unwrap();
+    match &document.view.selection {
+        Some(Selection::AnimationFrame(_, af)) => assert_eq!(af.items, vec![0, 1, 2]),
+        _ => panic!("Animation frames are not selected"),
+    }
+
+    let history_length = document.history.len();
+    document.process_command(&DeleteSelection).unwrap();
+    assert_eq!(document.history.len(), history_length + 1);
+    let animation = document.sheet.get_animation("New Animation").unwrap();
+    assert_eq!(animation.get_num_frames(), 0);
+}
+
+#[test]
+fn test_copy_and_paste_whole_selection() {
+    use DocumentCommand::*;
+    let mut document = document_with_animation(&["walk_0.png", "walk_1.png"]);
+    document.process_command(&SelectAll).unwrap();
+    let content = document.copy_selection().unwrap();
+    document.process_command(&Paste(content)).unwrap();
+    let animation = document.sheet.get_animation("New Animation").unwrap();
+    assert_eq!(animation.get_num_frames(), 4);
+    match &document.view.selection {
+        Some(Selection::AnimationFrame(_, af)) => assert_eq!(af.items, vec![0, 1]),
+        _ => panic!("Pasted animation frames are not selected"),
+    }
+
+    let mut document = document_with_hitboxes(2);
+    document.process_command(&SelectAll).unwrap();
+    let content = document.copy_selection().unwrap();
+    document.process_command(&Paste(content)).unwrap();
+    let frame = document.sheet.get_frame("walk.png").unwrap();
+    assert_eq!(frame.hitboxes_iter().count(), 4);
+    match &document.view.selection {
+        Some(Selection::Hitbox(_, h)) => assert_eq!(h.items.len(), 2),
+        _ => panic!("Pasted hitboxes are not selected"),
+    }
+}
+
+#[test]
+fn test_hitbox_range_and_invert_selection() {
+    use DocumentCommand::*;
+    let mut document = document_with_hitboxes(3);
+
+    let selected_hitboxes = |document: &Document| match &document.view.selection {
+        Some(Selection::Hitbox(_, h)) => (h.items.clone(), h.last_touched.clone()),
+        _ => panic!("Hitboxes are not selected"),
+    };
+
+    document
+        .process_command(&SelectHitbox("New Hitbox 2".to_owned()))
+        .unwrap();
+    let range = vec!["New Hitbox 2".to_owned(), "New Hitbox 3".to_owned()];
+    document
+        .process_command(&SelectHitboxRange(range.clone()))
+        .unwrap();
+    assert_eq!(
+        selected_hitboxes(&document),
+        (range, "New Hitbox 2".to_owned())
+    );
+
+    document
+        .process_command(&ToggleHitboxSelection("New Hitbox 3".to_owned()))
+        .unwrap();
+    document.process_command(&InvertSelection).unwrap();
+    assert_eq!(
+        selected_hitboxes(&document),
+        (
+            vec!["New Hitbox".to_owned(), "New Hitbox 3".to_owned()],
+            "New Hitbox 3".to_owned()
+        )
+    );
+
+    document.process_command(&SelectAll).unwrap();
+    document.process_command(&InvertSelection).unwrap();
+    assert_eq!(document.view.selection, None);
+}
+
+#[test]
+fn test_drag_multiple_hitboxes() {
+    use DocumentCommand::*;
+    let mut document = document_with_hitboxes(3);
+    {
+        let frame = document.sheet.get_frame_mut("walk.png").unwrap();
+        for (name, position) in &[
+            ("New Hitbox", vec2(0, 0)),
+            ("New Hitbox 2", vec2(10, 20)),
+            ("New Hitbox 3", vec2(-5, 5)),
+        ] {
+            frame.get_hitbox_mut(name).unwrap().set_position(*position);
+        }
+    }
+    let selection = vec!["New Hitbox".to_owned(), "New Hitbox 2".to_owned()];
+    document
+        .process_command(&SelectHitboxRange(selection.clone()))
+        .unwrap();
+
+    let commands = vec![
+        BeginHitboxDrag("New Hitbox 2".to_owned()),
+        UpdateHitboxDrag(vec2(3.0, 4.0), true, false),
+        SelectHitbox("New Hitbox 2".to_owned()),
+        EndHitboxDrag,
+    ];
+    for command in &commands {
+        document.process_command(command).unwrap();
+    }
+
+    let frame = document.sheet.get_frame("walk.png").unwrap();
+    let position = |name: &str| frame.get_hitbox(name).unwrap().get_position();
+    assert_eq!(position("New Hitbox"), vec2(3, 4));
+    assert_eq!(position("New Hitbox 2"), vec2(13, 24));
+    assert_eq!(position("New Hitbox 3"), vec2(-5, 5));
+    match &document.view.selection {
+        Some(Selection::Hitbox(_, h)) => assert_eq!(h.items, selection),
+        _ => panic!("Hitboxes are not selected"),
+    }
+}
+
+#[test]
+fn test_align_and_distribute_hitboxes() {
+    use DocumentCommand::*;
+    let mut document = document_with_hitboxes(3);
+    {
+        let frame = document.sheet.get_frame_mut("walk.png").unwrap();
+        for (name, position, size) in &[
+            ("New Hitbox", vec2(0, 0), vec2(10, 10)),
+            ("New Hitbox 2", vec2(30, 5), vec2(10, 20)),
+            ("New Hitbox 3", vec2(100, -10), vec2(20, 10)),
+        ] {
+            let hitbox = frame.get_hitbox_mut(name).unwrap();
+            hitbox.set_position(*position);
+            hitbox.set_size(*size);
+        }
+    }
+
+    let positions = |document: &Document| -> Vec<Vector2D<i32>> {
+        let frame = document.sheet.get_frame("walk.png").unwrap();
+        ["New Hitbox", "New Hitbox 2", "New Hitbox 3"]
+            .iter()
+            .map(|n| frame.get_hitbox(n).unwrap().get_position())
+            .collect()
+    };
+
+    document
+        .process_command(&SelectHitbox("New Hitbox".to_owned()))
+        .unwrap();
+    document
+        .process_command(&AlignHitboxes(HitboxAlignment::Top))
+        .unwrap();
+    assert_eq!(
+        positions(&document),
+        vec![vec2(0, 0), vec2(30, 5), vec2(100, -10)]
+    );
+
+    document.process_command(&SelectAll).unwrap();
+    let history_length = document.history.len();
+    document
+        .process_command(&AlignHitboxes(HitboxAlignment::Bottom))
+        .unwrap();
+    assert_eq!(document.history.len(), history_length + 1);
+    assert_eq!(
+        positions(&document),
+        vec![vec2(0, 15), vec2(30, 5), vec2(100, 15)]
+    );
+
+    document
+        .process_command(&DistributeHitboxes(HitboxDistribution::Horizontal))
+        .unwrap();
+    assert_eq!(
+        positions(&document),
+        vec![vec2(0, 15), vec2(50, 5), vec2(100, 15)]
+    );
+}
+
+#[test]
+fn test_mirror_hitbox() {
+    use DocumentCommand::*;
+    let mut document = document_with_hitboxes(1);
+    {
+        let frame = document.sheet.get_frame_mut("walk.png").unwrap();
+        let hitbox = frame.get_hitbox_mut("New Hitbox").unwrap();
+        hitbox.set_position(vec2(2, -4));
+        hitbox.set_size(vec2(6, 3));
+    }
+    document
+        .process_command(&SelectHitbox("New Hitbox".to_owned()))
+        .unwrap();
+
+    document
+        .process_command(&MirrorHitbox(vec2(16, 16)))
+        .unwrap();
+    document
+        .process_command(&SelectHitbox("New Hitbox".to_owned()))
+        .unwrap();
+    document
+        .process_command(&MirrorHitbox(vec2(15, 15)))
+        .unwrap();
+
+    document
+        .sheet
+        .get_frame_mut("walk.png")
+        .unwrap()
+        .set_anchor(vec2(3, 0));
+    document
+        .process_command(&SelectHitbox("New Hitbox".to_owned()))
+        .unwrap();
+    document
+        .process_command(&MirrorHitbox(vec2(16, 16)))
+        .unwrap();
+
+    let frame = document.sheet.get_frame("walk.png").unwrap();
+    let rectangle = |name: &str| frame.get_hitbox(name).unwrap().get_rectangle();
+    assert_eq!(rectangle("New Hitbox 2"), rect(-8, -4, 6, 3));
+    assert_eq!(rectangle("New Hitbox 3"), rect(-7, -4, 6, 3));
+    assert_eq!(rectangle("New Hitbox 4"), rect(-2, -4, 6, 3));
+    assert_eq!(document.get_undo_label(), Some("Mirror Hitbox"));
+}
+
+#[test]
+fn test_refresh_frame_keeps_keyframes_in_place() {
+    use DocumentCommand::*;
+    let mut document = document_with_animation(&["walk.png", "idle.png", "walk.png"]);
+    for animation_frame in document
+        .sheet
+        .get_animation_mut("New Animation")
+        .unwrap()
+        .frames_iter_mut()
+    {
+        animation_frame.set_offset(vec2(5, -3));
+    }
+
+    document
+        .process_command(&RefreshFrame("walk.png".into(), None))
+        .unwrap();
+    document
+        .process_command(&RefreshFrame(
+            "walk.png".into(),
+            Some((vec2(16, 16), vec2(21, 10))),
+        ))
+        .unwrap();
+
+    let animation = document.sheet.get_animation("New Animation").unwrap();
+    let offsets: Vec<Vector2D<i32>> = animation.frames_iter().map(|f| f.get_offset()).collect();
+    assert_eq!(offsets, vec![vec2(7, -6), vec2(5, -3), vec2(7, -6)]);
+    assert_eq!(document.get_undo_label(), Some("Refresh Frame"));
+}
+
+#[test]
+fn test_loop_region_bounds_playback_and_scrubbing() {
+    use DocumentCommand::*;
+    let mut document = document_with_animation(&["walk.png", "walk.png"]);
+    assert_eq!(document.get_loop_region(), None);
+
+    let commands = vec![
+        BeginLoopMarkerDrag(LoopMarker::In),
+        UpdateLoopMarkerDrag(Duration::from_millis(50)),
+        EndLoopMarkerDrag,
+        BeginLoopMarkerDrag(LoopMarker::Out),
+        UpdateLoopMarkerDrag(Duration::from_millis(500)),
+        EndLoopMarkerDrag,
+    ];
+    for command in &commands {
+        document.process_command(command).unwrap();
+    }
+    assert_eq!(document.get_loop_region(), Some((50, 200)));
+
+    document.process_command(&TogglePlayback).unwrap();
+    document.tick(Duration::from_millis(170));
+    assert_eq!(document.view.timeline_clock, Duration::from_millis(70));
+
+    document.process_command(&TogglePlayback).unwrap();
+    document.process_command(&BeginScrub).unwrap();
+    document
+        .process_command(&UpdateScrub(Duration::from_millis(10)))
+        .unwrap();
+    document.process_command(&EndScrub).unwrap();
+    assert_eq!(document.view.timeline_clock, Duration::from_millis(50));
+
+    document.process_command(&ClearLoopRegion).unwrap();
+    assert_eq!(document.get_loop_region(), None);
+}
+
+#[test]
+fn test_step_timeline_clock() {
+    use DocumentCommand::*;
+    let mut document = document_with_animation(&["walk.png", "walk.png"]);
+
+    document.process_command(&StepTimelineClock(-10)).unwrap();
+    assert_eq!(document.view.timeline_clock, Duration::from_millis(0));
+
+    document.process_command(&TogglePlayback).unwrap();
+    document.process_command(&StepTimelineClock(110)).unwrap();
+    assert!(!document.persistent.timeline_is_playing);
+    assert_eq!(document.view.timeline_clock, Duration::from_millis(110));
+    match &document.view.selection {
+        Some(Selection::AnimationFrame(_, af)) => assert_eq!(af.items, vec![1]),
+        _ => panic!("Animation frame is not selected"),
+    }
+
+    document.process_command(&StepTimelineClock(500)).unwrap();
+    assert_eq!(document.view.timeline_clock, Duration::from_millis(199));
+    match &document.view.selection {
+        Some(Selection::AnimationFrame(_, af)) => assert_eq!(af.items, vec![1]),
+        _ => panic!("Animation frame is not selected"),
+    }
+}
+
+#[test]
+fn test_set_animation_frame_duration() {
+    use DocumentCommand::*;
+    let mut document = document_with_animation(&["walk.png", "walk.png"]);
+    document.process_command(&SelectAll).unwrap();
+    let history_length = document.history.len();
+
+    document
+        .process_command(&SetAnimationFrameDuration(0))
+        .unwrap();
+    assert_eq!(document.history.len(), history_length + 1);
+    assert_eq!(document.get_undo_label(), Some("Resize Keyframe"));
+    let animation = document.sheet.get_animation("New Animation").unwrap();
+    assert_eq!(animation.get_duration(), Some(2));
+}
+
+#[test]
+fn test_set_all_keyframe_durations_clamps_playhead() {
+    use DocumentCommand::*;
+    let mut document = document_with_animation(&["walk.png", "walk.png", "walk.png"]);
+    document
+        .process_command(&UpdateScrub(Duration::from_millis(250)))
+        .unwrap();
+
+    document
+        .process_command(&SetAllKeyframeDurations(40))
+        .unwrap();
+    assert_eq!(document.get_undo_label(), Some("Resize All Keyframes"));
+    let animation = document.sheet.get_animation("New Animation").unwrap();
+    assert!(animation.frames_iter().all(|f| f.get_duration() == 40));
+    assert_eq!(document.view.timeline_clock, Duration::from_millis(120));
+}
+
+#[test]
+fn test_reverse_animation_keeps_selected_keyframe() {
+    use DocumentCommand::*;
+    let mut document = document_with_animation(&["walk_0.png", "walk_1.png", "walk_2.png"]);
+    document.process_command(&SelectAnimationFrame(0)).unwrap();
+
+    document.process_command(&ReverseAnimation).unwrap();
+    assert_eq!(document.get_undo_label(), Some("Reverse Animation"));
+    assert_eq!(document.view.timeline_clock, Duration::new(0, 0));
+    let animation = document.sheet.get_animation("New Animation").unwrap();
+    let frames: Vec<&Path> = animation.frames_iter().map(|f| f.get_frame()).collect();
+    assert_eq!(
+        frames,
+        vec![
+            Path::new("walk_2.png"),
+            Path::new("walk_1.png"),
+            Path::new("walk_0.png")
+        ]
+    );
+    match &document.view.selection {
+        Some(Selection::AnimationFrame(_, af)) => assert_eq!(af.items, vec![2]),
+        _ => panic!("Animation frame is not selected"),
+    }
+}
+
+#[test]
+fn test_insert_frames_at_playhead() {
+    use DocumentCommand::*;
+    let mut document = document_with_animation(&["walk_0.png", "walk_1.png"]);
+    document.sheet.add_frame("idle.png");
+    document
+        .process_command(&UpdateScrub(Duration::from_millis(150)))
+        .unwrap();
+    document
+        .process_command(&SelectFrame(PathBuf::from("idle.png")))
+        .unwrap();
+    let history_length = document.history.len();
+
+    document.process_command(&InsertFramesAtPlayhead).unwrap();
+    assert_eq!(document.history.len(), history_length + 1);
+    assert_eq!(document.get_undo_label(), Some("Create Frame"));
+    let animation = document.sheet.get_animation("New Animation").unwrap();
+    assert_eq!(
+        animation.get_frame(1).unwrap().get_frame(),
+        Path::new("idle.png")
+    );
+}
+
+#[test]
+fn test_ping_pong_bounce_keeps_playback_direction() {
+    use DocumentCommand::*;
+    let mut document = document_with_animation(&["walk.png", "walk.png"]);
+    document
+        .process_command(&SetLoopMode(LoopMode::PingPong))
+        .unwrap();
+    document.process_command(&TogglePlayback).unwrap();
+
+    document.tick(Duration::from_millis(250));
+    assert_eq!(document.view.timeline_clock, Duration::from_millis(150));
+    document.tick(Duration::from_millis(100));
+    assert_eq!(document.view.timeline_clock, Duration::from_millis(50));
+    assert_eq!(
+        document.persistent.playback_direction,
+        PlaybackDirection::Forward
+    );
+
+    document.tick(Duration::from_millis(100));
+    assert_eq!(document.view.timeline_clock, Duration::from_millis(50));
+    document.tick(Duration::from_millis(100));
+    assert_eq!(document.view.timeline_clock, Duration::from_millis(150));
+}
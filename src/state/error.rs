@@ -2,16 +2,26 @@
 pub enum StateError {
     #[fail(display = "No document is open")]
     NoDocumentOpen,
+    #[fail(display = "Nothing is selected")]
+    NothingSelected,
+    #[fail(display = "Clipboard is empty")]
+    ClipboardEmpty,
     #[fail(display = "Requested document was not found")]
     DocumentNotFound,
     #[fail(display = "Sheet has no export settings")]
     NoExistingExportSettings,
+    #[fail(display = "These documents have no export settings: {}", _0)]
+    DocumentsWithoutExportSettings(String),
+    #[fail(display = "Export was cancelled")]
+    ExportCancelled,
     #[fail(display = "Cannot perform undo operation")]
     UndoOperationNowAllowed,
     #[fail(display = "Requested frame is not in document")]
     FrameNotInDocument,
     #[fail(display = "Requested animation is not in document")]
     AnimationNotInDocument,
+    #[fail(display = "Keyframe durations are set by the animation frame rate")]
+    DurationsSetByFrameRate,
     #[fail(display = "Requested hitbox is not in frame")]
     HitboxNotInFrame,
     #[fail(display = "A hitbox with this name already exists")]
@@ -24,16 +34,36 @@ pub enum StateError {
     NotEditingAnyAnimation,
     #[fail(display = "Currently not adjusting a hitbox")]
     NotDraggingAHitbox,
+    #[fail(display = "Currently not moving an anchor")]
+    NotDraggingAnAnchor,
     #[fail(display = "Frame does not have a hitbox at the requested index")]
     InvalidHitboxIndex,
     #[fail(display = "Animation does not have a frame at the requested index")]
     InvalidAnimationFrameIndex,
+    #[fail(display = "Export settings do not have a template variable at the requested index")]
+    InvalidTemplateVariableIndex,
     #[fail(display = "Currently not adjusting the duration of an animation frame")]
     NotDraggingATimelineFrame,
+    #[fail(display = "Currently not moving a loop marker")]
+    NotDraggingALoopMarker,
     #[fail(display = "No animation frame found for requested time")]
     NoAnimationFrameForThisTime,
     #[fail(display = "Not currently adjusting export settings")]
     NotExporting,
     #[fail(display = "Not currently renaming an item")]
     NotRenaming,
+    #[fail(display = "Not currently building animations from frame names")]
+    NotBuildingAnimations,
+    #[fail(display = "Not currently merging duplicate animations")]
+    NotMergingAnimations,
+    #[fail(display = "Could not access the clipboard")]
+    ClipboardError,
+    #[fail(display = "Not currently importing a grid of frames")]
+    NotImportingGrid,
+    #[fail(display = "No autosave to recover")]
+    NoAutosaveToRecover,
+    #[fail(display = "Not currently closing documents")]
+    NotClosingDocuments,
+    #[fail(display = "Could not locate the user configuration directory")]
+    NoConfigDirectory,
 }
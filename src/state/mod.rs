@@ -3,6 +3,8 @@ mod command;
 mod command_buffer;
 mod document;
 mod error;
+mod preferences;
+mod saved_views;
 mod transient;
 mod view;
 
@@ -11,5 +13,7 @@ pub use crate::state::command::*;
 pub use crate::state::command_buffer::*;
 pub use crate::state::document::*;
 pub use crate::state::error::*;
+pub use crate::state::preferences::*;
+pub use crate::state::saved_views::*;
 pub use crate::state::transient::*;
 pub use crate::state::view::*;
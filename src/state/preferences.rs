@@ -0,0 +1,79 @@
+use failure::Error;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::PathBuf;
+
+use crate::state::*;
+
+const PREFERENCES_DIRECTORY: &str = "Tiger";
+const PREFERENCES_FILE_NAME: &str = "preferences.json";
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum ContentSort {
+    NameAscending,
+    NameDescending,
+    DateModified,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum WorkbenchBackground {
+    Black,
+    Gray,
+    White,
+    Checkerboard,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Preferences {
+    pub new_animations_loop: bool,
+    pub absolute_paths: bool,
+    pub content_sort: ContentSort,
+    pub live_export: bool,
+    pub right_drag_pan: bool,
+    pub space_drag_pan: bool,
+    pub workbench_background: WorkbenchBackground,
+}
+
+impl Default for Preferences {
+    fn default() -> Preferences {
+        Preferences {
+            new_animations_loop: true,
+            absolute_paths: false,
+            content_sort: ContentSort::NameAscending,
+            live_export: false,
+            right_drag_pan: true,
+            space_drag_pan: false,
+            workbench_background: WorkbenchBackground::Black,
+        }
+    }
+}
+
+pub(super) fn get_config_directory() -> Result<PathBuf, Error> {
+    let mut path = dirs::config_dir().ok_or(StateError::NoConfigDirectory)?;
+    path.push(PREFERENCES_DIRECTORY);
+    Ok(path)
+}
+
+impl Preferences {
+    fn get_path() -> Result<PathBuf, Error> {
+        let mut path = get_config_directory()?;
+        path.push(PREFERENCES_FILE_NAME);
+        Ok(path)
+    }
+
+    pub fn read() -> Result<Preferences, Error> {
+        let file = BufReader::new(File::open(Preferences::get_path()?)?);
+        Ok(serde_json::from_reader(file)?)
+    }
+
+    pub fn write(&self) -> Result<(), Error> {
+        let path = Preferences::get_path()?;
+        if let Some(directory) = path.parent() {
+            fs::create_dir_all(directory)?;
+        }
+        let file = BufWriter::new(File::create(&path)?);
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,45 @@
+use failure::Error;
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+use crate::state::*;
+
+const SAVED_VIEWS_FILE_NAME: &str = "views.json";
+
+// View state lives in the user's config directory rather than in the sheet, so that
+// browsing a document does not modify it
+type SavedViews = BTreeMap<PathBuf, SavedView>;
+
+fn get_path() -> Result<PathBuf, Error> {
+    let mut path = get_config_directory()?;
+    path.push(SAVED_VIEWS_FILE_NAME);
+    Ok(path)
+}
+
+fn read_saved_views() -> Result<SavedViews, Error> {
+    let path = get_path()?;
+    if !path.exists() {
+        return Ok(SavedViews::new());
+    }
+    let file = BufReader::new(File::open(path)?);
+    Ok(serde_json::from_reader(file)?)
+}
+
+pub fn read_saved_view<T: AsRef<Path>>(document_path: T) -> Result<Option<SavedView>, Error> {
+    Ok(read_saved_views()?.remove(document_path.as_ref()))
+}
+
+pub fn write_saved_views(views: Vec<(PathBuf, SavedView)>) -> Result<(), Error> {
+    // A corrupt file is replaced rather than preventing new views from being saved
+    let mut saved_views = read_saved_views().unwrap_or_default();
+    saved_views.extend(views);
+    let path = get_path()?;
+    if let Some(directory) = path.parent() {
+        fs::create_dir_all(directory)?;
+    }
+    let file = BufWriter::new(File::create(&path)?);
+    serde_json::to_writer_pretty(file, &saved_views)?;
+    Ok(())
+}
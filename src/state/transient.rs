@@ -5,6 +5,11 @@ use std::time::Duration;
 #[derive(Clone, Debug, PartialEq)]
 pub enum RenameItem {
     Animation(String),
+    AnimationGroup(String),
+    AnimationDirection(String),
+    AnimationTag(String),
+    AnimationFrameSound(String, usize),
+    AnimationFrameUserData(String, usize),
     Hitbox(PathBuf, String),
 }
 
@@ -33,6 +38,12 @@ impl ResizeAxis {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LoopMarker {
+    In,
+    Out,
+}
+
 // State preventing undo/redo while not default
 // Reset when focusing different document
 #[derive(Clone, Debug, Default, PartialEq)]
@@ -41,11 +52,13 @@ pub struct Transient {
     pub item_being_renamed: Option<RenameItem>,
     pub rename_buffer: Option<String>,
     pub workbench_hitbox_being_dragged: Option<String>,
-    pub workbench_hitbox_drag_initial_offset: Vector2D<i32>,
+    pub workbench_hitbox_drag_initial_offsets: Vec<(String, Vector2D<i32>)>,
     pub workbench_hitbox_being_scaled: Option<String>,
     pub workbench_hitbox_scale_axis: ResizeAxis,
     pub workbench_hitbox_scale_initial_position: Vector2D<i32>,
     pub workbench_hitbox_scale_initial_size: Vector2D<u32>,
+    pub workbench_anchor_being_dragged: bool,
+    pub workbench_anchor_drag_initial_offset: Vector2D<i32>,
     pub workbench_animation_frame_being_dragged: Option<usize>,
     pub workbench_animation_frame_drag_initial_offset: Vector2D<i32>,
     pub timeline_frame_being_scaled: Option<usize>,
@@ -53,4 +66,9 @@ pub struct Transient {
     pub timeline_frame_scale_initial_clock: Duration,
     pub timeline_frame_being_dragged: Option<usize>,
     pub timeline_scrubbing: bool,
+    pub timeline_loop_marker_being_dragged: Option<LoopMarker>,
+    pub workbench_scrubbing: bool,
+    pub workbench_scrub_initial_clock: Duration,
+    pub workbench_ruler_start: Option<Vector2D<i32>>,
+    pub workbench_ruler_end: Vector2D<i32>,
 }
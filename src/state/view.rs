@@ -2,12 +2,96 @@ use euclid::*;
 use std::path::PathBuf;
 use std::time::Duration;
 
+use crate::sheet::Sheet;
+
+const WORKBENCH_ZOOM_LEVELS: [i32; 8] = [16, 8, 4, 2, 1, -2, -4, -8];
+const TIMELINE_ZOOM_LEVELS: [i32; 5] = [4, 2, 1, -2, -4];
+const WORKBENCH_FIT_MARGIN: f32 = 20.0; // TODO DPI?
+
+fn get_zoom_factor(zoom_level: i32) -> f32 {
+    if zoom_level >= 0 {
+        zoom_level as f32
+    } else {
+        -1.0 / zoom_level as f32
+    }
+}
+
+// Zooming in and out steps between known levels, a level outside of them could not be zoomed
+// away from (or divide by zero in the case of 0). Unknown levels fall back to the default zoom.
+fn restore_zoom_level(zoom_level: i32, zoom_levels: &[i32]) -> i32 {
+    if zoom_levels.contains(&zoom_level) {
+        zoom_level
+    } else {
+        1
+    }
+}
+
+// Items selected together, `last_touched` anchors range selections
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultiSelection<T> {
+    pub last_touched: T,
+    pub items: Vec<T>,
+}
+
+impl<T: Clone + PartialEq> MultiSelection<T> {
+    pub fn new(item: T) -> MultiSelection<T> {
+        MultiSelection {
+            last_touched: item.clone(),
+            items: vec![item],
+        }
+    }
+
+    // Returns None when there are no items, `last_touched` is only kept if it is one of them
+    pub fn with_items(items: Vec<T>, last_touched: Option<T>) -> Option<MultiSelection<T>> {
+        let last_touched = match last_touched {
+            Some(t) if items.contains(&t) => t,
+            _ => items.last()?.clone(),
+        };
+        Some(MultiSelection {
+            last_touched,
+            items,
+        })
+    }
+
+    pub fn contains(&self, item: &T) -> bool {
+        self.items.contains(item)
+    }
+
+    // Returns None once the last item is deselected
+    pub fn toggle(mut self, item: T) -> Option<MultiSelection<T>> {
+        if self.contains(&item) {
+            self.items.retain(|i| *i != item);
+            self.last_touched = self.items.last()?.clone();
+        } else {
+            self.items.push(item.clone());
+            self.last_touched = item;
+        }
+        Some(self)
+    }
+
+    // Returns None when every item is already selected
+    pub fn invert(&self, all_items: Vec<T>) -> Option<MultiSelection<T>> {
+        let items = all_items
+            .into_iter()
+            .filter(|i| !self.contains(i))
+            .collect();
+        MultiSelection::with_items(items, None)
+    }
+
+    pub fn map<F: Fn(&T) -> T>(&self, f: F) -> MultiSelection<T> {
+        MultiSelection {
+            last_touched: f(&self.last_touched),
+            items: self.items.iter().map(&f).collect(),
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub enum Selection {
-    Frame(PathBuf),
+    Frame(MultiSelection<PathBuf>),
     Animation(String),
-    Hitbox(PathBuf, String),
-    AnimationFrame(String, usize),
+    Hitbox(PathBuf, MultiSelection<String>),
+    AnimationFrame(String, MultiSelection<usize>),
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
@@ -16,7 +100,7 @@ pub enum ContentTab {
     Animations,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub enum WorkbenchItem {
     Frame(PathBuf),
     Animation(String),
@@ -29,6 +113,13 @@ pub struct View {
     pub workbench_item: Option<WorkbenchItem>,
     pub workbench_offset: Vector2D<f32>,
     pub timeline_clock: Duration,
+    pub timeline_loop_in: Option<Duration>,
+    pub timeline_loop_out: Option<Duration>,
+    pub onion_skin_count: usize,
+    pub show_animation_hitboxes: bool,
+    pub animation_tag_filter: Option<String>,
+    pub snap_to_grid: bool,
+    pub snap_grid_size: u32,
     workbench_zoom_level: i32,
     timeline_zoom_level: i32,
 }
@@ -43,17 +134,71 @@ impl Default for View {
             workbench_zoom_level: 1,
             timeline_zoom_level: 1,
             timeline_clock: Default::default(),
+            timeline_loop_in: None,
+            timeline_loop_out: None,
+            onion_skin_count: 0,
+            show_animation_hitboxes: true,
+            animation_tag_filter: None,
+            snap_to_grid: false,
+            snap_grid_size: 8,
+        }
+    }
+}
+
+// Subset of the view which is remembered when a document is closed
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct SavedView {
+    workbench_item: Option<WorkbenchItem>,
+    workbench_offset: (f32, f32),
+    workbench_zoom_level: i32,
+    timeline_zoom_level: i32,
+    timeline_clock_millis: u64,
+}
+
+impl Default for SavedView {
+    fn default() -> SavedView {
+        SavedView::from(&View::default())
+    }
+}
+
+impl From<&View> for SavedView {
+    fn from(view: &View) -> SavedView {
+        SavedView {
+            workbench_item: view.workbench_item.clone(),
+            workbench_offset: view.workbench_offset.to_tuple(),
+            workbench_zoom_level: view.workbench_zoom_level,
+            timeline_zoom_level: view.timeline_zoom_level,
+            timeline_clock_millis: view.timeline_clock.as_millis() as u64,
         }
     }
 }
 
 impl View {
-    pub fn get_workbench_zoom_factor(&self) -> f32 {
-        if self.workbench_zoom_level >= 0 {
-            self.workbench_zoom_level as f32
-        } else {
-            -1.0 / self.workbench_zoom_level as f32
+    // Items which are no longer in the sheet are not restored
+    pub fn restore(&mut self, saved_view: &SavedView, sheet: &Sheet) {
+        self.workbench_item = match &saved_view.workbench_item {
+            Some(WorkbenchItem::Frame(p)) if sheet.has_frame(p) => {
+                Some(WorkbenchItem::Frame(p.clone()))
+            }
+            Some(WorkbenchItem::Animation(n)) if sheet.has_animation(n) => {
+                Some(WorkbenchItem::Animation(n.clone()))
+            }
+            _ => None,
+        };
+        if let Some(WorkbenchItem::Animation(_)) = self.workbench_item {
+            self.content_tab = ContentTab::Animations;
         }
+        self.workbench_offset = saved_view.workbench_offset.into();
+        self.workbench_zoom_level =
+            restore_zoom_level(saved_view.workbench_zoom_level, &WORKBENCH_ZOOM_LEVELS);
+        self.timeline_zoom_level =
+            restore_zoom_level(saved_view.timeline_zoom_level, &TIMELINE_ZOOM_LEVELS);
+        self.timeline_clock = Duration::from_millis(saved_view.timeline_clock_millis);
+    }
+
+    pub fn get_workbench_zoom_factor(&self) -> f32 {
+        get_zoom_factor(self.workbench_zoom_level)
     }
 
     pub fn workbench_zoom_in(&mut self) {
@@ -78,14 +223,59 @@ impl View {
         self.workbench_zoom_level = std::cmp::max(self.workbench_zoom_level, -8);
     }
 
+    // `cursor` is relative to the center of the workbench, in screen pixels. The offset is
+    // recomputed from the zoom factors alone so repeated zooms don't accumulate drift.
+    fn keep_point_under_cursor(&mut self, cursor: Vector2D<f32>, old_zoom: f32) {
+        let new_zoom = self.get_workbench_zoom_factor();
+        self.workbench_offset = cursor - (cursor - self.workbench_offset) * (new_zoom / old_zoom);
+    }
+
+    pub fn workbench_zoom_in_at(&mut self, cursor: Vector2D<f32>) {
+        let old_zoom = self.get_workbench_zoom_factor();
+        self.workbench_zoom_in();
+        self.keep_point_under_cursor(cursor, old_zoom);
+    }
+
+    pub fn workbench_zoom_out_at(&mut self, cursor: Vector2D<f32>) {
+        let old_zoom = self.get_workbench_zoom_factor();
+        self.workbench_zoom_out();
+        self.keep_point_under_cursor(cursor, old_zoom);
+    }
+
+    // Picks the largest zoom level at which `bounds` (in workbench pixels) fit on screen
+    pub fn workbench_fit(&mut self, bounds: Rect<f32>, workbench_size: Vector2D<f32>) {
+        let available_size = workbench_size - vec2(2.0, 2.0) * WORKBENCH_FIT_MARGIN;
+        self.workbench_zoom_level = WORKBENCH_ZOOM_LEVELS
+            .iter()
+            .cloned()
+            .find(|l| {
+                let size = bounds.size.to_vector() * get_zoom_factor(*l);
+                size.x <= available_size.x && size.y <= available_size.y
+            })
+            .unwrap_or(-8);
+        let zoom = self.get_workbench_zoom_factor();
+        self.workbench_offset = (-bounds.center().to_vector() * zoom).round();
+    }
+
     pub fn workbench_reset_zoom(&mut self) {
         self.workbench_zoom_level = 1;
+        self.workbench_offset = Default::default();
     }
 
     pub fn workbench_center(&mut self) {
         self.workbench_offset = Default::default();
     }
 
+    // Snaps a position or size in pixels to the nearest grid line
+    pub fn snap(&self, value: i32) -> i32 {
+        let grid_size = std::cmp::max(1, self.snap_grid_size) as f32;
+        ((value as f32 / grid_size).round() * grid_size) as i32
+    }
+
+    pub fn toggle_onion_skin(&mut self) {
+        self.onion_skin_count = if self.onion_skin_count == 0 { 1 } else { 0 };
+    }
+
     pub fn timeline_zoom_in(&mut self) {
         if self.timeline_zoom_level >= 1 {
             self.timeline_zoom_level *= 2;
@@ -113,14 +303,27 @@ impl View {
     }
 
     pub fn get_timeline_zoom_factor(&self) -> f32 {
-        if self.timeline_zoom_level >= 0 {
-            self.timeline_zoom_level as f32
-        } else {
-            -1.0 / self.timeline_zoom_level as f32
-        }
+        get_zoom_factor(self.timeline_zoom_level)
     }
 
     pub fn pan(&mut self, delta: Vector2D<f32>) {
         self.workbench_offset += delta
     }
 }
+
+#[test]
+fn test_restore_unknown_zoom_levels() {
+    let mut view = View::default();
+    let mut saved_view = SavedView::from(&view);
+    saved_view.workbench_zoom_level = 0;
+    saved_view.timeline_zoom_level = 3;
+    view.restore(&saved_view, &Sheet::default());
+    assert_eq!(view.workbench_zoom_level, 1);
+    assert_eq!(view.timeline_zoom_level, 1);
+
+    saved_view.workbench_zoom_level = -4;
+    saved_view.timeline_zoom_level = 2;
+    view.restore(&saved_view, &Sheet::default());
+    assert_eq!(view.workbench_zoom_level, -4);
+    assert_eq!(view.timeline_zoom_level, 2);
+}
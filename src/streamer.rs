@@ -31,11 +31,14 @@ pub fn load_from_disk(
     texture_cache: Arc<Mutex<TextureCache>>,
     sender: &Sender<StreamerPayload>,
 ) {
-    // List textures we want loaded
-    let mut desired_textures = HashSet::new();
+    // List textures we want loaded, along with the part of their image they cover
+    let mut desired_textures = HashMap::new();
     for document in app_state.documents_iter() {
         for frame in document.sheet.frames_iter() {
-            desired_textures.insert(frame.get_source().to_owned());
+            desired_textures.insert(
+                frame.get_source().to_owned(),
+                (frame.get_image(), frame.get_source_rect()),
+            );
         }
     }
 
@@ -53,11 +56,14 @@ pub fn load_from_disk(
     let mut queued_textures = HashSet::new();
     let mut io_time = std::time::Duration::new(0, 0);
 
-    for path in desired_textures.iter() {
+    for (path, (image_path, source_rect)) in desired_textures.iter() {
         obsolete_textures.remove(path);
 
         match cache_content.get(path) {
-            Some(TextureCacheEntry::Loaded(_)) | Some(TextureCacheEntry::Missing) => {
+            Some(TextureCacheEntry::Loaded(t)) if !t.stale => {
+                continue;
+            }
+            Some(TextureCacheEntry::Missing) => {
                 continue;
             }
             _ => (),
@@ -65,8 +71,11 @@ pub fn load_from_disk(
 
         if io_time.as_millis() < MAX_TEXTURES_LOAD_TIME_PER_TICK {
             let start = std::time::Instant::now();
-            if let Ok(file) = File::open(&path) {
-                if let Ok(image) = image::load(BufReader::new(file), image::PNG) {
+            if let Ok(file) = File::open(&image_path) {
+                if let Ok(mut image) = image::load(BufReader::new(file), image::PNG) {
+                    if let Some(r) = source_rect {
+                        image = image.crop(r.origin.x, r.origin.y, r.size.width, r.size.height);
+                    }
                     new_textures.insert(path.clone(), image.to_rgba());
                 };
             } else {
@@ -118,6 +127,9 @@ pub fn upload(
                 gfx::texture::Mipmap::Allocated,
                 &[&texture_data],
             ) {
+                if let Some(TextureCacheResult::Loaded(old_texture)) = texture_cache.get(&path) {
+                    renderer.textures().remove(old_texture.id);
+                }
                 let id = renderer.textures().insert((texture, sampler));
                 texture_cache.insert_entry(path, id, size);
             } else {
@@ -128,6 +140,9 @@ pub fn upload(
             texture_cache.insert_pending(path);
         }
         for path in payload.errored_textures {
+            if let Some(TextureCacheResult::Loaded(old_texture)) = texture_cache.get(&path) {
+                renderer.textures().remove(old_texture.id);
+            }
             texture_cache.insert_error(path);
         }
         for path in payload.obsolete_textures {
@@ -143,7 +158,7 @@ pub fn upload(
 struct TextureCacheImage {
     pub id: ImTexture,
     pub size: Vector2D<u32>,
-    // TODO dirty flag and file watches
+    pub stale: bool, // TODO file watches
 }
 
 #[derive(Clone)]
@@ -201,7 +216,11 @@ impl TextureCache {
     pub fn insert_entry<T: AsRef<Path>>(&mut self, path: T, id: ImTexture, size: Vector2D<u32>) {
         self.cache.insert(
             path.as_ref().to_owned(),
-            TextureCacheEntry::Loaded(TextureCacheImage { id, size }),
+            TextureCacheEntry::Loaded(TextureCacheImage {
+                id,
+                size,
+                stale: false,
+            }),
         );
     }
 
@@ -217,6 +236,14 @@ impl TextureCache {
         }
     }
 
+    pub fn invalidate<T: AsRef<Path>>(&mut self, path: T) {
+        match self.cache.get_mut(path.as_ref()) {
+            Some(TextureCacheEntry::Loaded(t)) => t.stale = true,
+            Some(TextureCacheEntry::Missing) => self.remove(path),
+            _ => (),
+        }
+    }
+
     pub fn remove<T: AsRef<Path>>(&mut self, path: T) {
         self.cache.remove(path.as_ref());
     }
@@ -1,12 +1,80 @@
+use euclid::*;
 use imgui::StyleVar::*;
 use imgui::*;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
 use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
 
-use crate::sheet::{Animation, Frame};
+use crate::sheet::{Animation, Frame, Sheet};
 use crate::state::*;
+use crate::streamer::{TextureCache, TextureCacheResult};
+use crate::ui::spinner::*;
 use crate::ui::Rect;
+use crate::utils;
 
-fn draw_tabs<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer) {
+const MODIFICATION_TIME_LIFETIME: Duration = Duration::from_secs(5);
+
+// Thumbnails are only drawn for rows in view, and no more than this many per UI frame
+const MAX_THUMBNAILS_PER_FRAME: usize = 64;
+
+thread_local! {
+    // Avoids hitting the file system for every frame of every UI update
+    static MODIFICATION_TIMES: RefCell<HashMap<PathBuf, (Instant, Option<SystemTime>)>> =
+        RefCell::new(HashMap::new());
+}
+
+fn get_modification_time(path: &Path) -> Option<SystemTime> {
+    MODIFICATION_TIMES.with(|times| {
+        let mut times = times.borrow_mut();
+        let now = Instant::now();
+        match times.get(path) {
+            Some((read_at, time)) if now - *read_at < MODIFICATION_TIME_LIFETIME => *time,
+            _ => {
+                let time = std::fs::metadata(path).and_then(|m| m.modified()).ok();
+                times.insert(path.to_owned(), (now, time));
+                time
+            }
+        }
+    })
+}
+
+// Most recently modified frame of the animation
+fn get_animation_modification_time(sheet: &Sheet, animation: &Animation) -> Option<SystemTime> {
+    animation
+        .frames_iter()
+        .filter(|f| sheet.has_frame(f.get_frame()))
+        .filter_map(|f| get_modification_time(f.get_frame()))
+        .max()
+}
+
+fn draw_sort_options<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut CommandBuffer) {
+    let sorts = [
+        ContentSort::NameAscending,
+        ContentSort::NameDescending,
+        ContentSort::DateModified,
+    ];
+    let labels = [
+        im_str!("Name (A-Z)"),
+        im_str!("Name (Z-A)"),
+        im_str!("Date Modified"),
+    ];
+    let mut preferences = app_state.get_preferences().clone();
+    let mut current_sort = sorts
+        .iter()
+        .position(|s| *s == preferences.content_sort)
+        .unwrap_or(0) as i32;
+    let height_in_items = labels.len() as i32;
+    ui.push_item_width(120.0); // TODO DPI?
+    if ui.combo(im_str!("Sort"), &mut current_sort, &labels, height_in_items) {
+        preferences.content_sort = sorts[current_sort as usize];
+        commands.set_preferences(&preferences);
+    }
+    ui.pop_item_width();
+}
+
+fn draw_tabs<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut CommandBuffer) {
     if ui.small_button(im_str!("Frames")) {
         commands.switch_to_content_tab(ContentTab::Frames);
     }
@@ -14,12 +82,112 @@ fn draw_tabs<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer) {
     if ui.small_button(im_str!("Animations")) {
         commands.switch_to_content_tab(ContentTab::Animations);
     }
+    ui.same_line(0.0);
+    draw_sort_options(ui, app_state, commands);
+}
+
+// Frames listed between the last touched frame and the clicked one, in display order
+fn get_frame_range(
+    document: &Document,
+    frames: &[(&OsStr, &Frame)],
+    clicked_index: usize,
+) -> Option<Vec<PathBuf>> {
+    let anchor = match &document.view.selection {
+        Some(Selection::Frame(p)) => Some(&p.last_touched),
+        _ => None,
+    }?;
+    let anchor_index = frames.iter().position(|(_, f)| f.get_source() == anchor)?;
+    let (from, to) = if anchor_index <= clicked_index {
+        (anchor_index, clicked_index)
+    } else {
+        (clicked_index, anchor_index)
+    };
+    Some(
+        frames[from..=to]
+            .iter()
+            .map(|(_, f)| f.get_source().to_owned())
+            .collect(),
+    )
+}
+
+// Sized from the font so thumbnails follow the DPI scaling of text
+fn get_thumbnail_size<'a>(ui: &Ui<'a>) -> f32 {
+    let text_size: Vector2D<f32> = ui.calc_text_size(im_str!("M"), false, -1.0).into();
+    (text_size.y * 2.0).round()
 }
 
-fn draw_frames<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer, document: &Document) {
+fn is_row_visible<'a>(ui: &Ui<'a>, height: f32) -> bool {
+    let window_position: Vector2D<f32> = ui.get_window_pos().into();
+    let window_size: Vector2D<f32> = ui.get_window_size().into();
+    let row_position: Vector2D<f32> = ui.get_cursor_screen_pos().into();
+    row_position.y + height >= window_position.y
+        && row_position.y <= window_position.y + window_size.y
+}
+
+fn draw_thumbnail<'a>(ui: &Ui<'a>, texture_cache: &TextureCache, frame: &Frame, size: f32) {
+    let space = vec2(size, size);
+    let cursor_pos: Vector2D<f32> = ui.get_cursor_pos().into();
+    match texture_cache.get(frame.get_source()) {
+        Some(TextureCacheResult::Loaded(texture)) => {
+            if let Some(fill) = utils::fill(space, texture.size) {
+                let draw_position = cursor_pos + fill.rect.origin.to_vector();
+                ui.set_cursor_pos(draw_position.to_tuple());
+                ui.image(texture.id, fill.rect.size.to_tuple()).build();
+            }
+        }
+        Some(TextureCacheResult::Loading) => {
+            draw_spinner(ui, &ui.get_window_draw_list(), space);
+        }
+        _ => (),
+    }
+}
+
+fn draw_frames<'a>(
+    ui: &Ui<'a>,
+    app_state: &AppState,
+    commands: &mut CommandBuffer,
+    texture_cache: &TextureCache,
+    document: &Document,
+) {
     if ui.small_button(im_str!("Import…")) {
         commands.import(document);
     }
+    ui.same_line(0.0);
+    if ui.small_button(im_str!("Import Folder…")) {
+        commands.import_folder(document);
+    }
+    ui.same_line(0.0);
+    if ui.small_button(im_str!("Import Grid…")) {
+        commands.import_grid(document);
+    }
+    ui.same_line(0.0);
+    if ui.small_button(im_str!("Import Aseprite…")) {
+        commands.import_aseprite(document);
+    }
+    ui.same_line(0.0);
+    if ui.small_button(im_str!("Build Animations…")) {
+        commands.begin_build_animations_from_frame_names();
+    }
+    if let Some(Selection::Frame(paths)) = &document.view.selection {
+        if let Some(frame) = document.sheet.get_frame(&paths.last_touched) {
+            ui.same_line(0.0);
+            if ui.small_button(im_str!("Refresh")) {
+                commands.refresh_frame(frame, None);
+            }
+            ui.same_line(0.0);
+            if ui.small_button(im_str!("Refresh & Keep Position")) {
+                let old_size = match texture_cache.get(frame.get_source()) {
+                    Some(TextureCacheResult::Loaded(t)) => Some(t.size.to_u32()),
+                    _ => None,
+                };
+                commands.refresh_frame(frame, old_size);
+            }
+            ui.same_line(0.0);
+            if ui.small_button(im_str!("Copy Rectangle")) {
+                commands.copy_frame_rectangle(&document.sheet, frame);
+            }
+        }
+    }
     let mut frames: Vec<(&OsStr, &Frame)> = document
         .sheet
         .frames_iter()
@@ -31,23 +199,54 @@ fn draw_frames<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer, document: &Documen
             }
         })
         .collect();
-    frames.sort_unstable();
-    for (name, frame) in frames.iter() {
+    match app_state.get_preferences().content_sort {
+        ContentSort::NameAscending => frames.sort_unstable(),
+        ContentSort::NameDescending => frames.sort_unstable_by(|a, b| b.cmp(a)),
+        ContentSort::DateModified => frames.sort_by(|a, b| {
+            let a_time = get_modification_time(a.1.get_source());
+            let b_time = get_modification_time(b.1.get_source());
+            b_time.cmp(&a_time).then_with(|| a.cmp(b))
+        }),
+    };
+    let thumbnail_size = get_thumbnail_size(ui);
+    let mut num_thumbnails = 0;
+    for (index, (name, frame)) in frames.iter().enumerate() {
         let is_selected = match &document.view.selection {
-            Some(Selection::Frame(p)) => p == frame.get_source(),
+            Some(Selection::Frame(p)) => p.contains(&frame.get_source().to_owned()),
             _ => false,
         };
 
+        // The label is drawn on the same line, past the thumbnail
+        let cursor_pos: Vector2D<f32> = ui.get_cursor_pos().into();
+        if num_thumbnails < MAX_THUMBNAILS_PER_FRAME && is_row_visible(ui, thumbnail_size) {
+            draw_thumbnail(ui, texture_cache, frame, thumbnail_size);
+            num_thumbnails += 1;
+        }
+        let label_offset = vec2(thumbnail_size * 1.25, 0.0).round();
+        ui.set_cursor_pos((cursor_pos + label_offset).to_tuple());
+
+        let label = match texture_cache.get(frame.get_source()) {
+            Some(TextureCacheResult::Missing) => format!("{} (missing)", name.to_string_lossy()),
+            _ => name.to_string_lossy().into_owned(),
+        };
+
         let mut flags = ImGuiSelectableFlags::empty();
         flags.set(ImGuiSelectableFlags::AllowDoubleClick, true);
         if ui.selectable(
-            &ImString::new(name.to_string_lossy()),
+            &ImString::new(label),
             is_selected,
             flags,
-            ImVec2::new(0.0, 0.0),
+            ImVec2::new(0.0, thumbnail_size),
         ) {
             if ui.imgui().is_mouse_double_clicked(ImMouseButton::Left) {
                 commands.edit_frame(frame);
+            } else if ui.imgui().key_ctrl() {
+                commands.toggle_frame_selection(frame);
+            } else if ui.imgui().key_shift() {
+                match get_frame_range(document, &frames, index) {
+                    Some(range) => commands.select_frame_range(range),
+                    None => commands.select_frame(frame),
+                }
             } else {
                 commands.select_frame(frame);
             }
@@ -62,35 +261,185 @@ fn draw_frames<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer, document: &Documen
     }
 }
 
-fn draw_animations<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer, document: &Document) {
-    if ui.small_button(im_str!("Add")) {
-        commands.create_animation();
+fn draw_animation<'a>(
+    ui: &Ui<'a>,
+    commands: &mut CommandBuffer,
+    document: &Document,
+    animation: &Animation,
+) {
+    let is_selected = match &document.view.selection {
+        Some(Selection::Animation(a)) => a == animation.get_name(),
+        _ => false,
+    };
+    let mut flags = ImGuiSelectableFlags::empty();
+    flags.set(ImGuiSelectableFlags::AllowDoubleClick, true);
+    if ui.selectable(
+        &ImString::new(animation.get_name()),
+        is_selected,
+        flags,
+        ImVec2::new(0.0, 0.0),
+    ) {
+        if ui.imgui().is_mouse_double_clicked(ImMouseButton::Left) {
+            commands.edit_animation(animation);
+        } else {
+            commands.select_animation(animation);
+        }
     }
-    let mut animations: Vec<&Animation> = document.sheet.animations_iter().collect();
-    animations.sort_unstable();
-    for animation in animations.iter() {
-        let is_selected = match &document.view.selection {
-            Some(Selection::Animation(a)) => a == animation.get_name(),
-            _ => false,
+}
+
+// Filters on tags which no longer exist are ignored
+fn get_animation_tag_filter(document: &Document) -> Option<&str> {
+    document
+        .view
+        .animation_tag_filter
+        .as_ref()
+        .map(|t| t.as_str())
+        .filter(|t| document.sheet.animations_iter().any(|a| a.has_tag(t)))
+}
+
+fn draw_tag_filter<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer, document: &Document) {
+    let mut tags: Vec<&str> = document
+        .sheet
+        .animations_iter()
+        .flat_map(|a| a.tags_iter())
+        .map(|t| t.as_str())
+        .collect();
+    tags.sort_unstable();
+    tags.dedup();
+    if tags.is_empty() {
+        return;
+    }
+
+    let mut labels = vec![ImString::new("All")];
+    labels.extend(tags.iter().map(|t| ImString::new(*t)));
+    let labels: Vec<&ImStr> = labels.iter().map(|l| &**l).collect();
+    let mut current_tag = match get_animation_tag_filter(document) {
+        Some(filter) => tags.iter().position(|t| *t == filter).map_or(0, |i| i + 1),
+        None => 0,
+    } as i32;
+    let height_in_items = std::cmp::min(labels.len(), 10) as i32;
+    ui.push_item_width(120.0); // TODO DPI?
+    if ui.combo(im_str!("Tag"), &mut current_tag, &labels, height_in_items) {
+        let filter = match current_tag {
+            0 => None,
+            i => tags.get(i as usize - 1).map(|t| (*t).to_owned()),
         };
-        let mut flags = ImGuiSelectableFlags::empty();
-        flags.set(ImGuiSelectableFlags::AllowDoubleClick, true);
-        if ui.selectable(
-            &ImString::new(animation.get_name()),
-            is_selected,
-            flags,
-            ImVec2::new(0.0, 0.0),
-        ) {
-            if ui.imgui().is_mouse_double_clicked(ImMouseButton::Left) {
-                commands.edit_animation(animation);
-            } else {
-                commands.select_animation(animation);
+        commands.set_animation_tag_filter(filter);
+    }
+    ui.pop_item_width();
+}
+
+fn draw_animations<'a>(
+    ui: &Ui<'a>,
+    app_state: &AppState,
+    commands: &mut CommandBuffer,
+    document: &Document,
+) {
+    if ui.small_button(im_str!("Add")) {
+        commands.create_animation(app_state.get_preferences().new_animations_loop);
+    }
+    ui.same_line(0.0);
+    if ui.small_button(im_str!("Group…")) {
+        commands.begin_set_animation_group();
+    }
+    ui.same_line(0.0);
+    if ui.small_button(im_str!("Direction…")) {
+        commands.begin_set_animation_direction();
+    }
+    ui.same_line(0.0);
+    if ui.small_button(im_str!("Merge Duplicates…")) {
+        commands.begin_merge_duplicate_animations();
+    }
+    draw_tag_filter(ui, commands, document);
+
+    let mut animations: Vec<&Animation> = document
+        .sheet
+        .animations_iter()
+        .filter(|a| match get_animation_tag_filter(document) {
+            Some(tag) => a.has_tag(tag),
+            None => true,
+        })
+        .collect();
+    // Groups are shown in name order, the sort preference orders animations within them
+    animations.sort_by(|a, b| match app_state.get_preferences().content_sort {
+        ContentSort::NameAscending => a.cmp(b),
+        ContentSort::NameDescending => b.get_name().cmp(a.get_name()),
+        ContentSort::DateModified => {
+            let a_time = get_animation_modification_time(&document.sheet, a);
+            let b_time = get_animation_modification_time(&document.sheet, b);
+            b_time.cmp(&a_time).then_with(|| a.cmp(b))
+        }
+    });
+
+    let mut root = AnimationGroup::default();
+    for animation in animations {
+        root.insert(&animation.get_group_path(), animation);
+    }
+
+    // Top-level groups are collapsing headers, nested groups are tree nodes within them
+    if root.groups.is_empty() {
+        draw_animation_group(ui, commands, document, &root);
+        return;
+    }
+    if !root.animations.is_empty() {
+        let header = im_str!("Ungrouped");
+        if ui.collapsing_header(header).default_open(true).build() {
+            for animation in &root.animations {
+                draw_animation(ui, commands, document, animation);
             }
         }
     }
+    for (name, group) in &root.groups {
+        let header = ImString::new(*name);
+        if ui.collapsing_header(&header).default_open(true).build() {
+            draw_animation_group(ui, commands, document, group);
+        }
+    }
+}
+
+#[derive(Default)]
+struct AnimationGroup<'a> {
+    groups: BTreeMap<&'a str, AnimationGroup<'a>>,
+    animations: Vec<&'a Animation>,
+}
+
+impl<'a> AnimationGroup<'a> {
+    fn insert(&mut self, path: &[&'a str], animation: &'a Animation) {
+        match path.split_first() {
+            Some((name, rest)) => self
+                .groups
+                .entry(*name)
+                .or_insert_with(AnimationGroup::default)
+                .insert(rest, animation),
+            None => self.animations.push(animation),
+        }
+    }
+}
+
+fn draw_animation_group<'a>(
+    ui: &Ui<'a>,
+    commands: &mut CommandBuffer,
+    document: &Document,
+    group: &AnimationGroup,
+) {
+    for (name, subgroup) in &group.groups {
+        let label = ImString::new(*name);
+        ui.tree_node(&label)
+            .opened(true, ImGuiCond::FirstUseEver)
+            .build(|| draw_animation_group(ui, commands, document, subgroup));
+    }
+    for animation in &group.animations {
+        draw_animation(ui, commands, document, animation);
+    }
 }
 
-pub fn draw<'a>(ui: &Ui<'a>, rect: &Rect<f32>, app_state: &AppState, commands: &mut CommandBuffer) {
+pub fn draw<'a>(
+    ui: &Ui<'a>,
+    rect: &Rect<f32>,
+    app_state: &AppState,
+    commands: &mut CommandBuffer,
+    texture_cache: &TextureCache,
+) {
     ui.with_style_vars(&[WindowRounding(0.0), WindowBorderSize(0.0)], || {
         ui.window(im_str!("Content"))
             .position(rect.origin.to_tuple(), ImGuiCond::Always)
@@ -101,11 +450,15 @@ pub fn draw<'a>(ui: &Ui<'a>, rect: &Rect<f32>, app_state: &AppState, commands: &
             .build(|| {
                 // TODO draw something before document is loaded?
                 if let Some(document) = app_state.get_current_document() {
-                    draw_tabs(ui, commands);
+                    draw_tabs(ui, app_state, commands);
                     ui.separator();
                     match document.view.content_tab {
-                        ContentTab::Frames => draw_frames(ui, commands, document),
-                        ContentTab::Animations => draw_animations(ui, commands, document),
+                        ContentTab::Frames => {
+                            draw_frames(ui, app_state, commands, texture_cache, document)
+                        }
+                        ContentTab::Animations => {
+                            draw_animations(ui, app_state, commands, document)
+                        }
                     }
                 }
             });
@@ -1,24 +1,153 @@
+use euclid::*;
 use imgui::StyleVar::*;
 use imgui::*;
 
 use crate::sheet::{Frame, Hitbox};
 use crate::state::*;
+use crate::streamer::{TextureCache, TextureCacheResult};
 use crate::ui::Rect;
 
+// Edits are committed on Enter so typing does not flood the undo history
+fn draw_hitbox_rectangle<'a>(
+    ui: &Ui<'a>,
+    commands: &mut CommandBuffer,
+    texture_cache: &TextureCache,
+    frame: &Frame,
+    hitbox: &Hitbox,
+) {
+    let position = hitbox.get_position();
+    let size = hitbox.get_size();
+
+    ui.push_item_width(120.0); // TODO DPI?
+    let mut from_center = [position.x, position.y];
+    if ui
+        .input_int2(im_str!("Left, Top (from center)"), &mut from_center)
+        .enter_returns_true(true)
+        .build()
+    {
+        commands.set_selection_position(vec2(from_center[0], from_center[1]));
+    }
+
+    // Frames are drawn with their center on the workbench origin
+    if let Some(TextureCacheResult::Loaded(texture)) = texture_cache.get(frame.get_source()) {
+        let half_size = (texture.size / 2.0).floor().to_i32();
+        let mut from_top_left = [position.x + half_size.x, position.y + half_size.y];
+        if ui
+            .input_int2(im_str!("Left, Top (from top-left)"), &mut from_top_left)
+            .enter_returns_true(true)
+            .build()
+        {
+            commands.set_selection_position(vec2(from_top_left[0], from_top_left[1]) - half_size);
+        }
+    }
+
+    let mut dimensions = [size.x as i32, size.y as i32];
+    if ui
+        .input_int2(im_str!("Width, Height"), &mut dimensions)
+        .enter_returns_true(true)
+        .build()
+    {
+        let width = std::cmp::max(0, dimensions[0]) as u32;
+        let height = std::cmp::max(0, dimensions[1]) as u32;
+        commands.set_hitbox_size(vec2(width, height));
+    }
+    ui.pop_item_width();
+
+    if let Some(TextureCacheResult::Loaded(texture)) = texture_cache.get(frame.get_source()) {
+        if ui.small_button(im_str!("Mirror Horizontally")) {
+            commands.mirror_hitbox(texture.size.to_u32());
+        }
+    }
+}
+
+fn draw_hitbox_layout_buttons<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer) {
+    ui.text(im_str!("Align:"));
+    let alignments = [
+        (im_str!("Left"), HitboxAlignment::Left),
+        (im_str!("Center"), HitboxAlignment::HorizontalCenter),
+        (im_str!("Right"), HitboxAlignment::Right),
+        (im_str!("Top"), HitboxAlignment::Top),
+        (im_str!("Middle"), HitboxAlignment::VerticalCenter),
+        (im_str!("Bottom"), HitboxAlignment::Bottom),
+    ];
+    for (label, alignment) in alignments.iter() {
+        ui.same_line(0.0);
+        if ui.small_button(label) {
+            commands.align_hitboxes(*alignment);
+        }
+    }
+
+    ui.text(im_str!("Distribute:"));
+    ui.same_line(0.0);
+    if ui.small_button(im_str!("Horizontally")) {
+        commands.distribute_hitboxes(HitboxDistribution::Horizontal);
+    }
+    ui.same_line(0.0);
+    if ui.small_button(im_str!("Vertically")) {
+        commands.distribute_hitboxes(HitboxDistribution::Vertical);
+    }
+}
+
+// Hitboxes listed between the last touched hitbox and the clicked one, in display order
+fn get_hitbox_range(
+    document: &Document,
+    frame: &Frame,
+    hitboxes: &[&Hitbox],
+    clicked_index: usize,
+) -> Option<Vec<String>> {
+    let anchor = match &document.view.selection {
+        Some(Selection::Hitbox(p, n)) if p == frame.get_source() => Some(&n.last_touched),
+        _ => None,
+    }?;
+    let anchor_index = hitboxes.iter().position(|h| h.get_name() == *anchor)?;
+    let (from, to) = if anchor_index <= clicked_index {
+        (anchor_index, clicked_index)
+    } else {
+        (clicked_index, anchor_index)
+    };
+    Some(
+        hitboxes[from..=to]
+            .iter()
+            .map(|h| h.get_name().to_owned())
+            .collect(),
+    )
+}
+
 fn draw_hitboxes<'a>(
     ui: &Ui<'a>,
     commands: &mut CommandBuffer,
+    texture_cache: &TextureCache,
     document: &Document,
     frame: &Frame,
 ) {
+    // Hitbox positions are relative to the center of the frame
+    if ui.small_button(im_str!("Add Hitbox")) {
+        commands.create_hitbox(vec2(0.0, 0.0));
+    }
+
     let mut hitboxes: Vec<&Hitbox> = frame.hitboxes_iter().collect();
     hitboxes.sort_unstable();
-    for hitbox in hitboxes.iter() {
+    for (index, hitbox) in hitboxes.iter().enumerate() {
         let is_selected = match &document.view.selection {
-            Some(Selection::Hitbox(p, n)) => p == frame.get_source() && n == hitbox.get_name(),
+            Some(Selection::Hitbox(p, n)) => {
+                p == frame.get_source() && n.contains(&hitbox.get_name().to_owned())
+            }
             _ => false,
         };
 
+        ui.push_id(index as i32);
+        if ui.small_button(im_str!("Delete")) {
+            commands.select_hitbox(hitbox);
+            commands.delete_selection();
+        }
+        ui.same_line(0.0);
+        if ui.small_button(im_str!("Rename…")) {
+            commands.select_hitbox(hitbox);
+            commands.begin_rename_selection();
+        }
+        ui.pop_id();
+        ui.same_line(0.0);
+
         let flags = ImGuiSelectableFlags::empty();
         if ui.selectable(
             &ImString::new(hitbox.get_name()),
@@ -26,12 +155,40 @@ fn draw_hitboxes<'a>(
             flags,
             ImVec2::new(0.0, 0.0),
         ) {
-            commands.select_hitbox(hitbox);
+            if ui.imgui().key_ctrl() {
+                commands.toggle_hitbox_selection(hitbox);
+            } else if ui.imgui().key_shift() {
+                match get_hitbox_range(document, frame, &hitboxes, index) {
+                    Some(range) => commands.select_hitbox_range(range),
+                    None => commands.select_hitbox(hitbox),
+                }
+            } else {
+                commands.select_hitbox(hitbox);
+            }
+        }
+    }
+
+    if let Some(Selection::Hitbox(p, n)) = &document.view.selection {
+        if p == frame.get_source() && n.items.len() > 1 {
+            ui.separator();
+            draw_hitbox_layout_buttons(ui, commands);
+        }
+        if p == frame.get_source() {
+            if let Some(hitbox) = frame.get_hitbox(&n.last_touched) {
+                ui.separator();
+                draw_hitbox_rectangle(ui, commands, texture_cache, frame, hitbox);
+            }
         }
     }
 }
 
-pub fn draw<'a>(ui: &Ui<'a>, rect: &Rect<f32>, app_state: &AppState, commands: &mut CommandBuffer) {
+pub fn draw<'a>(
+    ui: &Ui<'a>,
+    rect: &Rect<f32>,
+    app_state: &AppState,
+    commands: &mut CommandBuffer,
+    texture_cache: &TextureCache,
+) {
     ui.with_style_vars(&[WindowRounding(0.0), WindowBorderSize(0.0)], || {
         ui.window(im_str!("Hitboxes"))
             .position(rect.origin.to_tuple(), ImGuiCond::Always)
@@ -43,7 +200,7 @@ pub fn draw<'a>(ui: &Ui<'a>, rect: &Rect<f32>, app_state: &AppState, commands: &
                 if let Some(document) = app_state.get_current_document() {
                     if let Some(WorkbenchItem::Frame(frame_path)) = &document.view.workbench_item {
                         if let Some(frame) = document.sheet.get_frame(frame_path) {
-                            draw_hitboxes(ui, commands, document, frame);
+                            draw_hitboxes(ui, commands, texture_cache, document, frame);
                         }
                     }
                 }
@@ -4,9 +4,11 @@ use glutin::VirtualKeyCode;
 use imgui::StyleVar::*;
 use imgui::*;
 use std::borrow::Borrow;
+use std::cell::Cell;
 
+use crate::export::PackSettings;
 use crate::sheet::constants::*;
-use crate::sheet::ExportFormat;
+use crate::sheet::{ExportFormat, ExportSettings, PackStrategy, TemplateVariable};
 use crate::state::*;
 use crate::streamer::{TextureCache, TextureCacheResult};
 use crate::utils;
@@ -18,6 +20,11 @@ mod spinner;
 mod timeline_window;
 mod workbench_window;
 
+thread_local! {
+    // Whether Space is held down, and whether it was used to pan while held
+    static SPACE_HOLD: Cell<Option<bool>> = Cell::new(None);
+}
+
 pub fn init(window: &glutin::Window) -> ImGui {
     let mut imgui_instance = ImGui::init();
     imgui_instance.set_ini_filename(None);
@@ -86,18 +93,17 @@ pub fn run<'a>(
     let content_width = 0.12 * window_width;
     let hitboxes_width = 0.12 * window_width;
 
-    let (_, menu_height) = draw_main_menu(ui, app_state, &mut commands);
+    let mut fit_workbench = false;
+    let (_, menu_height) = draw_main_menu(ui, app_state, &mut commands, &mut fit_workbench);
 
-    {
-        let workbench_width = window_width - content_width - hitboxes_width;
-        let workbench_rect = rect(
-            content_width,
-            menu_height,
-            workbench_width,
-            window_height - menu_height,
-        );
-        workbench_window::draw(ui, &workbench_rect, app_state, &mut commands, texture_cache);
-    }
+    let workbench_width = window_width - content_width - hitboxes_width;
+    let workbench_rect = rect(
+        content_width,
+        menu_height,
+        workbench_width,
+        window_height - menu_height,
+    );
+    workbench_window::draw(ui, &workbench_rect, app_state, &mut commands, texture_cache);
 
     {
         let documents_rect = rect(content_width, menu_height, window_width, 0.0);
@@ -109,7 +115,7 @@ pub fn run<'a>(
 
     {
         let content_rect = rect(0.0, menu_height, content_width, content_height);
-        content_window::draw(ui, &content_rect, app_state, &mut commands);
+        content_window::draw(ui, &content_rect, app_state, &mut commands, texture_cache);
     }
 
     {
@@ -122,7 +128,7 @@ pub fn run<'a>(
             selection_width,
             selection_height,
         );
-        selection_window::draw(ui, &selection_rect, app_state, texture_cache);
+        selection_window::draw(ui, &selection_rect, app_state, &mut commands, texture_cache);
     }
 
     {
@@ -145,23 +151,48 @@ pub fn run<'a>(
             hitboxes_width,
             hitboxes_height,
         );
-        hitboxes_window::draw(ui, &hitboxes_rect, app_state, &mut commands);
+        hitboxes_window::draw(ui, &hitboxes_rect, app_state, &mut commands, texture_cache);
     }
 
     draw_export_popup(ui, app_state, &mut commands);
     draw_rename_popup(ui, app_state, &mut commands);
+    draw_revert_popup(ui, app_state, &mut commands);
+    draw_invalid_save_popup(ui, app_state, &mut commands);
+    draw_export_progress_popup(ui, app_state, &mut commands);
+    draw_build_animations_popup(ui, app_state, &mut commands);
+    draw_merge_duplicate_animations_popup(ui, app_state, &mut commands);
+    draw_recover_autosave_popup(ui, app_state, &mut commands);
+    draw_relink_frames_popup(ui, app_state, &mut commands);
+    draw_export_overwrite_popup(ui, app_state, &mut commands);
+    draw_export_validation_popup(ui, app_state, &mut commands);
+    draw_grid_import_popup(ui, app_state, &mut commands);
+    draw_close_popup(ui, app_state, &mut commands);
     draw_exit_popup(ui, app_state, &mut commands);
+    draw_error_popup(ui, app_state, &mut commands);
 
     update_drag_and_drop(ui, app_state, &mut commands);
     draw_drag_and_drop(ui, app_state, texture_cache);
-    process_shortcuts(ui, app_state, &mut commands);
+    process_shortcuts(ui, app_state, &mut commands, &mut fit_workbench);
+
+    if fit_workbench {
+        if let Some(document) = app_state.get_current_document() {
+            if let Some(bounds) = workbench_window::get_content_bounds(document, texture_cache) {
+                commands.workbench_fit(bounds, workbench_rect.size.to_vector());
+            }
+        }
+    }
 
     Ok(commands)
 }
 
 fn save_all(app_state: &AppState, commands: &mut CommandBuffer) {
     for document in app_state.documents_iter() {
-        commands.save(&document.source, &document.sheet, document.get_version());
+        commands.save(
+            &document.source,
+            &document.sheet,
+            document.get_version(),
+            app_state.get_preferences().absolute_paths,
+        );
     }
 }
 
@@ -169,6 +200,7 @@ fn draw_main_menu<'a>(
     ui: &Ui<'a>,
     app_state: &AppState,
     commands: &mut CommandBuffer,
+    fit_workbench: &mut bool,
 ) -> (f32, f32) {
     let size = &mut (0.0, 0.0);
     let has_document = app_state.get_current_document().is_some();
@@ -183,6 +215,9 @@ fn draw_main_menu<'a>(
                 {
                     commands.begin_new_document();
                 }
+                if ui.menu_item(im_str!("New Sheet from Template…")).build() {
+                    commands.begin_new_document_from_template();
+                }
                 if ui
                     .menu_item(im_str!("Open Sheet…"))
                     .shortcut(im_str!("Ctrl+O"))
@@ -198,7 +233,12 @@ fn draw_main_menu<'a>(
                     .build()
                 {
                     if let Some(document) = app_state.get_current_document() {
-                        commands.save(&document.source, &document.sheet, document.get_version());
+                        commands.save(
+                            &document.source,
+                            &document.sheet,
+                            document.get_version(),
+                            app_state.get_preferences().absolute_paths,
+                        );
                     }
                 }
                 if ui
@@ -208,7 +248,12 @@ fn draw_main_menu<'a>(
                     .build()
                 {
                     if let Some(document) = app_state.get_current_document() {
-                        commands.save_as(&document.source, &document.sheet, document.get_version());
+                        commands.save_as(
+                            &document.source,
+                            &document.sheet,
+                            document.get_version(),
+                            app_state.get_preferences().absolute_paths,
+                        );
                     }
                 }
                 if ui
@@ -219,6 +264,20 @@ fn draw_main_menu<'a>(
                 {
                     save_all(app_state, commands);
                 }
+                let is_modified = app_state
+                    .get_current_document()
+                    .map(|d| !d.is_saved())
+                    .unwrap_or(false);
+                if ui
+                    .menu_item(im_str!("Revert to Saved"))
+                    .enabled(is_modified)
+                    .build()
+                {
+                    if let Some(document) = app_state.get_current_document() {
+                        commands.request_revert_document(document);
+                    }
+                }
+                ui.separator();
                 if ui
                     .menu_item(im_str!("Export"))
                     .shortcut(im_str!("Ctrl+E"))
@@ -226,9 +285,16 @@ fn draw_main_menu<'a>(
                     .build()
                 {
                     if let Some(document) = app_state.get_current_document() {
-                        commands.export(&document.sheet);
+                        commands.export(document);
                     }
                 }
+                if ui
+                    .menu_item(im_str!("Export All"))
+                    .enabled(has_document)
+                    .build()
+                {
+                    commands.export_all(app_state.documents_iter());
+                }
                 if ui
                     .menu_item(im_str!("Export As…"))
                     .shortcut(im_str!("Ctrl+Shift+E"))
@@ -237,6 +303,31 @@ fn draw_main_menu<'a>(
                 {
                     commands.begin_export_as();
                 }
+                if ui
+                    .menu_item(im_str!("Validate Export"))
+                    .enabled(has_document)
+                    .build()
+                {
+                    if let Some(document) = app_state.get_current_document() {
+                        commands.validate_export(document);
+                    }
+                }
+                let edited_animation = app_state.get_current_document().and_then(|d| {
+                    match &d.view.workbench_item {
+                        Some(WorkbenchItem::Animation(a)) => d.sheet.get_animation(a),
+                        _ => None,
+                    }
+                    .map(|a| (d, a))
+                });
+                if ui
+                    .menu_item(im_str!("Export Animation as GIF…"))
+                    .enabled(edited_animation.is_some())
+                    .build()
+                {
+                    if let Some((document, animation)) = edited_animation {
+                        commands.begin_export_animation_gif(&document.sheet, animation);
+                    }
+                }
                 ui.separator();
                 if ui
                     .menu_item(im_str!("Close"))
@@ -259,8 +350,8 @@ fn draw_main_menu<'a>(
             ui.menu(im_str!("Edit")).build(|| {
                 let undo_command_name = app_state
                     .get_current_document()
-                    .and_then(|d| d.get_undo_command())
-                    .and_then(|c| Some(format!("Undo {}", c)));
+                    .and_then(|d| d.get_undo_label())
+                    .and_then(|l| Some(format!("Undo {}", l)));
                 if ui
                     .menu_item(&ImString::new(
                         undo_command_name.clone().unwrap_or("Undo".to_owned()),
@@ -274,8 +365,8 @@ fn draw_main_menu<'a>(
 
                 let redo_command_name = app_state
                     .get_current_document()
-                    .and_then(|d| d.get_redo_command())
-                    .and_then(|c| Some(format!("Redo {}", c)));
+                    .and_then(|d| d.get_redo_label())
+                    .and_then(|l| Some(format!("Redo {}", l)));
                 if ui
                     .menu_item(&ImString::new(
                         redo_command_name.clone().unwrap_or("Redo".to_owned()),
@@ -286,6 +377,129 @@ fn draw_main_menu<'a>(
                 {
                     commands.redo();
                 }
+                ui.separator();
+                let has_selection = app_state
+                    .get_current_document()
+                    .map_or(false, |d| d.view.selection.is_some());
+                if ui
+                    .menu_item(im_str!("Copy"))
+                    .shortcut(im_str!("Ctrl+C"))
+                    .enabled(has_selection)
+                    .build()
+                {
+                    commands.copy_selection();
+                }
+                if ui
+                    .menu_item(im_str!("Paste"))
+                    .shortcut(im_str!("Ctrl+V"))
+                    .enabled(has_document && app_state.get_clipboard().is_some())
+                    .build()
+                {
+                    commands.paste();
+                }
+                if ui
+                    .menu_item(im_str!("Select All"))
+                    .shortcut(im_str!("Ctrl+A"))
+                    .enabled(has_document)
+                    .build()
+                {
+                    commands.select_all();
+                }
+                if ui
+                    .menu_item(im_str!("Invert Selection"))
+                    .shortcut(im_str!("Ctrl+I"))
+                    .enabled(has_document)
+                    .build()
+                {
+                    commands.invert_selection();
+                }
+                let is_hitbox_selected = match app_state
+                    .get_current_document()
+                    .and_then(|d| d.view.selection.as_ref())
+                {
+                    Some(Selection::Hitbox(_, _)) => true,
+                    _ => false,
+                };
+                if ui
+                    .menu_item(im_str!("Duplicate Hitbox"))
+                    .shortcut(im_str!("Ctrl+D"))
+                    .enabled(is_hitbox_selected)
+                    .build()
+                {
+                    commands.duplicate_hitbox();
+                }
+                let is_animation_frame_selected = match app_state
+                    .get_current_document()
+                    .and_then(|d| d.view.selection.as_ref())
+                {
+                    Some(Selection::AnimationFrame(_, _)) => true,
+                    _ => false,
+                };
+                if ui
+                    .menu_item(im_str!("Flip Horizontally"))
+                    .shortcut(im_str!("H"))
+                    .enabled(is_animation_frame_selected)
+                    .build()
+                {
+                    commands.flip_selection_horizontally();
+                }
+                if ui
+                    .menu_item(im_str!("Flip Vertically"))
+                    .shortcut(im_str!("V"))
+                    .enabled(is_animation_frame_selected)
+                    .build()
+                {
+                    commands.flip_selection_vertically();
+                }
+                let is_editing_animation = match app_state
+                    .get_current_document()
+                    .and_then(|d| d.view.workbench_item.as_ref())
+                {
+                    Some(WorkbenchItem::Animation(_)) => true,
+                    _ => false,
+                };
+                if ui
+                    .menu_item(im_str!("Reverse Animation"))
+                    .enabled(is_editing_animation)
+                    .build()
+                {
+                    commands.reverse_animation();
+                }
+                let is_frame_selected = match app_state
+                    .get_current_document()
+                    .and_then(|d| d.view.selection.as_ref())
+                {
+                    Some(Selection::Frame(_)) => true,
+                    _ => false,
+                };
+                if ui
+                    .menu_item(im_str!("Insert Frames At Playhead"))
+                    .shortcut(im_str!("Insert"))
+                    .enabled(is_editing_animation && is_frame_selected)
+                    .build()
+                {
+                    commands.insert_frames_at_playhead();
+                }
+                ui.separator();
+                let mut preferences = app_state.get_preferences().clone();
+                ui.menu_item(im_str!("New Animations Loop"))
+                    .selected(&mut preferences.new_animations_loop)
+                    .build();
+                ui.menu_item(im_str!("Save Absolute Paths"))
+                    .selected(&mut preferences.absolute_paths)
+                    .build();
+                ui.menu_item(im_str!("Export On Template Change"))
+                    .selected(&mut preferences.live_export)
+                    .build();
+                ui.menu_item(im_str!("Pan With Right Mouse Button"))
+                    .selected(&mut preferences.right_drag_pan)
+                    .build();
+                ui.menu_item(im_str!("Pan With Space + Left Mouse Button"))
+                    .selected(&mut preferences.space_drag_pan)
+                    .build();
+                if &preferences != app_state.get_preferences() {
+                    commands.set_preferences(&preferences);
+                }
             });
 
             ui.menu(im_str!("View")).build(|| {
@@ -296,6 +510,14 @@ fn draw_main_menu<'a>(
                 {
                     commands.workbench_center();
                 }
+                if ui
+                    .menu_item(im_str!("Fit Workbench"))
+                    .shortcut(im_str!("Ctrl+Shift+Space"))
+                    .enabled(has_document)
+                    .build()
+                {
+                    *fit_workbench = true;
+                }
                 if ui
                     .menu_item(im_str!("Zoom In (Workbench)"))
                     .shortcut(im_str!("Ctrl++"))
@@ -317,6 +539,78 @@ fn draw_main_menu<'a>(
                 {
                     commands.workbench_reset_zoom();
                 }
+                if ui
+                    .menu_item(im_str!("Reload Textures"))
+                    .shortcut(im_str!("F5"))
+                    .enabled(has_document)
+                    .build()
+                {
+                    commands.reload_textures();
+                }
+                let mut onion_skin = app_state
+                    .get_current_document()
+                    .map_or(false, |d| d.view.onion_skin_count > 0);
+                if ui
+                    .menu_item(im_str!("Onion Skinning"))
+                    .selected(&mut onion_skin)
+                    .enabled(has_document)
+                    .build()
+                {
+                    commands.toggle_onion_skin();
+                }
+                let mut snap_to_grid = app_state
+                    .get_current_document()
+                    .map_or(false, |d| d.view.snap_to_grid);
+                if ui
+                    .menu_item(im_str!("Snap Hitboxes To Grid"))
+                    .selected(&mut snap_to_grid)
+                    .enabled(has_document)
+                    .build()
+                {
+                    commands.toggle_snap_to_grid();
+                }
+                let mut show_animation_hitboxes = app_state
+                    .get_current_document()
+                    .map_or(true, |d| d.view.show_animation_hitboxes);
+                if ui
+                    .menu_item(im_str!("Show Hitboxes In Animations"))
+                    .selected(&mut show_animation_hitboxes)
+                    .enabled(has_document)
+                    .build()
+                {
+                    commands.toggle_animation_hitboxes();
+                }
+                if let Some(document) = app_state.get_current_document() {
+                    ui.menu(im_str!("Snap Grid Size")).build(|| {
+                        for grid_size in [1, 2, 4, 8, 16, 32].iter() {
+                            let mut selected = document.view.snap_grid_size == *grid_size;
+                            if ui
+                                .menu_item(&ImString::new(format!("{}px", grid_size)))
+                                .selected(&mut selected)
+                                .build()
+                            {
+                                commands.set_snap_grid_size(*grid_size);
+                            }
+                        }
+                    });
+                }
+                ui.menu(im_str!("Workbench Background")).build(|| {
+                    let backgrounds = [
+                        (im_str!("Black"), WorkbenchBackground::Black),
+                        (im_str!("Gray"), WorkbenchBackground::Gray),
+                        (im_str!("White"), WorkbenchBackground::White),
+                        (im_str!("Checkerboard"), WorkbenchBackground::Checkerboard),
+                    ];
+                    let preferences = app_state.get_preferences();
+                    for (label, background) in backgrounds.iter() {
+                        let mut selected = preferences.workbench_background == *background;
+                        if ui.menu_item(label).selected(&mut selected).build() {
+                            let mut preferences = preferences.clone();
+                            preferences.workbench_background = *background;
+                            commands.set_preferences(&preferences);
+                        }
+                    }
+                });
                 ui.separator();
                 if ui
                     .menu_item(im_str!("Zoom In (Timeline)"))
@@ -348,6 +642,67 @@ fn draw_main_menu<'a>(
     *size
 }
 
+// The imgui version we bind has no tab bar, tabs are emulated with selectables.
+// IDs come from the document path so a tab keeps being held while it is reordered.
+// Returns whether the tab is being held.
+fn draw_document_tab<'a>(
+    ui: &Ui<'a>,
+    commands: &mut CommandBuffer,
+    document: &Document,
+    is_current: bool,
+) -> bool {
+    let mut document_name = document
+        .source
+        .file_name()
+        .and_then(|f| Some(f.to_string_lossy().into_owned()))
+        .unwrap_or("???".to_owned());
+    if !document.is_saved() {
+        document_name += " *";
+    }
+    let id = document.source.to_string_lossy();
+    let label = ImString::new(format!("{}###tab_{}", document_name, id));
+    let text_size: Vector2D<f32> = ui.calc_text_size(&label, true, -1.0).into();
+    let flags = ImGuiSelectableFlags::empty();
+    if ui.selectable(&label, is_current, flags, ImVec2::new(text_size.x, 0.0)) {
+        commands.focus_document(document);
+    }
+    let is_active = ui.is_item_active();
+    let is_middle_clicked =
+        ui.is_item_hovered() && ui.imgui().is_mouse_clicked(ImMouseButton::Middle);
+    ui.same_line(0.0);
+    let close_label = ImString::new(format!("x###close_{}", id));
+    if ui.small_button(&close_label) || is_middle_clicked {
+        commands.focus_document(document);
+        commands.close_current_document();
+    }
+    is_active
+}
+
+// Swapping with a neighbor once the mouse crosses its middle keeps tabs of different
+// widths from swapping back and forth
+fn update_document_tab_drag<'a>(
+    ui: &Ui<'a>,
+    app_state: &AppState,
+    commands: &mut CommandBuffer,
+    tab_bounds: &[(f32, f32)],
+    dragged_index: usize,
+) {
+    if !ui.imgui().is_mouse_dragging(ImMouseButton::Left) {
+        return;
+    }
+    let document = match app_state.documents_iter().nth(dragged_index) {
+        Some(d) => d,
+        None => return,
+    };
+    let mouse_x = ui.imgui().mouse_pos().0;
+    let middle = |index: usize| (tab_bounds[index].0 + tab_bounds[index].1) / 2.0;
+    if dragged_index > 0 && mouse_x < middle(dragged_index - 1) {
+        commands.reorder_document(document, dragged_index - 1);
+    } else if dragged_index + 1 < tab_bounds.len() && mouse_x > middle(dragged_index + 1) {
+        commands.reorder_document(document, dragged_index + 1);
+    }
+}
+
 fn draw_documents_window<'a>(
     ui: &Ui<'a>,
     rect: &Rect<f32>,
@@ -366,19 +721,20 @@ fn draw_documents_window<'a>(
             .menu_bar(false)
             .movable(false)
             .build(|| {
-                for document in app_state.documents_iter() {
-                    let mut document_name = document
-                        .source
-                        .file_name()
-                        .and_then(|f| Some(f.to_string_lossy().into_owned()))
-                        .unwrap_or("???".to_owned());
-                    if !document.is_saved() {
-                        document_name += " [Modified]";
-                    }
-                    if ui.small_button(&ImString::new(document_name)) {
-                        commands.focus_document(document);
+                let current_document = app_state.get_current_document().map(|d| &d.source);
+                let mut tab_bounds = Vec::new();
+                let mut dragged_index = None;
+                for (index, document) in app_state.documents_iter().enumerate() {
+                    let is_current = current_document == Some(&document.source);
+                    let start_x = ui.get_cursor_screen_pos().0;
+                    if draw_document_tab(ui, commands, document, is_current) {
+                        dragged_index = Some(index);
                     }
                     ui.same_line(0.0);
+                    tab_bounds.push((start_x, ui.get_cursor_screen_pos().0));
+                }
+                if let Some(index) = dragged_index {
+                    update_document_tab_drag(ui, app_state, commands, &tab_bounds, index);
                 }
                 *size = ui.get_window_size();
             });
@@ -412,9 +768,25 @@ fn update_drag_and_drop<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut Co
             if document.transient.workbench_hitbox_being_scaled.is_some() {
                 commands.end_hitbox_scale();
             }
+            if document.transient.workbench_anchor_being_dragged {
+                commands.end_anchor_drag();
+            }
             if document.transient.timeline_scrubbing {
                 commands.end_scrub();
             }
+            if document
+                .transient
+                .timeline_loop_marker_being_dragged
+                .is_some()
+            {
+                commands.end_loop_marker_drag();
+            }
+            if document.transient.workbench_scrubbing {
+                commands.end_workbench_scrub();
+            }
+            if document.transient.workbench_ruler_start.is_some() {
+                commands.end_ruler();
+            }
         }
     }
 }
@@ -445,6 +817,104 @@ fn draw_drag_and_drop<'a>(ui: &Ui<'a>, app_state: &AppState, texture_cache: &Tex
     }
 }
 
+fn draw_pack_strategy<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer, settings: &ExportSettings) {
+    let strategies = [
+        PackStrategy::Skyline,
+        PackStrategy::Shelf,
+        PackStrategy::RowByHeight,
+    ];
+    let labels = [
+        im_str!("Skyline"),
+        im_str!("Shelf"),
+        im_str!("Rows by height"),
+    ];
+    let mut current_strategy = strategies
+        .iter()
+        .position(|s| *s == settings.pack_strategy)
+        .unwrap_or(0) as i32;
+    let height_in_items = labels.len() as i32;
+    ui.push_item_width(120.0); // TODO DPI?
+    if ui.combo(
+        im_str!("Packing"),
+        &mut current_strategy,
+        &labels,
+        height_in_items,
+    ) {
+        commands.set_export_pack_strategy(strategies[current_strategy as usize]);
+    }
+    ui.pop_item_width();
+}
+
+fn draw_template_variables<'a>(
+    ui: &Ui<'a>,
+    commands: &mut CommandBuffer,
+    settings: &ExportSettings,
+) {
+    ui.text(im_str!("Template variables:"));
+    for (index, variable) in settings.template_variables.iter().enumerate() {
+        ui.push_id(100 + index as i32);
+        let mut name = ImString::with_capacity(MAX_TEMPLATE_VARIABLE_NAME_LENGTH);
+        name.push_str(&variable.name);
+        let mut value = ImString::with_capacity(MAX_TEMPLATE_VARIABLE_VALUE_LENGTH);
+        value.push_str(&variable.value);
+        ui.push_item_width(120.0); // TODO DPI?
+        let name_changed = ui.input_text(im_str!("##name"), &mut name).build();
+        ui.same_line(0.0);
+        let value_changed = ui.input_text(im_str!("##value"), &mut value).build();
+        ui.pop_item_width();
+        if name_changed || value_changed {
+            commands.set_export_template_variable(
+                index,
+                TemplateVariable {
+                    name: name.to_str().to_owned(),
+                    value: value.to_str().to_owned(),
+                },
+            );
+        }
+        ui.same_line(0.0);
+        if ui.small_button(im_str!("Remove")) {
+            commands.remove_export_template_variable(index);
+        }
+        ui.pop_id();
+    }
+    if ui.small_button(im_str!("Add Variable")) {
+        commands.add_export_template_variable();
+    }
+}
+
+// Packing reads every frame from disk, so it only runs when requested
+fn draw_pack_estimate<'a>(
+    ui: &Ui<'a>,
+    commands: &mut CommandBuffer,
+    document: &Document,
+    settings: &ExportSettings,
+) {
+    if let Some(estimate) = &document.persistent.pack_estimate {
+        let stats = estimate.stats;
+        let (width, height) = stats.page_size;
+        let size = if stats.num_pages > 1 {
+            format!("{}x{} px ({} pages)", width, height, stats.num_pages)
+        } else {
+            format!("{}x{} px", width, height)
+        };
+        let wasted = if stats.total_area > 0 {
+            100.0 * stats.get_wasted_area() as f64 / stats.total_area as f64
+        } else {
+            0.0
+        };
+        ui.text(&ImString::new(format!("Estimated atlas size: {}", size)));
+        ui.text(&ImString::new(format!("Wasted space: {:.1}%", wasted)));
+        let is_outdated = estimate.version != document.get_version()
+            || estimate.settings != PackSettings::from(settings);
+        if is_outdated {
+            ui.text_colored([1.0, 0.8, 0.2, 1.0], im_str!("Estimate is outdated"));
+        }
+    }
+    if ui.small_button(im_str!("Estimate Atlas Size")) {
+        commands.estimate_packing(document, settings);
+    }
+}
+
 fn draw_export_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut CommandBuffer) {
     if let Some(document) = app_state.get_current_document() {
         if let Some(settings) = &document.persistent.export_settings_edit {
@@ -468,6 +938,80 @@ fn draw_export_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut Comma
                         ui.pop_id();
                     }
 
+                    {
+                        let mut skip_texture = settings.skip_texture;
+                        if ui.checkbox(
+                            im_str!("Metadata only (skip texture atlas)"),
+                            &mut skip_texture,
+                        ) {
+                            commands.set_export_skip_texture(skip_texture);
+                        }
+                    }
+
+                    if !settings.skip_texture {
+                        ui.push_id(4);
+                        let mut is_fixed_size = settings.atlas_size.is_some();
+                        if ui.checkbox(im_str!("Fixed atlas size"), &mut is_fixed_size) {
+                            commands.set_export_atlas_size(if is_fixed_size {
+                                Some((1024, 1024))
+                            } else {
+                                None
+                            });
+                        }
+                        if let Some((width, height)) = settings.atlas_size {
+                            let mut size = [width as i32, height as i32];
+                            if ui.input_int2(im_str!("Atlas size (px)"), &mut size).build() {
+                                commands.set_export_atlas_size(Some((
+                                    std::cmp::max(1, size[0]) as u32,
+                                    std::cmp::max(1, size[1]) as u32,
+                                )));
+                            }
+                        }
+                        // Fixed size atlases use their size for every page
+                        let mut is_paged = settings.max_size.is_some();
+                        if ui.checkbox(im_str!("Split into pages"), &mut is_paged) {
+                            commands.set_export_max_size(if is_paged {
+                                Some((4096, 4096))
+                            } else {
+                                None
+                            });
+                        }
+                        if let (None, Some((width, height))) =
+                            (settings.atlas_size, settings.max_size)
+                        {
+                            let mut size = [width as i32, height as i32];
+                            if ui
+                                .input_int2(im_str!("Max page size (px)"), &mut size)
+                                .build()
+                            {
+                                commands.set_export_max_size(Some((
+                                    std::cmp::max(1, size[0]) as u32,
+                                    std::cmp::max(1, size[1]) as u32,
+                                )));
+                            }
+                        }
+                        let mut sort_frames_by_size = settings.sort_frames_by_size;
+                        if ui.checkbox(
+                            im_str!("Pack largest frames first"),
+                            &mut sort_frames_by_size,
+                        ) {
+                            commands.set_export_sort_frames_by_size(sort_frames_by_size);
+                        }
+                        draw_pack_strategy(ui, commands, settings);
+                        ui.push_item_width(80.0); // TODO DPI?
+                        let mut padding = settings.padding as i32;
+                        if ui.input_int(im_str!("Padding (px)"), &mut padding).build() {
+                            commands.set_export_padding(std::cmp::max(0, padding) as u32);
+                        }
+                        let mut extrude = settings.extrude as i32;
+                        if ui.input_int(im_str!("Extrude (px)"), &mut extrude).build() {
+                            commands.set_export_extrude(std::cmp::max(0, extrude) as u32);
+                        }
+                        ui.pop_item_width();
+                        draw_pack_estimate(ui, commands, document, settings);
+                        ui.pop_id();
+                    }
+
                     {
                         ui.push_id(1);
                         ui.label_text(
@@ -493,11 +1037,24 @@ fn draw_export_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut Comma
                         if ui.small_button(im_str!("Browse…")) {
                             commands.begin_set_export_metadata_paths_root(document);
                         }
+                        let mut absolute_image_paths = settings.absolute_image_paths;
+                        if ui.checkbox(im_str!("Absolute image paths"), &mut absolute_image_paths) {
+                            commands.set_export_absolute_image_paths(absolute_image_paths);
+                        }
                         ui.pop_id();
                     }
 
                     {
                         ui.push_id(3);
+                        let is_json = settings.format == ExportFormat::Json;
+                        if ui.radio_button_bool(im_str!("JSON (built-in)"), is_json) && !is_json {
+                            commands.end_set_export_format(&document.source, ExportFormat::Json);
+                        }
+                        ui.same_line(0.0);
+                        if ui.radio_button_bool(im_str!("Template (.liquid)"), !is_json) && is_json
+                        {
+                            commands.begin_set_export_format(document);
+                        }
                         match &settings.format {
                             ExportFormat::Template(p) => {
                                 ui.label_text(
@@ -509,13 +1066,25 @@ fn draw_export_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut Comma
                                     commands.begin_set_export_format(document);
                                 }
                             }
+                            ExportFormat::Json => (),
                         };
                         ui.pop_id();
                     }
 
-                    // TODO grey out and disable if bad settings
-                    if ui.small_button(im_str!("Ok")) {
-                        commands.end_export_as(&document.sheet);
+                    draw_template_variables(ui, commands, settings);
+
+                    match &document.persistent.export_settings_error {
+                        Some(error) => {
+                            ui.text_colored([1.0, 0.4, 0.4, 1.0], &ImString::new(error.as_str()));
+                            ui.with_style_var(Alpha(0.5), || {
+                                ui.small_button(im_str!("Ok"));
+                            });
+                        }
+                        None => {
+                            if ui.small_button(im_str!("Ok")) {
+                                commands.end_export_as(document);
+                            }
+                        }
                     }
                     ui.same_line(0.0);
                     if ui.small_button(im_str!("Cancel")) {
@@ -531,6 +1100,11 @@ fn draw_rename_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut Comma
     if let Some(document) = app_state.get_current_document() {
         let max_length = match document.transient.item_being_renamed {
             Some(RenameItem::Animation(_)) => MAX_ANIMATION_NAME_LENGTH,
+            Some(RenameItem::AnimationGroup(_)) => MAX_ANIMATION_GROUP_NAME_LENGTH,
+            Some(RenameItem::AnimationDirection(_)) => MAX_ANIMATION_DIRECTION_LENGTH,
+            Some(RenameItem::AnimationTag(_)) => MAX_ANIMATION_TAG_LENGTH,
+            Some(RenameItem::AnimationFrameSound(_, _)) => MAX_SOUND_NAME_LENGTH,
+            Some(RenameItem::AnimationFrameUserData(_, _)) => MAX_USER_DATA_LENGTH,
             Some(RenameItem::Hitbox(_, _)) => MAX_HITBOX_NAME_LENGTH,
             None => return,
         };
@@ -542,6 +1116,9 @@ fn draw_rename_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut Comma
             .resizable(false)
             .always_auto_resize(true)
             .build(|| {
+                if let Some(RenameItem::AnimationGroup(_)) = document.transient.item_being_renamed {
+                    ui.text(im_str!("Separate nested groups with /, eg. enemy/boss"));
+                }
                 let mut s = ImString::with_capacity(max_length);
                 if let Some(current) = &document.transient.rename_buffer {
                     s.push_str(current);
@@ -559,6 +1136,409 @@ fn draw_rename_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut Comma
     }
 }
 
+fn draw_revert_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut CommandBuffer) {
+    if let Some(path) = app_state.get_revert_request() {
+        let popup_id = im_str!("Revert to Saved");
+        ui.popup_modal(&popup_id)
+            .title_bar(true)
+            .resizable(false)
+            .always_auto_resize(true)
+            .build(|| {
+                let document_name = path
+                    .file_name()
+                    .map(|f| f.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "???".to_owned());
+                ui.text(&ImString::new(format!(
+                    "Discard all changes to {} and reload it from disk?",
+                    document_name
+                )));
+                if ui.small_button(im_str!("Revert")) {
+                    commands.revert_document(path);
+                }
+                ui.same_line(0.0);
+                if ui.small_button(im_str!("Cancel")) {
+                    commands.cancel_revert_document();
+                }
+            });
+        ui.open_popup(&popup_id);
+    }
+}
+
+fn draw_invalid_save_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut CommandBuffer) {
+    if let Some(invalid_save) = app_state.get_invalid_save() {
+        let popup_id = im_str!("Invalid Sheet");
+        ui.popup_modal(&popup_id)
+            .title_bar(true)
+            .resizable(false)
+            .always_auto_resize(true)
+            .build(|| {
+                ui.text(&ImString::new(invalid_save.reason.clone()));
+                ui.text(im_str!(
+                    "Saving this sheet may produce a file which cannot be opened again."
+                ));
+                if ui.small_button(im_str!("Save Anyway")) {
+                    commands.save_without_validation(invalid_save);
+                }
+                ui.same_line(0.0);
+                if ui.small_button(im_str!("Cancel")) {
+                    commands.cancel_save_without_validation();
+                }
+            });
+        ui.open_popup(&popup_id);
+    }
+}
+
+fn draw_error_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut CommandBuffer) {
+    if let Some(error) = app_state.get_error() {
+        let popup_id = im_str!("Error");
+        ui.popup_modal(&popup_id)
+            .title_bar(true)
+            .resizable(false)
+            .always_auto_resize(true)
+            .build(|| {
+                ui.text(&ImString::new(error.message.as_str()));
+                if ui.small_button(im_str!("Ok")) {
+                    commands.dismiss_error();
+                }
+            });
+        ui.open_popup(&popup_id);
+    }
+}
+
+fn draw_export_progress_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut CommandBuffer) {
+    if let Some(progress) = app_state.get_export_progress() {
+        let popup_id = im_str!("Exporting");
+        ui.popup_modal(&popup_id)
+            .title_bar(true)
+            .resizable(false)
+            .always_auto_resize(true)
+            .build(|| {
+                let status = match progress.step {
+                    ExportStep::PackingFrames => format!(
+                        "Packing frames ({}/{})…",
+                        progress.frames_packed, progress.num_frames
+                    ),
+                    ExportStep::WritingTexture => "Writing texture…".to_owned(),
+                    ExportStep::WritingMetadata => "Writing metadata…".to_owned(),
+                };
+                ui.text(&ImString::new(status));
+                ui.progress_bar(progress.get_completion())
+                    .size((300.0, 0.0)) // TODO DPI?
+                    .build();
+                if ui.small_button(im_str!("Cancel")) {
+                    commands.cancel_export();
+                }
+            });
+        ui.open_popup(&popup_id);
+    }
+}
+
+fn draw_build_animations_popup<'a>(
+    ui: &Ui<'a>,
+    app_state: &AppState,
+    commands: &mut CommandBuffer,
+) {
+    if let Some(document) = app_state.get_current_document() {
+        if let Some(proposals) = &document.persistent.animation_proposals {
+            let popup_id = im_str!("Build Animations");
+            ui.popup_modal(&popup_id)
+                .title_bar(true)
+                .resizable(false)
+                .always_auto_resize(true)
+                .build(|| {
+                    if proposals.is_empty() {
+                        ui.text(im_str!(
+                            "No sequence of numbered frames was found (eg. walk_0, walk_1…)."
+                        ));
+                    } else {
+                        ui.text(im_str!("The following animations will be created:"));
+                        for proposal in proposals {
+                            ui.bullet_text(&ImString::new(format!(
+                                "{} ({} frames)",
+                                proposal.name,
+                                proposal.frames.len()
+                            )));
+                        }
+                        if ui.small_button(im_str!("Ok")) {
+                            let is_looping = app_state.get_preferences().new_animations_loop;
+                            commands.end_build_animations_from_frame_names(is_looping);
+                        }
+                        ui.same_line(0.0);
+                    }
+                    if ui.small_button(im_str!("Cancel")) {
+                        commands.cancel_build_animations_from_frame_names();
+                    }
+                });
+            ui.open_popup(&popup_id);
+        }
+    }
+}
+
+fn draw_merge_duplicate_animations_popup<'a>(
+    ui: &Ui<'a>,
+    app_state: &AppState,
+    commands: &mut CommandBuffer,
+) {
+    if let Some(document) = app_state.get_current_document() {
+        if let Some(duplicates) = &document.persistent.animation_duplicates {
+            let popup_id = im_str!("Merge Duplicate Animations");
+            ui.popup_modal(&popup_id)
+                .title_bar(true)
+                .resizable(false)
+                .always_auto_resize(true)
+                .build(|| {
+                    if duplicates.is_empty() {
+                        ui.text(im_str!("No duplicate animations were found."));
+                    } else {
+                        ui.text(im_str!("The following animations will be deleted:"));
+                        for duplicate in duplicates {
+                            ui.bullet_text(&ImString::new(format!(
+                                "{} (same as {})",
+                                duplicate.name, duplicate.original
+                            )));
+                        }
+                        if ui.small_button(im_str!("Ok")) {
+                            commands.end_merge_duplicate_animations();
+                        }
+                        ui.same_line(0.0);
+                    }
+                    if ui.small_button(im_str!("Cancel")) {
+                        commands.cancel_merge_duplicate_animations();
+                    }
+                });
+            ui.open_popup(&popup_id);
+        }
+    }
+}
+
+fn draw_grid_import_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut CommandBuffer) {
+    if let Some(document) = app_state.get_current_document() {
+        if let Some(grid_import) = &document.persistent.grid_import {
+            let popup_id = im_str!("Import Grid");
+            ui.popup_modal(&popup_id)
+                .title_bar(true)
+                .resizable(false)
+                .always_auto_resize(true)
+                .build(|| {
+                    let tile_size = grid_import.tile_size;
+                    let mut size = [tile_size.x as i32, tile_size.y as i32];
+                    if ui.input_int2(im_str!("Tile size (px)"), &mut size).build() {
+                        commands.set_grid_import_tile_size(vec2(
+                            std::cmp::max(1, size[0]) as u32,
+                            std::cmp::max(1, size[1]) as u32,
+                        ));
+                    }
+                    let num_frames = (grid_import.image_size.x / tile_size.x.max(1))
+                        * (grid_import.image_size.y / tile_size.y.max(1));
+                    let summary = format!("{} frames will be created.", num_frames);
+                    ui.text(&ImString::new(summary));
+                    if ui.small_button(im_str!("Ok")) {
+                        commands.end_grid_import();
+                    }
+                    ui.same_line(0.0);
+                    if ui.small_button(im_str!("Cancel")) {
+                        commands.cancel_grid_import();
+                    }
+                });
+            ui.open_popup(&popup_id);
+        }
+    }
+}
+
+fn draw_recover_autosave_popup<'a>(
+    ui: &Ui<'a>,
+    app_state: &AppState,
+    commands: &mut CommandBuffer,
+) {
+    if let Some(document) = app_state.get_current_document() {
+        if document.persistent.autosave_recovery.is_some() {
+            let popup_id = im_str!("Recover Autosave");
+            ui.popup_modal(&popup_id)
+                .title_bar(true)
+                .resizable(false)
+                .always_auto_resize(true)
+                .build(|| {
+                    ui.text(im_str!(
+                        "This sheet has unsaved changes from a previous session. Would you like to recover them?"
+                    ));
+                    if ui.small_button(im_str!("Recover")) {
+                        commands.end_recover_autosave();
+                    }
+                    ui.same_line(0.0);
+                    if ui.small_button(im_str!("Discard")) {
+                        commands.delete_autosave(&document.source);
+                        commands.cancel_recover_autosave();
+                    }
+                });
+            ui.open_popup(&popup_id);
+        }
+    }
+}
+
+// Waits for autosave recovery, which can change the frames the sheet uses
+fn draw_relink_frames_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut CommandBuffer) {
+    if let Some(document) = app_state.get_current_document() {
+        if document.persistent.autosave_recovery.is_some() {
+            return;
+        }
+        if let Some(missing_images) = &document.persistent.missing_images {
+            let popup_id = im_str!("Missing Images");
+            ui.popup_modal(&popup_id)
+                .title_bar(true)
+                .resizable(false)
+                .always_auto_resize(true)
+                .build(|| {
+                    ui.text(im_str!("The following images could not be found:"));
+                    for image in missing_images {
+                        ui.bullet_text(&ImString::new(image.to_string_lossy()));
+                    }
+                    ui.text(im_str!(
+                        "Pick a folder to look for images with the same file names."
+                    ));
+                    if ui.small_button(im_str!("Relink…")) {
+                        commands.begin_relink_frames(document);
+                    }
+                    ui.same_line(0.0);
+                    if ui.small_button(im_str!("Ignore")) {
+                        commands.cancel_relink_frames();
+                    }
+                });
+            ui.open_popup(&popup_id);
+        }
+    }
+}
+
+fn draw_export_overwrite_popup<'a>(
+    ui: &Ui<'a>,
+    app_state: &AppState,
+    commands: &mut CommandBuffer,
+) {
+    // Export All can leave several documents waiting for confirmation, they are resolved in turn
+    let pending_document = app_state
+        .documents_iter()
+        .find(|d| d.persistent.export_overwrite_conflicts.is_some());
+    if let Some(document) = pending_document {
+        if let Some(conflicts) = &document.persistent.export_overwrite_conflicts {
+            let popup_id = im_str!("Overwrite Files");
+            ui.popup_modal(&popup_id)
+                .title_bar(true)
+                .resizable(false)
+                .always_auto_resize(true)
+                .build(|| {
+                    let source = document.source.to_string_lossy();
+                    ui.text(&ImString::new(format!(
+                        "Exporting {} will replace the following files, which were not exported from it since it was opened:",
+                        source
+                    )));
+                    for file in conflicts {
+                        ui.bullet_text(&ImString::new(file.to_string_lossy()));
+                    }
+                    if ui.small_button(im_str!("Overwrite")) {
+                        commands.end_export_overwrite(document, false);
+                    }
+                    ui.same_line(0.0);
+                    if ui.small_button(im_str!("Always Overwrite For This Document")) {
+                        commands.end_export_overwrite(document, true);
+                    }
+                    ui.same_line(0.0);
+                    if ui.small_button(im_str!("Cancel")) {
+                        commands.cancel_export_overwrite(document);
+                    }
+                });
+            ui.open_popup(&popup_id);
+        }
+    }
+}
+
+fn draw_export_validation_popup<'a>(
+    ui: &Ui<'a>,
+    app_state: &AppState,
+    commands: &mut CommandBuffer,
+) {
+    if let Some(document) = app_state.get_current_document() {
+        if let Some(validation) = &document.persistent.export_validation {
+            let popup_id = im_str!("Export Validation");
+            ui.popup_modal(&popup_id)
+                .title_bar(true)
+                .resizable(false)
+                .always_auto_resize(true)
+                .build(|| {
+                    if let Some(stats) = validation.stats {
+                        let (width, height) = stats.page_size;
+                        let size = if stats.num_pages > 1 {
+                            format!("{}x{} px ({} pages)", width, height, stats.num_pages)
+                        } else {
+                            format!("{}x{} px", width, height)
+                        };
+                        ui.text(&ImString::new(format!("Atlas size: {}", size)));
+                    }
+                    ui.text(&ImString::new(format!(
+                        "Frames packed: {}",
+                        validation.num_frames_packed
+                    )));
+                    match &validation.error {
+                        Some(error) => {
+                            ui.text_colored([1.0, 0.4, 0.4, 1.0], &ImString::new(error.as_str()))
+                        }
+                        None => ui.text(im_str!("No errors, nothing was written.")),
+                    }
+                    if ui.small_button(im_str!("Ok")) {
+                        commands.dismiss_export_validation();
+                    }
+                });
+            ui.open_popup(&popup_id);
+        }
+    }
+}
+
+fn draw_close_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut CommandBuffer) {
+    if let Some(document) = app_state.get_document_to_close() {
+        if document.is_saved() {
+            return;
+        }
+        let popup_id = im_str!("Close Document");
+        ui.popup_modal(&popup_id)
+            .title_bar(true)
+            .resizable(false)
+            .always_auto_resize(true)
+            .build(|| {
+                let document_name = document
+                    .source
+                    .file_name()
+                    .map(|f| f.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "???".to_owned());
+                ui.text(&ImString::new(format!(
+                    "Would you like to save changes to {} before closing it?",
+                    document_name
+                )));
+                if ui.small_button(im_str!("Save")) {
+                    commands.save(
+                        &document.source,
+                        &document.sheet,
+                        document.get_version(),
+                        app_state.get_preferences().absolute_paths,
+                    );
+                }
+                ui.same_line(0.0);
+                if ui.small_button(im_str!("Don't Save")) {
+                    commands.delete_autosave(&document.source);
+                    commands.close_without_saving();
+                }
+                if app_state.get_num_documents_to_close() > 1 {
+                    ui.same_line(0.0);
+                    if ui.small_button(im_str!("Discard All")) {
+                        commands.discard_all_on_close();
+                    }
+                }
+                ui.same_line(0.0);
+                if ui.small_button(im_str!("Cancel")) {
+                    commands.cancel_close();
+                }
+            });
+        ui.open_popup(&popup_id);
+    }
+}
+
 fn draw_exit_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut CommandBuffer) {
     match app_state.get_exit_state() {
         Some(ExitState::Requested) => {
@@ -611,7 +1591,32 @@ fn draw_exit_popup<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut Command
     }
 }
 
-fn process_shortcuts<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut CommandBuffer) {
+// When Space also pans the workbench, playback toggles when Space is released without
+// having dragged the mouse
+fn process_space_release<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer) {
+    let is_space_down = ui.imgui().is_key_down(VirtualKeyCode::Space as _);
+    let is_dragging = ui.imgui().is_mouse_down(ImMouseButton::Left);
+    SPACE_HOLD.with(|hold| {
+        hold.set(match (hold.get(), is_space_down) {
+            (None, false) => None,
+            (None, true) => Some(is_dragging),
+            (Some(panned), true) => Some(panned || is_dragging),
+            (Some(panned), false) => {
+                if !panned {
+                    commands.toggle_playback();
+                }
+                None
+            }
+        });
+    });
+}
+
+fn process_shortcuts<'a>(
+    ui: &Ui<'a>,
+    app_state: &AppState,
+    commands: &mut CommandBuffer,
+    fit_workbench: &mut bool,
+) {
     if ui.want_capture_keyboard() {
         return;
     }
@@ -624,9 +1629,29 @@ fn process_shortcuts<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut Comma
         if ui.imgui().is_key_pressed(VirtualKeyCode::F2 as _) {
             commands.begin_rename_selection();
         }
-        if ui.imgui().is_key_pressed(VirtualKeyCode::Space as _) {
+        if app_state.get_preferences().space_drag_pan {
+            process_space_release(ui, commands);
+        } else if ui.imgui().is_key_pressed(VirtualKeyCode::Space as _) {
             commands.toggle_playback();
         }
+        if ui.imgui().is_key_pressed(VirtualKeyCode::Comma as _) {
+            commands.step_to_previous_keyframe();
+        }
+        if ui.imgui().is_key_pressed(VirtualKeyCode::Period as _) {
+            commands.step_to_next_keyframe();
+        }
+        if ui.imgui().is_key_pressed(VirtualKeyCode::H as _) {
+            commands.flip_selection_horizontally();
+        }
+        if ui.imgui().is_key_pressed(VirtualKeyCode::V as _) {
+            commands.flip_selection_vertically();
+        }
+        if ui.imgui().is_key_pressed(VirtualKeyCode::F5 as _) {
+            commands.reload_textures();
+        }
+        if ui.imgui().is_key_pressed(VirtualKeyCode::Insert as _) {
+            commands.insert_frames_at_playhead();
+        }
     }
 
     // Arrow shortcuts
@@ -645,11 +1670,21 @@ fn process_shortcuts<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut Comma
             commands.nudge_selection_down(large_nudge);
         }
     } else {
+        // Shift scrubs through the timeline in small steps instead of snapping to frames
+        let fine_scrub = ui.imgui().key_shift();
         if ui.imgui().is_key_pressed(VirtualKeyCode::Left as _) {
-            commands.snap_to_previous_frame();
+            if fine_scrub {
+                commands.step_timeline_clock_backward();
+            } else {
+                commands.snap_to_previous_frame();
+            }
         }
         if ui.imgui().is_key_pressed(VirtualKeyCode::Right as _) {
-            commands.snap_to_next_frame();
+            if fine_scrub {
+                commands.step_timeline_clock_forward();
+            } else {
+                commands.snap_to_next_frame();
+            }
         }
         if ui.imgui().is_key_pressed(VirtualKeyCode::Up as _) {
             commands.select_previous(); // TODO autoscroll somehow?
@@ -668,6 +1703,21 @@ fn process_shortcuts<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut Comma
                 commands.undo();
             }
         }
+        if ui.imgui().is_key_pressed(VirtualKeyCode::C as _) {
+            commands.copy_selection();
+        }
+        if ui.imgui().is_key_pressed(VirtualKeyCode::V as _) {
+            commands.paste();
+        }
+        if ui.imgui().is_key_pressed(VirtualKeyCode::A as _) {
+            commands.select_all();
+        }
+        if ui.imgui().is_key_pressed(VirtualKeyCode::I as _) {
+            commands.invert_selection();
+        }
+        if ui.imgui().is_key_pressed(VirtualKeyCode::D as _) {
+            commands.duplicate_hitbox();
+        }
 
         if ui.imgui().is_key_pressed(VirtualKeyCode::N as _) {
             commands.begin_new_document();
@@ -678,19 +1728,29 @@ fn process_shortcuts<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut Comma
         if ui.imgui().is_key_pressed(VirtualKeyCode::S as _) {
             if ui.imgui().key_shift() {
                 if let Some(document) = app_state.get_current_document() {
-                    commands.save_as(&document.source, &document.sheet, document.get_version());
+                    commands.save_as(
+                        &document.source,
+                        &document.sheet,
+                        document.get_version(),
+                        app_state.get_preferences().absolute_paths,
+                    );
                 }
             } else if ui.imgui().key_alt() {
                 save_all(app_state, commands);
             } else if let Some(document) = app_state.get_current_document() {
-                commands.save(&document.source, &document.sheet, document.get_version());
+                commands.save(
+                    &document.source,
+                    &document.sheet,
+                    document.get_version(),
+                    app_state.get_preferences().absolute_paths,
+                );
             }
         }
         if ui.imgui().is_key_pressed(VirtualKeyCode::E as _) {
             if ui.imgui().key_shift() {
                 commands.begin_export_as();
             } else if let Some(document) = app_state.get_current_document() {
-                commands.export(&document.sheet);
+                commands.export(document);
             }
         }
         if ui.imgui().is_key_pressed(VirtualKeyCode::W as _) {
@@ -728,7 +1788,11 @@ fn process_shortcuts<'a>(ui: &Ui<'a>, app_state: &AppState, commands: &mut Comma
             }
         }
         if ui.imgui().is_key_pressed(VirtualKeyCode::Space as _) {
-            commands.workbench_center();
+            if ui.imgui().key_shift() {
+                *fit_workbench = true;
+            } else {
+                commands.workbench_center();
+            }
         }
     }
 }
@@ -13,8 +13,13 @@ use crate::utils::*;
 fn draw_frame<'a>(ui: &Ui<'a>, texture_cache: &TextureCache, frame: &Frame) {
     if let Some(name) = frame.get_source().file_name() {
         ui.text(&ImString::new(name.to_string_lossy()));
+        let texture = texture_cache.get(frame.get_source());
+        if let Some(TextureCacheResult::Loaded(texture)) = &texture {
+            let (width, height) = (texture.size.x as u32, texture.size.y as u32);
+            ui.text(&ImString::new(format!("Size: {}x{} px", width, height)));
+        }
         let space = ui.get_content_region_avail().into();
-        match texture_cache.get(frame.get_source()) {
+        match texture {
             Some(TextureCacheResult::Loaded(texture)) => {
                 if let Some(fill) = utils::fill(space, texture.size) {
                     let cursor_pos = Vector2D::<f32>::from(ui.get_cursor_pos());
@@ -33,18 +38,44 @@ fn draw_frame<'a>(ui: &Ui<'a>, texture_cache: &TextureCache, frame: &Frame) {
     }
 }
 
-fn draw_hitbox<'a>(ui: &Ui<'a>, hitbox: &Hitbox) {
+fn draw_hitbox<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer, hitbox: &Hitbox) {
     let position = hitbox.get_position();
     let size = hitbox.get_size();
     ui.text(&ImString::new(format!("Tag: {}", hitbox.get_name())));
-    ui.text(&ImString::new(format!(
-        "Offset: {}, {}",
-        position.x, position.y
-    )));
-    ui.text(&ImString::new(format!(
-        "Dimensions: {} x {}",
-        size.x, size.y
-    )));
+    if hitbox.is_circle() {
+        ui.text(im_str!("Shape: Circle"));
+        ui.same_line(0.0);
+        if ui.small_button(im_str!("Make Rectangle")) {
+            commands.toggle_hitbox_shape();
+        }
+    } else {
+        ui.text(im_str!("Shape: Rectangle"));
+        ui.same_line(0.0);
+        if ui.small_button(im_str!("Make Circle")) {
+            commands.toggle_hitbox_shape();
+        }
+    }
+    // Values are only committed on Enter so typing does not flood the undo history
+    ui.push_item_width(120.0); // TODO DPI?
+    let mut offset = [position.x, position.y];
+    if ui
+        .input_int2(im_str!("Offset"), &mut offset)
+        .enter_returns_true(true)
+        .build()
+    {
+        commands.set_selection_position(vec2(offset[0], offset[1]));
+    }
+    let mut dimensions = [size.x as i32, size.y as i32];
+    if ui
+        .input_int2(im_str!("Dimensions"), &mut dimensions)
+        .enter_returns_true(true)
+        .build()
+    {
+        let width = std::cmp::max(0, dimensions[0]) as u32;
+        let height = std::cmp::max(0, dimensions[1]) as u32;
+        commands.set_hitbox_size(vec2(width, height));
+    }
+    ui.pop_item_width();
 
     let space: Vector2D<f32> = ui.get_content_region_avail().into();
     let padding = 0.2;
@@ -53,35 +84,68 @@ fn draw_hitbox<'a>(ui: &Ui<'a>, hitbox: &Hitbox) {
         let cursor_screen_pos: Vector2D<f32> = ui.get_cursor_screen_pos().into();
         let draw_list = ui.get_window_draw_list();
         let color = [1.0, 1.0, 1.0, 1.0]; // TODO.style
-        draw_list
-            .add_rect(
-                (cursor_screen_pos + space * padding / 2.0 + fill.rect.origin.to_vector())
+        if hitbox.is_circle() {
+            draw_list
+                .add_circle(
+                    (cursor_screen_pos + space * padding / 2.0 + fill.rect.center().to_vector())
+                        .to_tuple(),
+                    fill.rect.size.width / 2.0,
+                    color,
+                )
+                .thickness(2.0) // TODO dpi
+                .build();
+        } else {
+            draw_list
+                .add_rect(
+                    (cursor_screen_pos + space * padding / 2.0 + fill.rect.origin.to_vector())
+                        .to_tuple(),
+                    (cursor_screen_pos
+                        + space * padding / 2.0
+                        + fill.rect.bottom_right().to_vector())
                     .to_tuple(),
-                (cursor_screen_pos + space * padding / 2.0 + fill.rect.bottom_right().to_vector())
-                    .to_tuple(),
-                color,
-            )
-            .thickness(2.0) // TODO dpi
-            .build();
+                    color,
+                )
+                .thickness(2.0) // TODO dpi
+                .build();
+        }
+    }
+}
+
+fn draw_animation_tags<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer, animation: &Animation) {
+    ui.text(im_str!("Tags:"));
+    for tag in animation.tags_iter() {
+        ui.same_line(0.0);
+        if ui.small_button(&ImString::new(format!("{} x", tag))) {
+            commands.remove_animation_tag(tag);
+        }
+    }
+    ui.same_line(0.0);
+    if ui.small_button(im_str!("Add…")) {
+        commands.begin_add_animation_tag();
     }
 }
 
 fn draw_animation<'a>(
     ui: &Ui<'a>,
     app_state: &AppState,
+    commands: &mut CommandBuffer,
     texture_cache: &TextureCache,
     animation: &Animation,
 ) {
     ui.text(&ImString::new(animation.get_name().to_owned()));
+    draw_animation_tags(ui, commands, animation);
     let space = ui.get_content_region_avail().into();
     match utils::get_bounding_box(animation, texture_cache) {
         Ok(mut bbox) => {
             bbox.center_on_origin();
             if let Some(fill) = utils::fill(space, bbox.rect.size.to_f32().to_vector()) {
                 let duration = animation.get_duration().unwrap(); // TODO no unwrap
-                let time = Duration::from_millis(
-                    app_state.get_clock().as_millis() as u64 % u64::from(duration),
-                ); // TODO pause on first and last frame for non looping animation?
+                let period = match animation.get_loop_mode() {
+                    LoopMode::PingPong => 2 * u64::from(duration),
+                    _ => u64::from(duration),
+                };
+                let clock = app_state.get_clock().as_millis() as u64;
+                let time = Duration::from_millis(clock % period); // TODO pause on first and last frame for non looping animation?
                 let (_, animation_frame) = animation.get_frame_at(time).unwrap(); // TODO no unwrap
                 match texture_cache.get(animation_frame.get_frame()) {
                     Some(TextureCacheResult::Loaded(texture)) => {
@@ -113,18 +177,81 @@ fn draw_animation<'a>(
     }
 }
 
+// Durations can also be entered as a frame rate, which is converted to milliseconds
+fn draw_animation_frame_duration<'a>(
+    ui: &Ui<'a>,
+    commands: &mut CommandBuffer,
+    animation_frame: &AnimationFrame,
+) {
+    let duration = std::cmp::max(1, animation_frame.get_duration());
+    ui.push_item_width(120.0); // TODO DPI?
+    let mut duration_ms = duration as i32;
+    if ui
+        .input_int(im_str!("Duration (ms)"), &mut duration_ms)
+        .enter_returns_true(true)
+        .build()
+    {
+        commands.set_animation_frame_duration(std::cmp::max(1, duration_ms) as u32);
+    }
+    let mut frame_rate = (1000.0 / duration as f32).round() as i32;
+    if ui
+        .input_int(im_str!("Duration (FPS)"), &mut frame_rate)
+        .enter_returns_true(true)
+        .build()
+    {
+        let frame_rate = std::cmp::max(1, frame_rate) as f32;
+        commands.set_animation_frame_duration((1000.0 / frame_rate).round() as u32);
+    }
+    ui.pop_item_width();
+}
+
 fn draw_animation_frame<'a>(
     ui: &Ui<'a>,
+    commands: &mut CommandBuffer,
     texture_cache: &TextureCache,
+    animation: &Animation,
     animation_frame: &AnimationFrame,
 ) {
     let frame = animation_frame.get_frame();
     if let Some(name) = frame.file_name() {
         ui.text(&ImString::new(name.to_string_lossy()));
+        if animation.get_frame_rate().is_some() {
+            ui.text(&ImString::new(format!(
+                "Duration: {}ms (fixed frame rate)",
+                animation.get_frame_duration(animation_frame)
+            )));
+        } else {
+            draw_animation_frame_duration(ui, commands, animation_frame);
+        }
+        ui.text(&ImString::new(format!(
+            "Sound: {}",
+            animation_frame.get_sound().unwrap_or("None")
+        )));
+        ui.same_line(0.0);
+        if ui.small_button(im_str!("Edit…")) {
+            commands.begin_set_animation_frame_sound();
+        }
         ui.text(&ImString::new(format!(
-            "Duration: {}ms",
-            animation_frame.get_duration()
+            "User Data: {}",
+            animation_frame.get_user_data().unwrap_or("None")
         )));
+        ui.same_line(0.0);
+        ui.push_id(1);
+        if ui.small_button(im_str!("Edit…")) {
+            commands.begin_set_animation_frame_user_data();
+        }
+        ui.pop_id();
+        let offset = animation_frame.get_offset();
+        let mut offset = [offset.x, offset.y];
+        ui.push_item_width(120.0); // TODO DPI?
+        if ui
+            .input_int2(im_str!("Offset"), &mut offset)
+            .enter_returns_true(true)
+            .build()
+        {
+            commands.set_selection_position(vec2(offset[0], offset[1]));
+        }
+        ui.pop_item_width();
         let space = ui.get_content_region_avail().into();
         match texture_cache.get(frame) {
             Some(TextureCacheResult::Loaded(texture)) => {
@@ -145,7 +272,13 @@ fn draw_animation_frame<'a>(
     }
 }
 
-pub fn draw<'a>(ui: &Ui<'a>, rect: &Rect<f32>, app_state: &AppState, texture_cache: &TextureCache) {
+pub fn draw<'a>(
+    ui: &Ui<'a>,
+    rect: &Rect<f32>,
+    app_state: &AppState,
+    commands: &mut CommandBuffer,
+    texture_cache: &TextureCache,
+) {
     ui.with_style_vars(&[WindowRounding(0.0), WindowBorderSize(0.0)], || {
         ui.window(im_str!("Selection"))
             .position(rect.origin.to_tuple(), ImGuiCond::Always)
@@ -156,27 +289,34 @@ pub fn draw<'a>(ui: &Ui<'a>, rect: &Rect<f32>, app_state: &AppState, texture_cac
             .build(|| {
                 if let Some(document) = app_state.get_current_document() {
                     match &document.view.selection {
-                        Some(Selection::Frame(path)) => {
-                            if let Some(frame) = document.sheet.get_frame(path) {
+                        Some(Selection::Frame(paths)) => {
+                            if let Some(frame) = document.sheet.get_frame(&paths.last_touched) {
                                 draw_frame(ui, texture_cache, frame);
                             }
                         }
                         Some(Selection::Animation(name)) => {
                             if let Some(animation) = document.sheet.get_animation(name) {
-                                draw_animation(ui, app_state, texture_cache, animation);
+                                draw_animation(ui, app_state, commands, texture_cache, animation);
                             }
                         }
-                        Some(Selection::AnimationFrame(name, index)) => {
+                        Some(Selection::AnimationFrame(name, indices)) => {
                             if let Some(animation) = document.sheet.get_animation(name) {
-                                if let Some(animation_frame) = animation.get_frame(*index) {
-                                    draw_animation_frame(ui, texture_cache, animation_frame);
+                                let index = indices.last_touched;
+                                if let Some(animation_frame) = animation.get_frame(index) {
+                                    draw_animation_frame(
+                                        ui,
+                                        commands,
+                                        texture_cache,
+                                        animation,
+                                        animation_frame,
+                                    );
                                 }
                             }
                         }
-                        Some(Selection::Hitbox(path, name)) => {
+                        Some(Selection::Hitbox(path, names)) => {
                             if let Some(frame) = document.sheet.get_frame(path) {
-                                if let Some(hitbox) = frame.get_hitbox(name) {
-                                    draw_hitbox(ui, hitbox);
+                                if let Some(hitbox) = frame.get_hitbox(&names.last_touched) {
+                                    draw_hitbox(ui, commands, hitbox);
                                 }
                             }
                         }
@@ -2,15 +2,212 @@ use imgui::StyleVar::*;
 use imgui::*;
 use std::time::Duration;
 
-use crate::sheet::{Animation, AnimationFrame};
+use crate::sheet::{Animation, AnimationFrame, LoopMode};
 use crate::state::*;
 use crate::ui::Rect;
 
-fn draw_timeline_ticks<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer, document: &Document) {
+fn draw_playback_speed<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer, document: &Document) {
+    let speeds = [0.25, 0.5, 1.0, 2.0, 4.0];
+    let labels = [
+        im_str!("0.25x"),
+        im_str!("0.5x"),
+        im_str!("1x"),
+        im_str!("2x"),
+        im_str!("4x"),
+    ];
+    let mut current_speed = speeds
+        .iter()
+        .position(|s| *s == document.persistent.playback_speed)
+        .unwrap_or(2) as i32;
+    let height_in_items = labels.len() as i32;
+    ui.push_item_width(80.0); // TODO DPI?
+    if ui.combo(
+        im_str!("Speed"),
+        &mut current_speed,
+        &labels,
+        height_in_items,
+    ) {
+        commands.set_playback_speed(speeds[current_speed as usize]);
+    }
+    ui.pop_item_width();
+}
+
+fn draw_loop_mode<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer, animation: &Animation) {
+    let loop_modes = [LoopMode::Once, LoopMode::Loop, LoopMode::PingPong];
+    let labels = [im_str!("Once"), im_str!("Loop"), im_str!("Ping-Pong")];
+    let mut current_loop_mode = loop_modes
+        .iter()
+        .position(|m| *m == animation.get_loop_mode())
+        .unwrap_or(0) as i32;
+    let height_in_items = labels.len() as i32;
+    ui.push_item_width(100.0); // TODO DPI?
+    if ui.combo(
+        im_str!("Loop Mode"),
+        &mut current_loop_mode,
+        &labels,
+        height_in_items,
+    ) {
+        commands.set_loop_mode(loop_modes[current_loop_mode as usize]);
+    }
+    ui.pop_item_width();
+}
+
+// Starts out with the duration of the keyframe under the playhead
+fn draw_set_all_durations<'a>(
+    ui: &Ui<'a>,
+    commands: &mut CommandBuffer,
+    document: &Document,
+    animation: &Animation,
+) {
+    let mut duration = animation
+        .get_frame_at(document.view.timeline_clock)
+        .map(|(_, f)| f.get_duration() as i32)
+        .unwrap_or(100);
+    ui.push_item_width(80.0); // TODO DPI?
+    if ui
+        .input_int(im_str!("Set all to… ms"), &mut duration)
+        .enter_returns_true(true)
+        .build()
+    {
+        commands.set_all_keyframe_durations(std::cmp::max(1, duration) as u32);
+    }
+    ui.pop_item_width();
+}
+
+fn draw_timing_readout<'a>(ui: &Ui<'a>, document: &Document, animation: &Animation) {
+    let duration = animation.get_duration().unwrap_or(0);
+    let now_ms = std::cmp::min(
+        document.view.timeline_clock.as_millis(),
+        u128::from(duration),
+    );
+    let frame_index = animation
+        .get_frame_at(document.view.timeline_clock)
+        .map(|(i, _)| i + 1)
+        .unwrap_or(0);
+    ui.text(&ImString::new(format!(
+        "{}ms / {}ms - Frame {} / {}",
+        now_ms,
+        duration,
+        frame_index,
+        animation.get_num_frames()
+    )));
+}
+
+const LOOP_MARKER_HANDLE_SIZE: f32 = 8.0; // TODO DPI?
+
+fn get_loop_marker_times(document: &Document, animation: &Animation) -> (u64, u64) {
+    let duration = u64::from(animation.get_duration().unwrap_or(0));
+    document.get_loop_region().unwrap_or((0, duration))
+}
+
+// Handles are submitted before the ticks so they take precedence over scrubbing
+fn draw_loop_marker_handles<'a>(
+    ui: &Ui<'a>,
+    commands: &mut CommandBuffer,
+    document: &Document,
+    animation: &Animation,
+) {
+    if animation.get_duration().unwrap_or(0) == 0 {
+        return;
+    }
+    let zoom = document.view.get_timeline_zoom_factor();
+    let cursor_start = ui.get_cursor_screen_pos();
+    let is_mouse_dragging = ui.imgui().is_mouse_dragging(ImMouseButton::Left);
+    let marker_being_dragged = document.transient.timeline_loop_marker_being_dragged;
+    let (start, end) = get_loop_marker_times(document, animation);
+
+    let draw_list = ui.get_window_draw_list();
+    let color = if document.get_loop_region().is_some() {
+        [0.3, 0.8, 0.4] // TODO.style
+    } else {
+        [0.5, 0.5, 0.5] // TODO.style
+    };
+
+    for (marker, time) in &[(LoopMarker::In, start), (LoopMarker::Out, end)] {
+        let x = cursor_start.0 + *time as f32 * zoom - LOOP_MARKER_HANDLE_SIZE / 2.0;
+        let top_left = (x, cursor_start.1);
+        let bottom_right = (
+            x + LOOP_MARKER_HANDLE_SIZE,
+            cursor_start.1 + LOOP_MARKER_HANDLE_SIZE,
+        );
+        draw_list.add_rect_filled_multicolor(top_left, bottom_right, color, color, color, color);
+
+        let id = match marker {
+            LoopMarker::In => im_str!("loop_in_marker"),
+            LoopMarker::Out => im_str!("loop_out_marker"),
+        };
+        ui.set_cursor_screen_pos(top_left);
+        ui.invisible_button(id, (LOOP_MARKER_HANDLE_SIZE, LOOP_MARKER_HANDLE_SIZE));
+        if ui.is_item_hovered() || marker_being_dragged == Some(*marker) {
+            ui.imgui().set_mouse_cursor(ImGuiMouseCursor::ResizeEW);
+        }
+        if ui.is_item_active() && is_mouse_dragging && marker_being_dragged.is_none() {
+            commands.begin_loop_marker_drag(*marker);
+        }
+    }
+
+    if marker_being_dragged.is_some() && is_mouse_dragging {
+        let new_t = (ui.imgui().mouse_pos().0 - cursor_start.0) / zoom;
+        let new_t = std::cmp::max(0, new_t as i64) as u64;
+        commands.update_loop_marker_drag(Duration::from_millis(new_t));
+    }
+
+    ui.set_cursor_screen_pos(cursor_start);
+}
+
+// Dims the parts of the timeline which are not played back
+fn draw_loop_region<'a>(ui: &Ui<'a>, document: &Document, animation: &Animation) {
+    let (start, end) = match document.get_loop_region() {
+        Some(r) => r,
+        None => return,
+    };
+    let duration = u64::from(animation.get_duration().unwrap_or(0));
+    let zoom = document.view.get_timeline_zoom_factor();
+    let draw_list = ui.get_window_draw_list();
+    let cursor_pos = ui.get_cursor_screen_pos();
+    let space = ui.get_content_region_avail();
+    let bottom = cursor_pos.1 + space.1;
+    let dim_color = [0.0, 0.0, 0.0, 0.5]; // TODO.style
+    let marker_color = [0.3, 0.8, 0.4]; // TODO.style
+
+    for (from, to) in &[(0, start), (end, duration)] {
+        if to > from {
+            draw_list.add_rect_filled_multicolor(
+                (cursor_pos.0 + *from as f32 * zoom, cursor_pos.1),
+                (cursor_pos.0 + *to as f32 * zoom, bottom),
+                dim_color,
+                dim_color,
+                dim_color,
+                dim_color,
+            );
+        }
+    }
+
+    for time in &[start, end] {
+        let x = cursor_pos.0 + *time as f32 * zoom;
+        draw_list.add_rect_filled_multicolor(
+            (x, cursor_pos.1),
+            (x + 1.0, bottom),
+            marker_color,
+            marker_color,
+            marker_color,
+            marker_color,
+        );
+    }
+}
+
+fn draw_timeline_ticks<'a>(
+    ui: &Ui<'a>,
+    commands: &mut CommandBuffer,
+    document: &Document,
+    animation: &Animation,
+) {
     let zoom = document.view.get_timeline_zoom_factor();
     let h = 8.0; // TODO DPI?
     let padding = 4.0; // TODO DPI?
 
+    draw_loop_marker_handles(ui, commands, document, animation);
+
     let draw_list = ui.get_window_draw_list();
     let cursor_start = ui.get_cursor_screen_pos();
     let max_draw_x = cursor_start.0 + ui.get_content_region_avail().0
@@ -84,10 +281,10 @@ struct FrameLocation {
 fn get_frame_location(
     document: &Document,
     frame_starts_at: Duration,
-    animation_frame: &AnimationFrame,
+    duration: u32,
 ) -> FrameLocation {
     let zoom = document.view.get_timeline_zoom_factor();
-    let w = (animation_frame.get_duration() as f32 * zoom).ceil();
+    let w = (duration as f32 * zoom).ceil();
     let h = 20.0; // TODO DPI?
     let top_left = ((frame_starts_at.as_millis() as f32 * zoom).floor(), 0.0);
     FrameLocation {
@@ -105,7 +302,11 @@ fn draw_animation_frame<'a>(
     animation_frame: &AnimationFrame,
     frame_starts_at: Duration,
 ) {
-    let animation_frame_location = get_frame_location(document, frame_starts_at, animation_frame);
+    let animation_frame_location = get_frame_location(
+        document,
+        frame_starts_at,
+        animation.get_frame_duration(animation_frame),
+    );
     let zoom = document.view.get_timeline_zoom_factor();
     let outline_size = 1.0; // TODO DPI?
     let text_padding = 4.0; // TODO DPI?
@@ -119,7 +320,7 @@ fn draw_animation_frame<'a>(
     let resize_handle_size_right = match animation.get_frame(animation_frame_index + 1) {
         None => resize_handle_size_left,
         Some(n) => {
-            let nw = (n.get_duration() as f32 * zoom).ceil();
+            let nw = (animation.get_frame_duration(n) as f32 * zoom).ceil();
             (nw / 3.0).floor().min(max_resize_handle_size)
         }
     };
@@ -127,11 +328,12 @@ fn draw_animation_frame<'a>(
         .min(resize_handle_size_right)
         .max(1.0);
 
-    let is_selected = &document.view.selection
-        == &Some(Selection::AnimationFrame(
-            animation.get_name().to_string(),
-            animation_frame_index,
-        ));
+    let is_selected = match &document.view.selection {
+        Some(Selection::AnimationFrame(a, af)) => {
+            a == animation.get_name() && af.contains(&animation_frame_index)
+        }
+        _ => false,
+    };
 
     let draw_list = ui.get_window_draw_list();
     let mut cursor_pos = ui.get_cursor_screen_pos();
@@ -181,6 +383,22 @@ fn draw_animation_frame<'a>(
             });
         }
 
+        // Draw sound marker
+        if animation_frame.get_sound().is_some() {
+            let marker_size = 3.0; // TODO DPI?
+            let marker_color = [25.0 / 255.0, 15.0 / 255.0, 0.0 / 255.0, 1.0]; // TODO.style
+            let marker_position = (
+                fill_bottom_right.0 - text_padding - marker_size,
+                fill_bottom_right.1 - text_padding - marker_size,
+            );
+            if marker_position.0 - marker_size > fill_top_left.0 {
+                draw_list
+                    .add_circle(marker_position, marker_size, marker_color)
+                    .filled(true)
+                    .build();
+            }
+        }
+
         // Click interactions
         {
             let id = format!("frame_button_{}", top_left.0);
@@ -221,8 +439,8 @@ fn draw_animation_frame<'a>(
         }
     }
 
-    // Drag to resize interaction
-    if !is_too_small {
+    // Drag to resize interaction, keyframe durations are unused at a fixed frame rate
+    if !is_too_small && animation.get_frame_rate().is_none() {
         assert!(resize_handle_size >= 1.0);
         let id = format!("frame_handle_{}", top_left.0);
         ui.set_cursor_screen_pos((bottom_right.0 - resize_handle_size, top_left.1));
@@ -292,12 +510,13 @@ fn get_frame_under_mouse<'a>(
     let mouse_pos = ui.imgui().mouse_pos();
     let mut cursor = Duration::new(0, 0);
     for (frame_index, animation_frame) in animation.frames_iter().enumerate() {
-        let frame_location = get_frame_location(document, cursor, animation_frame);
+        let duration = animation.get_frame_duration(animation_frame);
+        let frame_location = get_frame_location(document, cursor, duration);
         let frame_start_x = start_screen_position.0 + frame_location.top_left.0;
         if mouse_pos.0 >= frame_start_x && mouse_pos.0 < (frame_start_x + frame_location.size.0) {
             return Some((frame_index, frame_location));
         }
-        cursor += Duration::from_millis(u64::from(animation_frame.get_duration()));
+        cursor += Duration::from_millis(u64::from(duration));
     }
     None
 }
@@ -393,19 +612,74 @@ pub fn draw<'a>(ui: &Ui<'a>, rect: &Rect<f32>, app_state: &AppState, commands: &
                         &document.view.workbench_item
                     {
                         if let Some(animation) = document.sheet.get_animation(animation_name) {
+                            if ui.small_button(im_str!("<")) {
+                                commands.step_to_previous_keyframe();
+                            }
+                            ui.same_line(0.0);
                             if ui.small_button(im_str!("Play/Pause")) {
                                 commands.toggle_playback();
                             }
                             ui.same_line(0.0);
-                            let mut looping = animation.is_looping();
-                            if ui.checkbox(im_str!("Loop"), &mut looping) {
-                                commands.toggle_looping();
+                            if ui.small_button(im_str!(">")) {
+                                commands.step_to_next_keyframe();
+                            }
+                            ui.same_line(0.0);
+                            let mut reverse = document.persistent.playback_direction
+                                == PlaybackDirection::Backward;
+                            if ui.checkbox(im_str!("Reverse"), &mut reverse) {
+                                commands.toggle_playback_direction();
+                            }
+                            ui.same_line(0.0);
+                            draw_playback_speed(ui, commands, document);
+                            ui.same_line(0.0);
+                            draw_loop_mode(ui, commands, animation);
+                            ui.same_line(0.0);
+                            draw_timing_readout(ui, document, animation);
+                            ui.same_line(0.0);
+                            let mut fixed_frame_rate = animation.get_frame_rate().is_some();
+                            if ui.checkbox(im_str!("Fixed FPS"), &mut fixed_frame_rate) {
+                                commands.set_animation_frame_rate(if fixed_frame_rate {
+                                    Some(12.0)
+                                } else {
+                                    None
+                                });
+                            }
+                            if let Some(frame_rate) = animation.get_frame_rate() {
+                                ui.same_line(0.0);
+                                let mut frame_rate = frame_rate;
+                                ui.push_item_width(80.0);
+                                if ui
+                                    .input_float(im_str!("FPS"), &mut frame_rate)
+                                    .enter_returns_true(true)
+                                    .build()
+                                {
+                                    commands.set_animation_frame_rate(Some(frame_rate));
+                                }
+                                ui.pop_item_width();
+                            } else {
+                                ui.same_line(0.0);
+                                draw_set_all_durations(ui, commands, document, animation);
+                            }
+
+                            ui.same_line(0.0);
+                            if ui.small_button(im_str!("Set In")) {
+                                commands.set_loop_in();
+                            }
+                            ui.same_line(0.0);
+                            if ui.small_button(im_str!("Set Out")) {
+                                commands.set_loop_out();
+                            }
+                            if document.get_loop_region().is_some() {
+                                ui.same_line(0.0);
+                                if ui.small_button(im_str!("Clear Loop")) {
+                                    commands.clear_loop_region();
+                                }
                             }
 
                             // TODO autoscroll during playback
 
                             let ticks_cursor_position = ui.get_cursor_pos();
-                            draw_timeline_ticks(ui, commands, document);
+                            draw_timeline_ticks(ui, commands, document, animation);
 
                             let frames_cursor_position_start = ui.get_cursor_screen_pos();
                             let mut frames_cursor_position_end = frames_cursor_position_start;
@@ -425,12 +699,13 @@ pub fn draw<'a>(ui: &Ui<'a>, rect: &Rect<f32>, app_state: &AppState, commands: &
                                 );
                                 frames_cursor_position_end = ui.get_cursor_screen_pos();
                                 cursor += Duration::from_millis(u64::from(
-                                    animation_frame.get_duration(),
+                                    animation.get_frame_duration(animation_frame),
                                 ));
                             }
 
                             ui.set_cursor_pos(ticks_cursor_position);
                             draw_playback_head(ui, document, animation);
+                            draw_loop_region(ui, document, animation);
 
                             handle_drag_and_drop(
                                 ui,
@@ -1,6 +1,9 @@
 use euclid::*;
+use glutin::VirtualKeyCode;
 use imgui::StyleVar::*;
 use imgui::*;
+use std::path::Path;
+use std::time::Duration;
 
 use crate::sheet::{Animation, AnimationFrame, Frame, Hitbox};
 use crate::state::*;
@@ -19,6 +22,41 @@ fn screen_to_workbench<'a>(
     (screen_coords - offset - window_position - window_size / 2.0) / zoom
 }
 
+fn workbench_to_screen<'a>(
+    ui: &Ui<'a>,
+    workbench_coords: Vector2D<f32>,
+    document: &Document,
+) -> Vector2D<f32> {
+    let window_position: Vector2D<f32> = ui.get_window_pos().into();
+    let window_size: Vector2D<f32> = ui.get_window_size().into();
+    let zoom = document.view.get_workbench_zoom_factor();
+    let offset = document.view.workbench_offset;
+    workbench_coords * zoom + offset + window_position + window_size / 2.0
+}
+
+// Holding M turns left drags into measurements instead of editing the workbench item
+fn is_measuring<'a>(ui: &Ui<'a>, document: &Document) -> bool {
+    document.transient.workbench_ruler_start.is_some()
+        || ui.imgui().is_key_down(VirtualKeyCode::M as _)
+}
+
+// Middle mouse always pans, other gestures depend on user preferences
+fn get_pan_button<'a>(ui: &Ui<'a>, preferences: &Preferences) -> Option<ImMouseButton> {
+    let is_space_down = ui.imgui().is_key_down(VirtualKeyCode::Space as _);
+    if ui.imgui().is_mouse_down(ImMouseButton::Middle) {
+        Some(ImMouseButton::Middle)
+    } else if preferences.right_drag_pan && ui.imgui().is_mouse_down(ImMouseButton::Right) {
+        Some(ImMouseButton::Right)
+    } else if preferences.space_drag_pan
+        && is_space_down
+        && ui.imgui().is_mouse_down(ImMouseButton::Left)
+    {
+        Some(ImMouseButton::Left)
+    } else {
+        None
+    }
+}
+
 fn axis_to_cursor(axis: ResizeAxis) -> ImGuiMouseCursor {
     match axis {
         ResizeAxis::N => ImGuiMouseCursor::ResizeNS,
@@ -32,6 +70,16 @@ fn axis_to_cursor(axis: ResizeAxis) -> ImGuiMouseCursor {
     }
 }
 
+fn get_hitbox_color(hitbox: &Hitbox) -> [f32; 4] {
+    let (r, g, b) = hitbox.get_color();
+    [
+        f32::from(r) / 255.0,
+        f32::from(g) / 255.0,
+        f32::from(b) / 255.0,
+        1.0,
+    ]
+}
+
 fn draw_hitbox_resize_controls<'a>(
     ui: &Ui<'a>,
     commands: &mut CommandBuffer,
@@ -53,6 +101,7 @@ fn draw_hitbox_resize_controls<'a>(
 
     let draw_list = ui.get_window_draw_list();
     let knob_size = 4.0; // TODO dpi
+    let knob_color = get_hitbox_color(hitbox);
     let button_size = 16.0; // TODO dpi
 
     for dx in -1..=1 {
@@ -61,6 +110,11 @@ fn draw_hitbox_resize_controls<'a>(
                 continue;
             }
 
+            // Circles are resized from the handles lying on their outline
+            if hitbox.is_circle() && dx != 0 && dy != 0 {
+                continue;
+            }
+
             if dx == 0 && rectangle.size.width < button_size * 2.0 {
                 continue;
             }
@@ -94,7 +148,7 @@ fn draw_hitbox_resize_controls<'a>(
                 .build();
 
             draw_list
-                .add_circle(screen_position, knob_size - 1.0, [0.0, 0.4, 0.9, 1.0]) // TODO dpi TODO.style
+                .add_circle(screen_position, knob_size - 1.0, knob_color) // TODO dpi
                 .filled(true)
                 .build();
 
@@ -133,6 +187,7 @@ fn draw_hitbox<'a>(
     let is_mouse_dragging = ui.imgui().is_mouse_dragging(ImMouseButton::Left);
     let drag_delta: Vector2D<f32> = ui.imgui().mouse_drag_delta(ImMouseButton::Left).into();
     let is_shift_down = ui.imgui().key_shift();
+    let is_alt_down = ui.imgui().key_alt(); // Temporarily disables grid snapping
 
     let cursor_pos = workbench_offset
         + (space / 2.0).floor()
@@ -142,11 +197,12 @@ fn draw_hitbox<'a>(
     let top_left: Vector2D<f32> = ui.get_cursor_screen_pos().into();
     let bottom_right = top_left + rectangle.size.to_f32().to_vector() * zoom;
 
-    let is_selected = document.view.selection
-        == Some(Selection::Hitbox(
-            frame.get_source().to_owned(),
-            hitbox.get_name().to_owned(),
-        ));
+    let is_selected = match &document.view.selection {
+        Some(Selection::Hitbox(p, n)) => {
+            p == frame.get_source() && n.contains(&hitbox.get_name().to_owned())
+        }
+        _ => false,
+    };
 
     let (is_hovered, is_active) = if is_selectable && !rectangle.size.is_empty_or_negative() {
         let hitbox_id = ImString::new(format!("hitbox_button_{}", hitbox.get_name()));
@@ -154,7 +210,11 @@ fn draw_hitbox<'a>(
             &hitbox_id,
             (rectangle.size.to_f32().to_vector() * zoom).to_tuple(),
         ) {
-            commands.select_hitbox(hitbox);
+            if ui.imgui().key_ctrl() {
+                commands.toggle_hitbox_selection(hitbox);
+            } else {
+                commands.select_hitbox(hitbox);
+            }
         }
         ui.set_item_allow_overlap();
         (ui.is_item_hovered(), ui.is_item_active())
@@ -167,15 +227,27 @@ fn draw_hitbox<'a>(
     } else if is_hovered {
         [0.0, 0.9, 0.9, 1.0] // TODO.style
     } else {
-        [1.0, 1.0, 1.0, 1.0] // TODO.style
+        get_hitbox_color(hitbox)
     };
 
     {
         let draw_list = ui.get_window_draw_list();
-        draw_list
-            .add_rect(top_left.to_tuple(), bottom_right.to_tuple(), outline_color)
-            .thickness(1.0) // TODO dpi
-            .build();
+        if hitbox.is_circle() {
+            let radius = (bottom_right.x - top_left.x) / 2.0;
+            draw_list
+                .add_circle(
+                    ((top_left + bottom_right) / 2.0).to_tuple(),
+                    radius,
+                    outline_color,
+                )
+                .thickness(1.0) // TODO dpi
+                .build();
+        } else {
+            draw_list
+                .add_rect(top_left.to_tuple(), bottom_right.to_tuple(), outline_color)
+                .thickness(1.0) // TODO dpi
+                .build();
+        }
     }
 
     if is_hovered && !*is_scaling && !*is_dragging {
@@ -187,7 +259,7 @@ fn draw_hitbox<'a>(
             Some(n) if n == hitbox.get_name() => {
                 ui.imgui().set_mouse_cursor(ImGuiMouseCursor::ResizeAll);
                 if is_mouse_dragging { // TODO this check is a workaround https://github.com/ocornut/imgui/issues/2419
-                    commands.update_hitbox_drag(drag_delta, !is_shift_down);
+                    commands.update_hitbox_drag(drag_delta, !is_shift_down, !is_alt_down);
                 }
             }
             _ => (),
@@ -200,7 +272,7 @@ fn draw_hitbox<'a>(
                 let axis = document.transient.workbench_hitbox_scale_axis;
                 ui.imgui().set_mouse_cursor(axis_to_cursor(axis));
                 if is_mouse_dragging { // TODO this check is a workaround https://github.com/ocornut/imgui/issues/2419
-                    commands.update_hitbox_scale(drag_delta, is_shift_down);
+                    commands.update_hitbox_scale(drag_delta, is_shift_down, !is_alt_down);
                 }
             }
             _ => (),
@@ -246,6 +318,8 @@ fn draw_frame<'a>(
             let mouse_pos = ui.imgui().mouse_pos().into();
             let mouse_position_in_workbench = screen_to_workbench(ui, mouse_pos, document);
 
+            let is_measuring = is_measuring(ui, document);
+
             for hitbox in frame.hitboxes_iter() {
                 draw_hitbox(
                     ui,
@@ -253,15 +327,19 @@ fn draw_frame<'a>(
                     document,
                     frame,
                     hitbox,
-                    true,
+                    !is_measuring,
                     vec2(0, 0),
                     &mut is_scaling_hitbox,
                     &mut is_dragging_hitbox,
                 );
             }
 
+            let is_dragging_anchor = draw_anchor(ui, commands, document, frame);
+
             if !is_scaling_hitbox
                 && !is_dragging_hitbox
+                && !is_dragging_anchor
+                && !is_measuring
                 && ui.is_window_hovered()
                 && is_mouse_dragging
             {
@@ -273,12 +351,53 @@ fn draw_frame<'a>(
             ui.set_cursor_pos(offset.to_tuple());
             draw_spinner(ui, &ui.get_window_draw_list(), space);
         }
-        _ => {
-            // TODO
+        Some(TextureCacheResult::Missing) => {
+            draw_missing_image(ui, frame.get_source(), offset + (space / 2.0).floor());
         }
+        None => (),
     }
 }
 
+fn draw_missing_image<'a>(ui: &Ui<'a>, path: &Path, center: Vector2D<f32>) {
+    let color = [1.0, 0.4, 0.4, 1.0]; // TODO.style
+    let file_name = path
+        .file_name()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.to_string_lossy().into_owned());
+    let text = ImString::new(format!("Missing image: {}", file_name));
+    let text_size: Vector2D<f32> = ui.calc_text_size(&text, false, -1.0).into();
+    ui.set_cursor_pos((center - (text_size / 2.0).floor()).to_tuple());
+    ui.text_colored(color, &text);
+}
+
+// Position of the frame center, so that the frame anchor lands on the keyframe offset
+fn get_frame_offset(
+    document: &Document,
+    animation_frame: &AnimationFrame,
+    frame_size: Vector2D<u32>,
+) -> Vector2D<i32> {
+    let anchor = document
+        .sheet
+        .get_frame(animation_frame.get_frame())
+        .map_or_else(Vector2D::zero, |f| f.get_anchor());
+    animation_frame.get_offset() - animation_frame.flip_point(anchor, frame_size)
+}
+
+// Flipped frames are drawn by swapping their texture coordinates
+fn get_texture_coordinates(animation_frame: &AnimationFrame) -> ((f32, f32), (f32, f32)) {
+    let (left, right) = if animation_frame.get_flip_x() {
+        (1.0, 0.0)
+    } else {
+        (0.0, 1.0)
+    };
+    let (top, bottom) = if animation_frame.get_flip_y() {
+        (1.0, 0.0)
+    } else {
+        (0.0, 1.0)
+    };
+    ((left, top), (right, bottom))
+}
+
 fn draw_animation_frame<'a>(
     ui: &Ui<'a>,
     commands: &mut CommandBuffer,
@@ -293,14 +412,19 @@ fn draw_animation_frame<'a>(
     let space: Vector2D<f32> = ui.get_window_size().into();
     match texture_cache.get(&animation_frame.get_frame()) {
         Some(TextureCacheResult::Loaded(texture)) => {
-            let frame_offset = animation_frame.get_offset().to_f32();
+            let frame_size = texture.size.to_u32();
+            let frame_offset = get_frame_offset(document, animation_frame, frame_size).to_f32();
             let draw_size = texture.size * zoom;
             let cursor_pos = offset + frame_offset * zoom + (space / 2.0).floor()
                 - ((draw_size / zoom / 2.0).floor() * zoom);
 
             ui.set_cursor_pos(cursor_pos.to_tuple());
             let cursor_screen_pos: Vector2D<f32> = ui.get_cursor_screen_pos().into();
-            ui.image(texture.id, draw_size.to_tuple()).build();
+            let (uv0, uv1) = get_texture_coordinates(animation_frame);
+            ui.image(texture.id, draw_size.to_tuple())
+                .uv0(uv0)
+                .uv1(uv1)
+                .build();
 
             ui.set_cursor_pos(cursor_pos.to_tuple());
             if ui.invisible_button(im_str!("current_animation_frame"), draw_size.to_tuple()) {
@@ -309,14 +433,23 @@ fn draw_animation_frame<'a>(
 
             let is_hovered = ui.is_item_hovered();
 
-            if let Some(frame) = document.sheet.get_frame(animation_frame.get_frame()) {
+            // Only hidden in animations, the frame editing view always shows hitboxes
+            let frame = document
+                .sheet
+                .get_frame(animation_frame.get_frame())
+                .filter(|_| document.view.show_animation_hitboxes);
+            if let Some(frame) = frame {
                 for hitbox in frame.hitboxes_iter() {
+                    let mut flipped_hitbox = hitbox.clone();
+                    let rectangle =
+                        animation_frame.flip_rectangle(hitbox.get_rectangle(), frame_size);
+                    flipped_hitbox.set_position(rectangle.origin.to_vector());
                     draw_hitbox(
                         ui,
                         commands,
                         document,
                         frame,
-                        hitbox,
+                        &flipped_hitbox,
                         false,
                         frame_offset.to_i32(),
                         &mut false,
@@ -346,8 +479,70 @@ fn draw_animation_frame<'a>(
             ui.set_cursor_pos(offset.to_tuple());
             draw_spinner(ui, &ui.get_window_draw_list(), space);
         }
-        _ => {
-            // TODO
+        Some(TextureCacheResult::Missing) => {
+            let frame_offset = animation_frame.get_offset().to_f32();
+            let center = offset + frame_offset * zoom + (space / 2.0).floor();
+            draw_missing_image(ui, animation_frame.get_frame(), center);
+        }
+        None => (),
+    }
+}
+
+// Ghosts are only drawn, they do not respond to mouse interactions
+fn draw_onion_skin_frame<'a>(
+    ui: &Ui<'a>,
+    texture_cache: &TextureCache,
+    document: &Document,
+    animation_frame: &AnimationFrame,
+    alpha: f32,
+) {
+    let zoom = document.view.get_workbench_zoom_factor();
+    let offset = document.view.workbench_offset;
+    let space: Vector2D<f32> = ui.get_window_size().into();
+    if let Some(TextureCacheResult::Loaded(texture)) =
+        texture_cache.get(&animation_frame.get_frame())
+    {
+        let frame_size = texture.size.to_u32();
+        let frame_offset = get_frame_offset(document, animation_frame, frame_size).to_f32();
+        let draw_size = texture.size * zoom;
+        let cursor_pos = offset + frame_offset * zoom + (space / 2.0).floor()
+            - ((draw_size / zoom / 2.0).floor() * zoom);
+        ui.set_cursor_pos(cursor_pos.to_tuple());
+        let (uv0, uv1) = get_texture_coordinates(animation_frame);
+        ui.with_style_var(StyleVar::Alpha(alpha), || {
+            ui.image(texture.id, draw_size.to_tuple())
+                .uv0(uv0)
+                .uv1(uv1)
+                .build();
+        });
+    }
+}
+
+fn draw_onion_skin<'a>(
+    ui: &Ui<'a>,
+    texture_cache: &TextureCache,
+    document: &Document,
+    animation: &Animation,
+    frame_index: usize,
+) {
+    let num_frames = animation.get_num_frames() as i64;
+    for distance in 1..=document.view.onion_skin_count {
+        let alpha = 0.2 / distance as f32;
+        for &neighbour in &[
+            frame_index as i64 - distance as i64,
+            frame_index as i64 + distance as i64,
+        ] {
+            let neighbour = if animation.is_looping() {
+                neighbour.rem_euclid(num_frames)
+            } else {
+                neighbour
+            };
+            if neighbour < 0 || neighbour >= num_frames || neighbour == frame_index as i64 {
+                continue;
+            }
+            if let Some(animation_frame) = animation.get_frame(neighbour as usize) {
+                draw_onion_skin_frame(ui, texture_cache, document, animation_frame, alpha);
+            }
         }
     }
 }
@@ -361,11 +556,14 @@ fn draw_animation<'a>(
 ) {
     let now = document.view.timeline_clock;
     if let Some((frame_index, animation_frame)) = animation.get_frame_at(now) {
-        let is_selected = document.view.selection
-            == Some(Selection::AnimationFrame(
-                animation.get_name().to_owned(),
-                frame_index,
-            ));
+        let is_selected = match &document.view.selection {
+            Some(Selection::AnimationFrame(a, af)) => {
+                a == animation.get_name() && af.contains(&frame_index)
+            }
+            _ => false,
+        };
+
+        draw_onion_skin(ui, texture_cache, document, animation, frame_index);
 
         draw_animation_frame(
             ui,
@@ -378,14 +576,20 @@ fn draw_animation<'a>(
         );
 
         let is_mouse_dragging = ui.imgui().is_mouse_dragging(ImMouseButton::Left);
+        // Shift constrains the drag to its dominant axis, Alt disables snapping and Ctrl
+        // scrubs through the animation (see `handle_scrub`) instead of moving keyframes
         let is_shift_down = ui.imgui().key_shift();
+        let is_alt_down = ui.imgui().key_alt();
+        let is_ctrl_down = ui.imgui().key_ctrl();
 
         match document.transient.workbench_animation_frame_being_dragged {
+            None if document.transient.workbench_scrubbing => (),
+            None if is_measuring(ui, document) => (),
             None => {
-                if ui.is_item_hovered() {
+                if ui.is_item_hovered() && !is_ctrl_down {
                     ui.imgui().set_mouse_cursor(ImGuiMouseCursor::ResizeAll);
                 }
-                if ui.is_item_active() && is_mouse_dragging {
+                if ui.is_item_active() && is_mouse_dragging && !is_ctrl_down {
                     commands.begin_animation_frame_offset_drag(frame_index);
                 }
             }
@@ -393,7 +597,14 @@ fn draw_animation<'a>(
                 ui.imgui().set_mouse_cursor(ImGuiMouseCursor::ResizeAll);
                 if is_mouse_dragging {
                     let delta = ui.imgui().mouse_drag_delta(ImMouseButton::Left).into();
-                    commands.update_animation_frame_offset_drag(delta, !is_shift_down);
+                    commands.update_animation_frame_offset_drag(
+                        delta,
+                        !is_shift_down,
+                        !is_alt_down,
+                    );
+                }
+                if let Some(animation_frame) = animation.get_frame(dragged_frame_index) {
+                    draw_offset_tooltip(ui, document, animation_frame);
                 }
                 if dragged_frame_index != frame_index {
                     if let Some(animation_frame) = animation.get_frame(dragged_frame_index) {
@@ -415,6 +626,138 @@ fn draw_animation<'a>(
     }
 }
 
+fn draw_offset_tooltip<'a>(ui: &Ui<'a>, document: &Document, animation_frame: &AnimationFrame) {
+    let offset = animation_frame.get_offset();
+    let initial_offset = document
+        .transient
+        .workbench_animation_frame_drag_initial_offset;
+    let delta = offset - initial_offset;
+    ui.tooltip(|| {
+        let offset_text = format!("Offset: ({}, {})", offset.x, offset.y);
+        let delta_text = format!("Delta: ({:+}, {:+})", delta.x, delta.y);
+        ui.text(&ImString::new(offset_text));
+        ui.text(&ImString::new(delta_text));
+    });
+}
+
+fn get_texture_bounds(texture_size: Vector2D<f32>, offset: Vector2D<f32>) -> Rect<f32> {
+    let origin = offset - (texture_size / 2.0).floor();
+    Rect::new(origin.to_point(), texture_size.to_size())
+}
+
+// Area covered by the workbench item, in workbench pixels
+pub fn get_content_bounds(document: &Document, texture_cache: &TextureCache) -> Option<Rect<f32>> {
+    let get_texture_size = |path: &Path| match texture_cache.get(path) {
+        Some(TextureCacheResult::Loaded(texture)) => Some(texture.size),
+        _ => None,
+    };
+    match &document.view.workbench_item {
+        Some(WorkbenchItem::Frame(path)) => {
+            get_texture_size(path).map(|size| get_texture_bounds(size, Vector2D::zero()))
+        }
+        Some(WorkbenchItem::Animation(name)) => {
+            let animation = document.sheet.get_animation(name)?;
+            let mut bounds: Option<Rect<f32>> = None;
+            for animation_frame in animation.frames_iter() {
+                if let Some(size) = get_texture_size(animation_frame.get_frame()) {
+                    let offset = get_frame_offset(document, animation_frame, size.to_u32());
+                    let frame_bounds = get_texture_bounds(size, offset.to_f32());
+                    bounds = Some(bounds.map_or(frame_bounds, |b| b.union(&frame_bounds)));
+                }
+            }
+            bounds
+        }
+        None => None,
+    }
+}
+
+// Ctrl + horizontal drag anywhere on the workbench scrubs through the animation
+fn handle_scrub<'a>(
+    ui: &Ui<'a>,
+    commands: &mut CommandBuffer,
+    document: &Document,
+    animation: &Animation,
+) {
+    let is_mouse_dragging = ui.imgui().is_mouse_dragging(ImMouseButton::Left);
+    if document.transient.workbench_scrubbing {
+        ui.imgui().set_mouse_cursor(ImGuiMouseCursor::ResizeEW);
+        if is_mouse_dragging {
+            let delta = ui.imgui().mouse_drag_delta(ImMouseButton::Left).0;
+            let zoom = document.view.get_timeline_zoom_factor();
+            let initial_clock = document.transient.workbench_scrub_initial_clock;
+            let new_t = initial_clock.as_millis() as f32 + delta / zoom;
+            let max_t = animation.get_duration().unwrap_or(0).saturating_sub(1);
+            let new_t = (new_t.max(0.0) as u64).min(u64::from(max_t));
+            commands.update_scrub(Duration::from_millis(new_t));
+        }
+    } else if ui.imgui().key_ctrl()
+        && is_mouse_dragging
+        && animation.get_num_frames() > 0
+        && document
+            .transient
+            .workbench_animation_frame_being_dragged
+            .is_none()
+        && ui.is_window_hovered_with_flags(ImGuiHoveredFlags::AllowWhenBlockedByActiveItem)
+    {
+        commands.begin_workbench_scrub();
+    }
+}
+
+fn handle_ruler<'a>(ui: &Ui<'a>, commands: &mut CommandBuffer, document: &Document) {
+    let is_mouse_dragging = ui.imgui().is_mouse_dragging(ImMouseButton::Left);
+    if !is_mouse_dragging || !is_measuring(ui, document) {
+        return;
+    }
+    let mouse_pos: Vector2D<f32> = ui.imgui().mouse_pos().into();
+    let end = screen_to_workbench(ui, mouse_pos, document)
+        .round()
+        .to_i32();
+    if document.transient.workbench_ruler_start.is_some() {
+        commands.update_ruler(end);
+    } else if ui.is_window_hovered_with_flags(ImGuiHoveredFlags::AllowWhenBlockedByActiveItem) {
+        let drag_delta: Vector2D<f32> = ui.imgui().mouse_drag_delta(ImMouseButton::Left).into();
+        let start = screen_to_workbench(ui, mouse_pos - drag_delta, document);
+        commands.begin_ruler(start.round().to_i32());
+        commands.update_ruler(end);
+    }
+}
+
+fn draw_ruler<'a>(ui: &Ui<'a>, document: &Document) {
+    let start = match document.transient.workbench_ruler_start {
+        Some(s) => s,
+        None => return,
+    };
+    let end = document.transient.workbench_ruler_end;
+    let delta = end - start;
+    let length = delta.to_f32().length();
+
+    let color = [1.0, 0.9, 0.2, 1.0]; // TODO.style
+    let screen_start = workbench_to_screen(ui, start.to_f32(), document);
+    let screen_end = workbench_to_screen(ui, end.to_f32(), document);
+    let draw_list = ui.get_window_draw_list();
+    draw_list
+        .add_line(screen_start.to_tuple(), screen_end.to_tuple(), color)
+        .thickness(1.0) // TODO dpi
+        .build();
+    for point in &[screen_start, screen_end] {
+        draw_list
+            .add_circle(point.to_tuple(), 3.0, color) // TODO dpi
+            .filled(true)
+            .build();
+    }
+
+    let window_position: Vector2D<f32> = ui.get_window_pos().into();
+    let text_offset = vec2(10.0, 10.0); // TODO dpi
+    ui.set_cursor_pos((screen_end - window_position + text_offset).to_tuple());
+    ui.text_colored(
+        color,
+        &ImString::new(format!(
+            "{:.1}px (dx: {}, dy: {})",
+            length, delta.x, delta.y
+        )),
+    );
+}
+
 fn draw_grid<'a>(ui: &Ui<'a>, app_state: &AppState) {
     let draw_list = ui.get_window_draw_list();
     let thickness = 0.5; // TODO DPI?
@@ -475,19 +818,86 @@ fn draw_grid<'a>(ui: &Ui<'a>, app_state: &AppState) {
     }
 }
 
-fn draw_origin<'a>(ui: &Ui<'a>, document: &Document) {
-    let offset = document.view.workbench_offset;
-    let size = 10.0; // TODO DPI?
-    let thickness = 1.0; // TODO DPI?
+const CHECKERBOARD_CELL_SIZE: f32 = 8.0;
+const CHECKERBOARD_MIN_CELL_SIZE: f32 = 12.0; // TODO DPI?
+
+// Checkerboard cells follow the workbench zoom, but never get small enough to look like noise
+fn draw_background<'a>(ui: &Ui<'a>, app_state: &AppState) {
+    let background = app_state.get_preferences().workbench_background;
+    let (fill_color, cell_color) = match background {
+        WorkbenchBackground::Black => return,
+        WorkbenchBackground::Gray => ([0.5, 0.5, 0.5, 1.0], None), // TODO.style
+        WorkbenchBackground::White => ([1.0, 1.0, 1.0, 1.0], None), // TODO.style
+        WorkbenchBackground::Checkerboard => {
+            ([0.4, 0.4, 0.4, 1.0], Some([0.3, 0.3, 0.3, 1.0])) // TODO.style
+        }
+    };
 
     let draw_list = ui.get_window_draw_list();
+    ui.set_cursor_pos((0.0, 0.0));
+    let top_left: Vector2D<f32> = ui.get_cursor_screen_pos().into();
+    let space: Vector2D<f32> = ui.get_window_size().into();
+    draw_list.add_rect_filled_multicolor(
+        top_left.to_tuple(),
+        (top_left + space).to_tuple(),
+        fill_color,
+        fill_color,
+        fill_color,
+        fill_color,
+    );
+
+    let cell_color = match cell_color {
+        Some(c) => c,
+        None => return,
+    };
+    let (offset, zoom) = app_state
+        .get_current_document()
+        .map(|d| (d.view.workbench_offset, d.view.get_workbench_zoom_factor()))
+        .unwrap_or_else(|| (Vector2D::<f32>::zero(), 1.0));
+    let mut cell_size = CHECKERBOARD_CELL_SIZE * zoom;
+    while cell_size < CHECKERBOARD_MIN_CELL_SIZE {
+        cell_size *= 2.0;
+    }
+
+    let origin = top_left + offset + (space / 2.0).floor();
+    let first_cell = ((top_left - origin) / cell_size).floor().to_i32();
+    let num_cells = (space / cell_size).ceil().to_i32() + vec2(1, 1);
+    for y in first_cell.y..(first_cell.y + num_cells.y) {
+        for x in first_cell.x..(first_cell.x + num_cells.x) {
+            if (x + y) & 1 == 0 {
+                continue;
+            }
+            let cell_top_left = origin + vec2(x, y).to_f32() * cell_size;
+            draw_list.add_rect_filled_multicolor(
+                cell_top_left.to_tuple(),
+                (cell_top_left + vec2(cell_size, cell_size)).to_tuple(),
+                cell_color,
+                cell_color,
+                cell_color,
+                cell_color,
+            );
+        }
+    }
+}
+
+const CROSSHAIR_SIZE: f32 = 10.0; // TODO DPI?
 
+fn draw_origin<'a>(ui: &Ui<'a>, document: &Document) {
+    let offset = document.view.workbench_offset;
     let fill_color = [0.0 / 255.0, 200.0 / 255.0, 200.0 / 255.0]; // TODO.style
     ui.set_cursor_pos((0.0, 0.0));
 
     let top_left: Vector2D<f32> = ui.get_cursor_screen_pos().into();
     let space: Vector2D<f32> = ui.get_window_size().into();
     let center = top_left + offset + (space / 2.0).floor();
+    draw_crosshair(ui, center, fill_color);
+}
+
+fn draw_crosshair<'a>(ui: &Ui<'a>, center: Vector2D<f32>, fill_color: [f32; 3]) {
+    let size = CROSSHAIR_SIZE;
+    let thickness = 1.0; // TODO DPI?
+
+    let draw_list = ui.get_window_draw_list();
     draw_list.add_rect_filled_multicolor(
         (center.x - thickness, center.y - size),
         (center.x + thickness, center.y + size),
@@ -507,6 +917,52 @@ fn draw_origin<'a>(ui: &Ui<'a>, document: &Document) {
     );
 }
 
+// Returns whether the anchor is being dragged
+fn draw_anchor<'a>(
+    ui: &Ui<'a>,
+    commands: &mut CommandBuffer,
+    document: &Document,
+    frame: &Frame,
+) -> bool {
+    let zoom = document.view.get_workbench_zoom_factor();
+    let offset = document.view.workbench_offset;
+    let space: Vector2D<f32> = ui.get_window_size().into();
+    let anchor = frame.get_anchor().to_f32() * zoom;
+    let center = offset + (space / 2.0).floor() + anchor;
+    let button_size = vec2(CROSSHAIR_SIZE, CROSSHAIR_SIZE) * 2.0;
+
+    ui.set_cursor_pos((center - button_size / 2.0).to_tuple());
+    let screen_top_left: Vector2D<f32> = ui.get_cursor_screen_pos().into();
+    let screen_center = screen_top_left + button_size / 2.0;
+    ui.invisible_button(im_str!("frame_anchor"), button_size.to_tuple());
+    let is_hovered = ui.is_item_hovered();
+    let is_active = ui.is_item_active();
+
+    let is_dragging = document.transient.workbench_anchor_being_dragged;
+    let fill_color = if is_hovered || is_dragging {
+        [255.0 / 255.0, 230.0 / 255.0, 120.0 / 255.0] // TODO.style
+    } else {
+        [255.0 / 255.0, 160.0 / 255.0, 0.0 / 255.0] // TODO.style
+    };
+    draw_crosshair(ui, screen_center, fill_color);
+
+    let is_mouse_dragging = ui.imgui().is_mouse_dragging(ImMouseButton::Left);
+    if is_hovered || is_dragging {
+        ui.imgui().set_mouse_cursor(ImGuiMouseCursor::ResizeAll);
+    }
+    if is_dragging {
+        if is_mouse_dragging {
+            let drag_delta = ui.imgui().mouse_drag_delta(ImMouseButton::Left).into();
+            commands.update_anchor_drag(drag_delta, !ui.imgui().key_shift());
+        }
+    } else if is_active && is_mouse_dragging && !is_measuring(ui, document) {
+        commands.begin_anchor_drag();
+        return true;
+    }
+
+    is_dragging
+}
+
 fn draw_item_name<'a, T: AsRef<str>>(ui: &Ui<'a>, name: T) {
     let color = [1.0, 1.0, 1.0, 1.0]; // TODO.style
     let text_position: Vector2D<f32> = vec2(10.0, 30.0);
@@ -553,10 +1009,22 @@ pub fn draw<'a>(
             .scroll_bar(false)
             .no_bring_to_front_on_focus(true)
             .build(|| {
+                draw_background(ui, app_state);
                 draw_grid(ui, app_state);
 
                 ui.set_cursor_pos((0.0, 0.0));
-                if ui.invisible_button(im_str!("workbench_dead_zone"), rect.size.to_tuple()) {
+                let is_scrubbing = app_state
+                    .get_current_document()
+                    .map(|d| d.transient.workbench_scrubbing)
+                    .unwrap_or(false);
+                let is_measuring = app_state
+                    .get_current_document()
+                    .map(|d| is_measuring(ui, d))
+                    .unwrap_or(false);
+                if ui.invisible_button(im_str!("workbench_dead_zone"), rect.size.to_tuple())
+                    && !is_scrubbing
+                    && !is_measuring
+                {
                     commands.clear_selection();
                 }
                 handle_drag_and_drop(ui, app_state, commands);
@@ -578,6 +1046,7 @@ pub fn draw<'a>(
                         Some(WorkbenchItem::Animation(name)) => {
                             if let Some(animation) = document.sheet.get_animation(name) {
                                 draw_animation(ui, commands, texture_cache, document, animation);
+                                handle_scrub(ui, commands, document, animation);
                                 draw_origin(ui, document);
                                 draw_item_name(ui, animation.get_name());
                             }
@@ -585,19 +1054,24 @@ pub fn draw<'a>(
                         None => (),
                     }
 
+                    handle_ruler(ui, commands, document);
+                    draw_ruler(ui, document);
+
                     if ui.is_window_hovered() {
                         if ui.imgui().key_ctrl() {
                             let mouse_wheel = ui.imgui().mouse_wheel();
+                            let mouse_pos: Vector2D<f32> = ui.imgui().mouse_pos().into();
+                            let cursor = mouse_pos - rect.center().to_vector();
                             if mouse_wheel > 0.0 {
-                                commands.workbench_zoom_in();
+                                commands.workbench_zoom_in_at(cursor);
                             } else if mouse_wheel < 0.0 {
-                                commands.workbench_zoom_out();
+                                commands.workbench_zoom_out_at(cursor);
                             }
                         }
-                        if ui.imgui().is_mouse_dragging(ImMouseButton::Right) {
-                            commands.pan(ui.imgui().mouse_delta().into());
-                        }
-                        if ui.imgui().is_mouse_down(ImMouseButton::Right) {
+                        if let Some(button) = get_pan_button(ui, app_state.get_preferences()) {
+                            if ui.imgui().is_mouse_dragging(button) {
+                                commands.pan(ui.imgui().mouse_delta().into());
+                            }
                             ui.imgui().set_mouse_cursor(ImGuiMouseCursor::ResizeAll);
                         }
                     }
@@ -0,0 +1,672 @@
+use euclid::*;
+use failure::Error;
+use liquid::value::{Scalar, Value};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::sheet::{
+    relative_path_or_absolute, Animation, AnimationFrame, ExportFormat, ExportSettings, Frame,
+    Hitbox, LoopMode, Shape, Sheet, TemplateVariable,
+};
+
+mod pack;
+mod preview;
+mod write;
+pub use pack::*;
+pub use preview::*;
+pub use write::*;
+
+type LiquidData = HashMap<Cow<'static, str>, Value>;
+type TextureLayout = HashMap<PathBuf, PackedFrame>;
+
+// Globals written by `liquid_data_from_sheet`, which template variables may not override
+const BUILT_IN_GLOBALS: [&str; 6] = [
+    "frames",
+    "animations",
+    "sheet_image",
+    "sheet_images",
+    "sheet_width",
+    "sheet_height",
+];
+
+#[derive(Fail, Debug)]
+pub enum ExportError {
+    #[fail(display = "Sheet has no export settings")]
+    MissingExportSettings,
+    #[fail(display = "Template parsing error: {}", _0)]
+    TemplateParsingError(String),
+    #[fail(display = "Template rendering error: {}", _0)]
+    TemplateRenderingError(String),
+    #[fail(display = "An animation references a frame which is not part of the sheet")]
+    InvalidFrameReference,
+    #[fail(display = "The sheet contains a frame which was not packed into the texture atlas")]
+    FrameWasNotPacked,
+    #[fail(display = "No {} destination selected", _0)]
+    MissingDestination(&'static str),
+    #[fail(display = "No template selected")]
+    MissingTemplate,
+    #[fail(display = "Destination directory does not exist: {}", _0)]
+    DestinationDirectoryNotFound(String),
+    #[fail(display = "These frame images are missing: {}", _0)]
+    MissingFrameImages(String),
+    #[fail(display = "Animation has no keyframes")]
+    EmptyAnimation,
+    #[fail(display = "Template variables must have a name")]
+    UnnamedTemplateVariable,
+    #[fail(display = "Template variable is defined more than once: {}", _0)]
+    DuplicateTemplateVariable(String),
+    #[fail(display = "Template variable name is reserved: {}", _0)]
+    ReservedTemplateVariable(String),
+}
+
+// Placement of a frame in the texture atlas, as exposed to every export format.
+// Fields for packing features that are turned off keep their defaults:
+// - `rotated` is false unless the frame was rotated by 90 degrees while packing
+// - `trimmed` is false and `trim_left`/`trim_top` are 0 unless transparent borders were trimmed
+// - `page` is 0 unless the atlas was split across several textures
+// UVs are normalized against the atlas texture and left out when there is no texture.
+// `pivot_x`/`pivot_y` locate the frame anchor, in pixels from the top-left corner of the frame.
+struct LiquidFrame {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    rotated: bool,
+    trimmed: bool,
+    trim_left: u32,
+    trim_top: u32,
+    page: u32,
+    uvs: Option<(f64, f64, f64, f64)>,
+    pivot: (i32, i32),
+}
+
+impl From<&PackedFrame> for LiquidFrame {
+    fn from(packed_frame: &PackedFrame) -> LiquidFrame {
+        LiquidFrame {
+            x: packed_frame.position_in_sheet.0,
+            y: packed_frame.position_in_sheet.1,
+            width: packed_frame.size_in_sheet.0,
+            height: packed_frame.size_in_sheet.1,
+            rotated: packed_frame.rotated,
+            trimmed: packed_frame.trimmed,
+            trim_left: packed_frame.trim_offset.0,
+            trim_top: packed_frame.trim_offset.1,
+            page: packed_frame.page,
+            uvs: None,
+            pivot: (
+                (packed_frame.size_in_sheet.0 / 2) as i32,
+                (packed_frame.size_in_sheet.1 / 2) as i32,
+            ),
+        }
+    }
+}
+
+impl LiquidFrame {
+    fn with_uvs(mut self, texture_size: Option<(u32, u32)>) -> LiquidFrame {
+        self.uvs = match texture_size {
+            Some((texture_width, texture_height)) if texture_width > 0 && texture_height > 0 => {
+                let texture_width = f64::from(texture_width);
+                let texture_height = f64::from(texture_height);
+                Some((
+                    f64::from(self.x) / texture_width,
+                    f64::from(self.y) / texture_height,
+                    f64::from(self.x + self.width) / texture_width,
+                    f64::from(self.y + self.height) / texture_height,
+                ))
+            }
+            _ => None,
+        };
+        self
+    }
+
+    fn with_anchor(mut self, anchor: Vector2D<i32>) -> LiquidFrame {
+        self.pivot = (self.pivot.0 + anchor.x, self.pivot.1 + anchor.y);
+        self
+    }
+
+    fn insert_into(&self, map: &mut LiquidData) {
+        map.insert("x".into(), Value::Scalar(Scalar::new(self.x as i32)));
+        map.insert("y".into(), Value::Scalar(Scalar::new(self.y as i32)));
+        map.insert(
+            "width".into(),
+            Value::Scalar(Scalar::new(self.width as i32)),
+        );
+        map.insert(
+            "height".into(),
+            Value::Scalar(Scalar::new(self.height as i32)),
+        );
+        map.insert("rotated".into(), Value::Scalar(Scalar::new(self.rotated)));
+        map.insert("trimmed".into(), Value::Scalar(Scalar::new(self.trimmed)));
+        map.insert(
+            "trim_left".into(),
+            Value::Scalar(Scalar::new(self.trim_left as i32)),
+        );
+        map.insert(
+            "trim_top".into(),
+            Value::Scalar(Scalar::new(self.trim_top as i32)),
+        );
+        map.insert("page".into(), Value::Scalar(Scalar::new(self.page as i32)));
+        map.insert("pivot_x".into(), Value::Scalar(Scalar::new(self.pivot.0)));
+        map.insert("pivot_y".into(), Value::Scalar(Scalar::new(self.pivot.1)));
+        let uv_names = ["u0", "v0", "u1", "v1"];
+        match self.uvs {
+            Some((u0, v0, u1, v1)) => {
+                for (name, uv) in uv_names.iter().zip(&[u0, v0, u1, v1]) {
+                    map.insert((*name).into(), Value::Scalar(Scalar::new(*uv)));
+                }
+            }
+            None => {
+                for name in &uv_names {
+                    map.insert((*name).into(), Value::Nil);
+                }
+            }
+        }
+    }
+}
+
+// Paths are relative to `metadata_paths_root` unless the settings ask for absolute paths.
+// Paths which cannot be made relative (eg. on another drive) are kept absolute.
+fn get_exported_path(path: &Path, export_settings: &ExportSettings) -> PathBuf {
+    if export_settings.absolute_image_paths {
+        return path.to_owned();
+    }
+    relative_path_or_absolute(path, &export_settings.metadata_paths_root)
+}
+
+// Matches the color hitboxes are drawn with in the workbench, as `#rrggbb`
+fn get_hitbox_color(hitbox: &Hitbox) -> String {
+    let (r, g, b) = hitbox.get_color();
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn liquid_data_from_hitbox(
+    hitbox: &Hitbox,
+    packed_frame: &PackedFrame,
+) -> Result<LiquidData, Error> {
+    let mut map = LiquidData::new();
+
+    map.insert(
+        "name".into(),
+        Value::Scalar(Scalar::new(hitbox.get_name().to_owned())),
+    );
+    map.insert(
+        "color".into(),
+        Value::Scalar(Scalar::new(get_hitbox_color(hitbox))),
+    );
+
+    // Circles are also described by their bounding box
+    let (shape, radius) = match hitbox.get_shape() {
+        Shape::Rectangle(_) => ("rectangle", Value::Nil),
+        Shape::Circle(c) => ("circle", Value::Scalar(Scalar::new(c.radius as i32))),
+    };
+    map.insert("shape".into(), Value::Scalar(Scalar::new(shape.to_owned())));
+    map.insert("radius".into(), radius);
+
+    map.insert(
+        "left_from_frame_center".into(),
+        Value::Scalar(Scalar::new(hitbox.get_position().x)),
+    );
+
+    map.insert(
+        "top_from_frame_center".into(),
+        Value::Scalar(Scalar::new(hitbox.get_position().y)),
+    );
+
+    let frame_size: Vector2D<u32> = packed_frame.size_in_sheet.into();
+    let hitbox_top_left_from_frame_top_left =
+        hitbox.get_position() + (frame_size.to_f32() / 2.0).floor().to_i32();
+
+    map.insert(
+        "left_from_frame_left".into(),
+        Value::Scalar(Scalar::new(hitbox_top_left_from_frame_top_left.x)),
+    );
+    map.insert(
+        "top_from_frame_top".into(),
+        Value::Scalar(Scalar::new(hitbox_top_left_from_frame_top_left.y)),
+    );
+
+    map.insert(
+        "width".into(),
+        Value::Scalar(Scalar::new(hitbox.get_size().x as i32)),
+    );
+
+    map.insert(
+        "height".into(),
+        Value::Scalar(Scalar::new(hitbox.get_size().y as i32)),
+    );
+
+    Ok(map)
+}
+
+// Keyframe hitboxes are flipped along with their frame and positioned relative to the
+// animation origin, like the keyframe itself
+fn liquid_data_from_keyframe_hitbox(
+    hitbox: &Hitbox,
+    animation_frame: &AnimationFrame,
+    frame_size: Vector2D<u32>,
+    center_offset: Vector2D<i32>,
+) -> LiquidData {
+    let mut map = LiquidData::new();
+
+    map.insert(
+        "name".into(),
+        Value::Scalar(Scalar::new(hitbox.get_name().to_owned())),
+    );
+    map.insert(
+        "color".into(),
+        Value::Scalar(Scalar::new(get_hitbox_color(hitbox))),
+    );
+
+    let (shape, radius) = match hitbox.get_shape() {
+        Shape::Rectangle(_) => ("rectangle", Value::Nil),
+        Shape::Circle(c) => ("circle", Value::Scalar(Scalar::new(c.radius as i32))),
+    };
+    map.insert("shape".into(), Value::Scalar(Scalar::new(shape.to_owned())));
+    map.insert("radius".into(), radius);
+
+    let rectangle = animation_frame.flip_rectangle(hitbox.get_rectangle(), frame_size);
+    let top_left = rectangle.origin.to_vector() + center_offset;
+    map.insert("left".into(), Value::Scalar(Scalar::new(top_left.x)));
+    map.insert("top".into(), Value::Scalar(Scalar::new(top_left.y)));
+    map.insert(
+        "width".into(),
+        Value::Scalar(Scalar::new(rectangle.size.width)),
+    );
+    map.insert(
+        "height".into(),
+        Value::Scalar(Scalar::new(rectangle.size.height)),
+    );
+
+    map
+}
+
+fn liquid_data_from_frame(
+    sheet: &Sheet,
+    export_settings: &ExportSettings,
+    frame: &Frame,
+    texture_layout: &TextureLayout,
+    texture_size: Option<(u32, u32)>,
+) -> Result<LiquidData, Error> {
+    let mut frame_data = LiquidData::new();
+    let source = get_exported_path(frame.get_source(), export_settings);
+    frame_data.insert(
+        "source".into(),
+        Value::Scalar(Scalar::new(source.to_string_lossy().into_owned())),
+    );
+
+    let index = sheet
+        .frames_iter()
+        .position(|f| f as *const Frame == frame as *const Frame)
+        .ok_or(ExportError::InvalidFrameReference)?;
+    frame_data.insert("index".into(), Value::Scalar(Scalar::new(index as i32)));
+
+    let frame_layout = texture_layout
+        .get(frame.get_source())
+        .ok_or(ExportError::FrameWasNotPacked)?;
+
+    LiquidFrame::from(frame_layout)
+        .with_uvs(texture_size)
+        .with_anchor(frame.get_anchor())
+        .insert_into(&mut frame_data);
+
+    let mut hitboxes = Vec::new();
+    for hitbox in frame.hitboxes_iter() {
+        let packed_frame = texture_layout
+            .get(frame.get_source())
+            .ok_or(ExportError::FrameWasNotPacked)?;
+        let hitbox_data = liquid_data_from_hitbox(hitbox, packed_frame)?;
+        hitboxes.push(Value::Object(hitbox_data));
+    }
+    frame_data.insert("hitboxes".into(), Value::Array(hitboxes));
+
+    Ok(frame_data)
+}
+
+fn liquid_data_from_animation_frame(
+    sheet: &Sheet,
+    export_settings: &ExportSettings,
+    animation: &Animation,
+    animation_frame: &AnimationFrame,
+    texture_layout: &TextureLayout,
+    texture_size: Option<(u32, u32)>,
+) -> Result<LiquidData, Error> {
+    let packed_frame = texture_layout
+        .get(animation_frame.get_frame())
+        .ok_or(ExportError::FrameWasNotPacked)?;
+
+    let frame = sheet
+        .get_frame(animation_frame.get_frame())
+        .ok_or(ExportError::InvalidFrameReference)?;
+
+    let mut map = LiquidData::new();
+    map.insert(
+        "duration".into(),
+        Value::Scalar(Scalar::new(
+            animation.get_frame_duration(animation_frame) as i32
+        )),
+    );
+
+    // Offsets place the frame so that its anchor lands on the keyframe offset
+    let frame_size: Vector2D<u32> = packed_frame.size_in_sheet.into();
+    let anchor = animation_frame.flip_point(frame.get_anchor(), frame_size);
+    let center_offset = animation_frame.get_offset() - anchor;
+    map.insert(
+        "center_offset_x".into(),
+        Value::Scalar(Scalar::new(center_offset.x)),
+    );
+    map.insert(
+        "center_offset_y".into(),
+        Value::Scalar(Scalar::new(center_offset.y)),
+    );
+
+    map.insert(
+        "sound".into(),
+        match animation_frame.get_sound() {
+            Some(s) => Value::Scalar(Scalar::new(s.to_owned())),
+            None => Value::Nil,
+        },
+    );
+
+    map.insert(
+        "user_data".into(),
+        match animation_frame.get_user_data() {
+            Some(d) => Value::Scalar(Scalar::new(d.to_owned())),
+            None => Value::Nil,
+        },
+    );
+
+    let top_left_offset = center_offset - (frame_size.to_f32() / 2.0).floor().to_i32();
+
+    map.insert(
+        "top_left_offset_x".into(),
+        Value::Scalar(Scalar::new(top_left_offset.x)),
+    );
+    map.insert(
+        "top_left_offset_y".into(),
+        Value::Scalar(Scalar::new(top_left_offset.y)),
+    );
+
+    map.insert(
+        "flip_x".into(),
+        Value::Scalar(Scalar::new(animation_frame.get_flip_x())),
+    );
+    map.insert(
+        "flip_y".into(),
+        Value::Scalar(Scalar::new(animation_frame.get_flip_y())),
+    );
+
+    let hitboxes = frame
+        .hitboxes_iter()
+        .map(|h| {
+            let hitbox_data =
+                liquid_data_from_keyframe_hitbox(h, animation_frame, frame_size, center_offset);
+            Value::Object(hitbox_data)
+        })
+        .collect();
+    map.insert("hitboxes".into(), Value::Array(hitboxes));
+
+    let frame_data =
+        liquid_data_from_frame(sheet, export_settings, frame, texture_layout, texture_size)?;
+    map.insert("frame".into(), Value::Object(frame_data));
+
+    Ok(map)
+}
+
+fn liquid_data_from_animation(
+    sheet: &Sheet,
+    export_settings: &ExportSettings,
+    animation: &Animation,
+    texture_layout: &TextureLayout,
+    texture_size: Option<(u32, u32)>,
+) -> Result<LiquidData, Error> {
+    let mut map = LiquidData::new();
+
+    map.insert(
+        "name".into(),
+        Value::Scalar(Scalar::new(animation.get_name().to_owned())),
+    );
+
+    map.insert(
+        "is_looping".into(),
+        Value::Scalar(Scalar::new(animation.is_looping())),
+    );
+
+    let loop_mode = match animation.get_loop_mode() {
+        LoopMode::Once => "once",
+        LoopMode::Loop => "loop",
+        LoopMode::PingPong => "ping_pong",
+    };
+    map.insert(
+        "loop_mode".into(),
+        Value::Scalar(Scalar::new(loop_mode.to_owned())),
+    );
+
+    map.insert(
+        "group".into(),
+        match animation.get_group() {
+            Some(g) => Value::Scalar(Scalar::new(g.to_owned())),
+            None => Value::Nil,
+        },
+    );
+
+    map.insert(
+        "direction".into(),
+        match animation.get_direction() {
+            Some(d) => Value::Scalar(Scalar::new(d.to_owned())),
+            None => Value::Nil,
+        },
+    );
+
+    let tags = animation
+        .tags_iter()
+        .map(|t| Value::Scalar(Scalar::new(t.to_owned())))
+        .collect();
+    map.insert("tags".into(), Value::Array(tags));
+
+    map.insert(
+        "duration_millis".into(),
+        Value::Scalar(Scalar::new(animation.get_duration().unwrap_or(0) as i32)),
+    );
+    map.insert(
+        "frame_count".into(),
+        Value::Scalar(Scalar::new(animation.get_num_frames() as i32)),
+    );
+
+    let mut frames = Vec::new();
+    let mut durations = Vec::new();
+    let mut frame_indices = Vec::new();
+    let frame_times = animation.get_frame_times();
+    for (animation_frame, start_time) in animation.frames_iter().zip(frame_times) {
+        let mut frame = liquid_data_from_animation_frame(
+            sheet,
+            export_settings,
+            animation,
+            animation_frame,
+            texture_layout,
+            texture_size,
+        )?;
+        frame.insert(
+            "start_time_millis".into(),
+            Value::Scalar(Scalar::new(start_time as i32)),
+        );
+        frames.push(Value::Object(frame));
+        durations.push(Value::Scalar(Scalar::new(
+            animation.get_frame_duration(animation_frame) as i32,
+        )));
+        let frame_index = sheet
+            .frames_iter()
+            .position(|f| f.get_source() == animation_frame.get_frame())
+            .ok_or(ExportError::InvalidFrameReference)?;
+        frame_indices.push(Value::Scalar(Scalar::new(frame_index as i32)));
+    }
+    map.insert("keyframes".into(), Value::Array(frames));
+    map.insert("durations".into(), Value::Array(durations));
+    map.insert("frame_indices".into(), Value::Array(frame_indices));
+
+    Ok(map)
+}
+
+fn liquid_data_from_sheet(
+    sheet: &Sheet,
+    export_settings: &ExportSettings,
+    texture_layout: &TextureLayout,
+    texture_size: Option<(u32, u32)>,
+) -> Result<LiquidData, Error> {
+    let mut map = LiquidData::new();
+
+    {
+        let mut frames = Vec::new();
+        for frame in sheet.frames_iter() {
+            frames.push(Value::Object(liquid_data_from_frame(
+                sheet,
+                export_settings,
+                frame,
+                texture_layout,
+                texture_size,
+            )?));
+        }
+        let frames_value = Value::Array(frames);
+        map.insert("frames".into(), frames_value);
+    }
+
+    {
+        let mut animations = Vec::new();
+        for animation in sheet.animations_iter() {
+            let animation_data = liquid_data_from_animation(
+                sheet,
+                export_settings,
+                animation,
+                texture_layout,
+                texture_size,
+            )?;
+            animations.push(Value::Object(animation_data));
+        }
+        let animations_value = Value::Array(animations);
+        map.insert("animations".into(), animations_value);
+    }
+
+    {
+        let num_pages = texture_layout
+            .values()
+            .map(|f| f.page + 1)
+            .max()
+            .unwrap_or(1);
+        let mut image_paths = Vec::new();
+        for page in 0..num_pages {
+            let image_path = if export_settings.has_texture_destination() {
+                let destination = export_settings.get_texture_page_destination(page, num_pages);
+                get_exported_path(&destination, export_settings)
+            } else {
+                PathBuf::new()
+            };
+            image_paths.push(Value::Scalar(Scalar::new(
+                image_path.to_string_lossy().into_owned(),
+            )));
+        }
+        map.insert("sheet_image".into(), image_paths[0].clone());
+        map.insert("sheet_images".into(), Value::Array(image_paths));
+    }
+
+    // Metadata-only exports have no atlas to measure
+    match texture_size {
+        Some((width, height)) => {
+            map.insert(
+                "sheet_width".into(),
+                Value::Scalar(Scalar::new(width as i32)),
+            );
+            map.insert(
+                "sheet_height".into(),
+                Value::Scalar(Scalar::new(height as i32)),
+            );
+        }
+        None => {
+            map.insert("sheet_width".into(), Value::Nil);
+            map.insert("sheet_height".into(), Value::Nil);
+        }
+    }
+
+    Ok(map)
+}
+
+fn parse_template(path: &Path) -> Result<liquid::Template, Error> {
+    liquid::ParserBuilder::with_liquid()
+        .build()
+        .parse_file(path)
+        .map_err(|e| ExportError::TemplateParsingError(e.to_string()).into())
+}
+
+fn validate_destination(destination: &Path, kind: &'static str) -> Result<(), Error> {
+    if destination.as_os_str().is_empty() {
+        return Err(ExportError::MissingDestination(kind).into());
+    }
+    match destination.parent() {
+        Some(directory) if directory.is_dir() => Ok(()),
+        _ => Err(ExportError::DestinationDirectoryNotFound(
+            destination.to_string_lossy().into_owned(),
+        )
+        .into()),
+    }
+}
+
+fn validate_template_variables(variables: &[TemplateVariable]) -> Result<(), Error> {
+    for (index, variable) in variables.iter().enumerate() {
+        if variable.name.trim().is_empty() {
+            return Err(ExportError::UnnamedTemplateVariable.into());
+        }
+        if BUILT_IN_GLOBALS.contains(&variable.name.as_str()) {
+            return Err(ExportError::ReservedTemplateVariable(variable.name.clone()).into());
+        }
+        if variables[..index].iter().any(|v| v.name == variable.name) {
+            return Err(ExportError::DuplicateTemplateVariable(variable.name.clone()).into());
+        }
+    }
+    Ok(())
+}
+
+// Catches settings that would make the export fail before they are accepted
+pub fn validate_export_settings(export_settings: &ExportSettings) -> Result<(), Error> {
+    if !export_settings.skip_texture {
+        validate_destination(&export_settings.texture_destination, "texture")?;
+    }
+    validate_destination(&export_settings.metadata_destination, "metadata")?;
+    if let ExportFormat::Template(p) = &export_settings.format {
+        if p.as_os_str().is_empty() {
+            return Err(ExportError::MissingTemplate.into());
+        }
+        parse_template(p)?;
+    }
+    validate_template_variables(&export_settings.template_variables)?;
+    Ok(())
+}
+
+pub fn export_sheet(
+    sheet: &Sheet,
+    export_settings: &ExportSettings,
+    texture_layout: &TextureLayout,
+    texture_size: Option<(u32, u32)>,
+) -> Result<String, Error> {
+    let mut globals: LiquidData =
+        liquid_data_from_sheet(sheet, export_settings, texture_layout, texture_size)?;
+
+    validate_template_variables(&export_settings.template_variables)?;
+    for variable in &export_settings.template_variables {
+        globals.insert(
+            variable.name.clone().into(),
+            Value::Scalar(Scalar::new(variable.value.clone())),
+        );
+    }
+
+    let output = match &export_settings.format {
+        ExportFormat::Template(p) => {
+            let template = parse_template(p)?;
+            template
+                .render(&globals)
+                .map_err(|e| ExportError::TemplateRenderingError(e.to_string()))?
+        }
+        // Going through serde_json::Value sorts keys, so the output is stable
+        ExportFormat::Json => serde_json::to_string_pretty(&serde_json::to_value(&globals)?)?,
+    };
+
+    Ok(output)
+}
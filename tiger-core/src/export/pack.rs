@@ -0,0 +1,621 @@
+use failure::Error;
+use image::{DynamicImage, GenericImageView};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use texture_packer::exporter::ImageExporter;
+use texture_packer::importer::ImageImporter;
+use texture_packer::{TexturePacker, TexturePackerConfig};
+
+use crate::export::ExportError;
+use crate::sheet::{ExportSettings, Frame, PackStrategy, Sheet};
+
+#[derive(Fail, Debug)]
+pub enum PackError {
+    #[fail(display = "Error reading a frame")]
+    FrameReadError,
+    #[fail(display = "Error exporting texture from packing data")]
+    PackerExportError,
+    #[fail(display = "These frames do not fit in the texture atlas: {}", _0)]
+    FramesDoNotFit(String),
+    #[fail(display = "These frames exceed the maximum atlas page size: {}", _0)]
+    FramesLargerThanPage(String),
+}
+
+pub struct PackedFrame {
+    pub position_in_sheet: (u32, u32),
+    pub size_in_sheet: (u32, u32),
+    pub rotated: bool,
+    pub trimmed: bool,
+    pub trim_offset: (u32, u32),
+    pub page: u32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PackSettings {
+    pub atlas_size: Option<(u32, u32)>,
+    pub max_page_size: Option<(u32, u32)>,
+    pub sort_frames_by_size: bool,
+    pub padding: u32,
+    pub extrude: u32,
+    pub strategy: PackStrategy,
+}
+
+impl From<&ExportSettings> for PackSettings {
+    fn from(export_settings: &ExportSettings) -> PackSettings {
+        PackSettings {
+            atlas_size: export_settings.atlas_size,
+            max_page_size: export_settings.max_size,
+            sort_frames_by_size: export_settings.sort_frames_by_size,
+            padding: export_settings.padding,
+            extrude: export_settings.extrude,
+            strategy: export_settings.pack_strategy,
+        }
+    }
+}
+
+// How well frames fill the atlas, padding and extrusion count as wasted space
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PackStats {
+    pub page_size: (u32, u32),
+    pub num_pages: u32,
+    pub used_area: u64,
+    pub total_area: u64,
+}
+
+impl PackStats {
+    pub fn get_wasted_area(&self) -> u64 {
+        self.total_area.saturating_sub(self.used_area)
+    }
+}
+
+pub struct PackedSheet {
+    pages: Vec<DynamicImage>,
+    layout: HashMap<PathBuf, PackedFrame>,
+}
+
+impl PackedSheet {
+    pub fn get_pages(&self) -> &[DynamicImage] {
+        &self.pages
+    }
+
+    pub fn get_layout(&self) -> &HashMap<PathBuf, PackedFrame> {
+        &self.layout
+    }
+
+    // Size of each page
+    pub fn get_size(&self) -> (u32, u32) {
+        self.pages.first().map(|p| p.dimensions()).unwrap_or((0, 0))
+    }
+
+    pub fn get_stats(&self) -> PackStats {
+        let page_size = self.get_size();
+        let num_pages = self.pages.len() as u32;
+        let used_area = self
+            .layout
+            .values()
+            .map(|f| u64::from(f.size_in_sheet.0) * u64::from(f.size_in_sheet.1))
+            .sum();
+        let total_area = u64::from(page_size.0) * u64::from(page_size.1) * u64::from(num_pages);
+        PackStats {
+            page_size,
+            num_pages,
+            used_area,
+            total_area,
+        }
+    }
+}
+
+// Reports every missing image at once instead of failing on the first one
+fn check_missing_images(sheet: &Sheet) -> Result<(), Error> {
+    let mut missing_images: Vec<String> = Vec::new();
+    for frame in sheet.frames_iter() {
+        let image = frame.get_image();
+        let name = image.to_string_lossy().into_owned();
+        if !image.exists() && !missing_images.contains(&name) {
+            missing_images.push(name);
+        }
+    }
+    if missing_images.is_empty() {
+        Ok(())
+    } else {
+        Err(ExportError::MissingFrameImages(missing_images.join(", ")).into())
+    }
+}
+
+pub fn pack_sheet(sheet: &Sheet, settings: &PackSettings) -> Result<PackedSheet, Error> {
+    pack_sheet_with_progress(sheet, settings, &|_| Ok(()))
+}
+
+// Reading frames is the slow part of packing, the callback runs after each one and can
+// abort packing by returning an error
+pub fn pack_sheet_with_progress(
+    sheet: &Sheet,
+    settings: &PackSettings,
+    on_frame_read: &dyn Fn(u32) -> Result<(), Error>,
+) -> Result<PackedSheet, Error> {
+    check_missing_images(sheet)?;
+    let mut images = ImageCache::new();
+    let mut textures = Vec::new();
+    for frame in sheet.frames_iter() {
+        let source = frame.get_source();
+        let texture = images.read_frame(frame)?;
+        textures.push((source.to_string_lossy().into_owned(), texture));
+        on_frame_read(textures.len() as u32)?;
+    }
+    pack_textures(textures, settings)
+}
+
+// Decoded source images for the duration of a single export, so frames sliced from the
+// same image only decode it once
+pub(super) struct ImageCache {
+    images: HashMap<PathBuf, DynamicImage>,
+}
+
+impl ImageCache {
+    pub(super) fn new() -> ImageCache {
+        ImageCache {
+            images: HashMap::new(),
+        }
+    }
+
+    fn get_image_mut(&mut self, path: PathBuf) -> Result<&mut DynamicImage, Error> {
+        if !self.images.contains_key(&path) {
+            let image =
+                ImageImporter::import_from_file(&path).map_err(|_| PackError::FrameReadError)?;
+            self.images.insert(path.clone(), image);
+        }
+        Ok(self.images.get_mut(&path).unwrap())
+    }
+
+    // Sliced frames only keep their part of the image
+    pub(super) fn read_frame(&mut self, frame: &Frame) -> Result<DynamicImage, Error> {
+        let image = self.get_image_mut(frame.get_image())?;
+        Ok(match frame.get_source_rect() {
+            Some(r) => image.crop(r.origin.x, r.origin.y, r.size.width, r.size.height),
+            None => image.clone(),
+        })
+    }
+
+    // Whole images are only measured from their header, without decoding their pixels
+    fn measure_frame(&mut self, frame: &Frame) -> Result<(u32, u32), Error> {
+        if let Some(r) = frame.get_source_rect() {
+            return Ok((r.size.width, r.size.height));
+        }
+        if let Some(image) = self.images.get(&frame.get_image()) {
+            return Ok(image.dimensions());
+        }
+        image::image_dimensions(frame.get_image()).map_err(|_| PackError::FrameReadError.into())
+    }
+}
+
+// Repeats the outermost pixels of the texture so filtering near its edges does not sample
+// neighbouring frames
+fn extrude_texture(texture: &DynamicImage, extrude: u32) -> DynamicImage {
+    let (width, height) = texture.dimensions();
+    if extrude == 0 || width == 0 || height == 0 {
+        return texture.clone();
+    }
+    let source = texture.to_rgba();
+    let mut extruded = image::RgbaImage::new(width + 2 * extrude, height + 2 * extrude);
+    for (x, y, pixel) in extruded.enumerate_pixels_mut() {
+        let source_x = std::cmp::min(x.saturating_sub(extrude), width - 1);
+        let source_y = std::cmp::min(y.saturating_sub(extrude), height - 1);
+        *pixel = *source.get_pixel(source_x, source_y);
+    }
+    DynamicImage::ImageRgba8(extruded)
+}
+
+// Only affects the packing order, exported frame indices still follow the sheet order
+fn sort_textures_by_size(textures: &mut Vec<(String, DynamicImage)>) {
+    textures.sort_by(|(_, a), (_, b)| (b.height(), b.width()).cmp(&(a.height(), a.width())));
+}
+
+fn sort_textures_by_height(textures: &mut Vec<(String, DynamicImage)>) {
+    textures.sort_by(|(_, a), (_, b)| b.height().cmp(&a.height()));
+}
+
+// Where a texture landed on its page
+struct Placement {
+    name: String,
+    position: (u32, u32),
+    size: (u32, u32),
+    rotated: bool,
+    trimmed: bool,
+    trim_offset: (u32, u32),
+}
+
+type Pages = (Vec<DynamicImage>, Vec<Vec<Placement>>);
+
+// Textures left over after filling a page can only go on a later page if the atlas is paged
+fn check_unplaced_textures(
+    unplaced_textures: &[(String, DynamicImage)],
+    is_paged: bool,
+    is_stuck: bool,
+) -> Result<(), Error> {
+    if !unplaced_textures.is_empty() && (!is_paged || is_stuck) {
+        let unplaced_frames: Vec<&str> = unplaced_textures
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        return Err(PackError::FramesDoNotFit(unplaced_frames.join(", ")).into());
+    }
+    Ok(())
+}
+
+fn pack_skyline(
+    mut textures: Vec<(String, DynamicImage)>,
+    config: TexturePackerConfig,
+    is_paged: bool,
+) -> Result<Pages, Error> {
+    let mut packers = Vec::new();
+    while !textures.is_empty() {
+        let mut packer = TexturePacker::new_skyline(config);
+        let mut unplaced_textures = Vec::new();
+        for (name, texture) in textures {
+            if packer.can_pack(&texture) {
+                packer.pack_own(name, texture);
+            } else {
+                unplaced_textures.push((name, texture));
+            }
+        }
+        let is_stuck = packer.get_frames().is_empty();
+        packers.push(packer);
+        check_unplaced_textures(&unplaced_textures, is_paged, is_stuck)?;
+        textures = unplaced_textures;
+    }
+
+    let mut pages = Vec::new();
+    let mut placements = Vec::new();
+    for packer in &packers {
+        pages.push(ImageExporter::export(packer).map_err(|_| PackError::PackerExportError)?);
+        placements.push(
+            packer
+                .get_frames()
+                .iter()
+                .map(|(k, v)| Placement {
+                    name: k.to_owned(),
+                    position: (v.frame.x, v.frame.y),
+                    size: (v.frame.w, v.frame.h),
+                    rotated: v.rotated,
+                    trimmed: v.trimmed,
+                    trim_offset: (v.source.x, v.source.y),
+                })
+                .collect(),
+        );
+    }
+    Ok((pages, placements))
+}
+
+// Places textures left to right, starting a new row whenever the current one is full.
+// Faster and nearly as tight as skyline packing when frames have similar sizes.
+fn pack_shelves(
+    mut textures: Vec<(String, DynamicImage)>,
+    max_size: (u32, u32),
+    padding: u32,
+    is_paged: bool,
+) -> Result<Pages, Error> {
+    let (max_width, max_height) = max_size;
+    let mut pages = Vec::new();
+    let mut placements = Vec::new();
+    while !textures.is_empty() {
+        let mut page_textures = Vec::new();
+        let mut unplaced_textures = Vec::new();
+        let (mut x, mut y, mut row_height) = (0, 0, 0);
+        let (mut page_width, mut page_height) = (0, 0);
+        for (name, texture) in textures {
+            let (width, height) = texture.dimensions();
+            if x > 0 && x + width > max_width {
+                x = 0;
+                y += row_height + padding;
+                row_height = 0;
+            }
+            if x + width > max_width || y + height > max_height {
+                unplaced_textures.push((name, texture));
+                continue;
+            }
+            page_width = std::cmp::max(page_width, x + width);
+            page_height = std::cmp::max(page_height, y + height);
+            page_textures.push((name, texture, (x, y)));
+            x += width + padding;
+            row_height = std::cmp::max(row_height, height);
+        }
+        check_unplaced_textures(&unplaced_textures, is_paged, page_textures.is_empty())?;
+
+        let mut page = image::RgbaImage::new(page_width, page_height);
+        let mut page_placements = Vec::new();
+        for (name, texture, position) in page_textures {
+            image::imageops::replace(&mut page, &texture.to_rgba(), position.0, position.1);
+            page_placements.push(Placement {
+                name,
+                position,
+                size: texture.dimensions(),
+                rotated: false,
+                trimmed: false,
+                trim_offset: (0, 0),
+            });
+        }
+        pages.push(DynamicImage::ImageRgba8(page));
+        placements.push(page_placements);
+        textures = unplaced_textures;
+    }
+    Ok((pages, placements))
+}
+
+fn pack_textures(
+    mut textures: Vec<(String, DynamicImage)>,
+    settings: &PackSettings,
+) -> Result<PackedSheet, Error> {
+    // A fixed atlas size is also the page size when frames overflow onto more pages
+    let atlas_size = settings.atlas_size;
+    let is_paged = settings.max_page_size.is_some();
+    let (max_width, max_height) = atlas_size
+        .or(settings.max_page_size)
+        .unwrap_or((4096, std::u32::MAX)); // TODO dynamic based on widest frame?
+    let config = TexturePackerConfig {
+        max_width,
+        max_height,
+        allow_rotation: false,
+        border_padding: 0, // TODO configurable?
+        texture_padding: settings.padding,
+        trim: false, // TODO support trimming?
+        texture_outlines: false,
+    };
+
+    let extrude = settings.extrude;
+    if extrude > 0 {
+        for (_, texture) in textures.iter_mut() {
+            *texture = extrude_texture(texture, extrude);
+        }
+    }
+
+    if is_paged {
+        let oversized_frames: Vec<&str> = textures
+            .iter()
+            .filter(|(_, t)| t.width() > max_width || t.height() > max_height)
+            .map(|(name, _)| name.as_str())
+            .collect();
+        if !oversized_frames.is_empty() {
+            return Err(PackError::FramesLargerThanPage(oversized_frames.join(", ")).into());
+        }
+    }
+
+    if settings.sort_frames_by_size {
+        sort_textures_by_size(&mut textures);
+    }
+
+    let max_size = (max_width, max_height);
+    let (mut pages, placements) = match settings.strategy {
+        PackStrategy::Skyline => pack_skyline(textures, config, is_paged)?,
+        PackStrategy::Shelf => pack_shelves(textures, max_size, settings.padding, is_paged)?,
+        PackStrategy::RowByHeight => {
+            sort_textures_by_height(&mut textures);
+            pack_shelves(textures, max_size, settings.padding, is_paged)?
+        }
+    };
+
+    // Pages share the same size so UVs can be computed against a single atlas size
+    let page_size = atlas_size.or_else(|| {
+        if pages.len() > 1 {
+            Some((
+                pages.iter().map(|p| p.width()).max().unwrap_or(0),
+                pages.iter().map(|p| p.height()).max().unwrap_or(0),
+            ))
+        } else {
+            None
+        }
+    });
+    if let Some((width, height)) = page_size {
+        for page in pages.iter_mut() {
+            if page.dimensions() == (width, height) {
+                continue;
+            }
+            let mut fixed_size_texture = image::RgbaImage::new(width, height);
+            image::imageops::replace(&mut fixed_size_texture, &page.to_rgba(), 0, 0);
+            *page = DynamicImage::ImageRgba8(fixed_size_texture);
+        }
+    }
+
+    let mut layout = HashMap::new();
+    for (page, page_placements) in placements.into_iter().enumerate() {
+        // Extruded pixels are not part of the frame
+        for p in page_placements {
+            layout.insert(
+                PathBuf::from(p.name),
+                PackedFrame {
+                    position_in_sheet: (p.position.0 + extrude, p.position.1 + extrude),
+                    size_in_sheet: (p.size.0 - 2 * extrude, p.size.1 - 2 * extrude),
+                    rotated: p.rotated,
+                    trimmed: p.trimmed,
+                    trim_offset: p.trim_offset,
+                    page: page as u32,
+                },
+            );
+        }
+    }
+
+    Ok(PackedSheet { pages, layout })
+}
+
+// Frame sizes for metadata-only exports, where no atlas is generated
+pub fn measure_sheet(sheet: &Sheet) -> Result<HashMap<PathBuf, PackedFrame>, Error> {
+    check_missing_images(sheet)?;
+    let mut images = ImageCache::new();
+    let mut layout = HashMap::new();
+    for frame in sheet.frames_iter() {
+        let source = frame.get_source();
+        layout.insert(
+            source.to_owned(),
+            PackedFrame {
+                position_in_sheet: (0, 0),
+                size_in_sheet: images.measure_frame(frame)?,
+                rotated: false,
+                trimmed: false,
+                trim_offset: (0, 0),
+                page: 0,
+            },
+        );
+    }
+    Ok(layout)
+}
+
+#[cfg(test)]
+fn used_height(packed_sheet: &PackedSheet) -> u32 {
+    packed_sheet
+        .get_layout()
+        .values()
+        .map(|f| f.position_in_sheet.1 + f.size_in_sheet.1)
+        .max()
+        .unwrap_or(0)
+}
+
+#[test]
+fn test_sorting_by_size_improves_density() {
+    let textures = vec![
+        ("small".to_owned(), DynamicImage::new_rgba8(8, 8)),
+        ("big_1".to_owned(), DynamicImage::new_rgba8(24, 24)),
+        ("big_2".to_owned(), DynamicImage::new_rgba8(24, 24)),
+    ];
+    let used_area: u32 = 8 * 8 + 2 * 24 * 24;
+
+    let mut settings = PackSettings {
+        atlas_size: Some((48, 48)),
+        max_page_size: None,
+        sort_frames_by_size: false,
+        padding: 0,
+        extrude: 0,
+        strategy: PackStrategy::Skyline,
+    };
+    let insertion_order = pack_textures(textures.clone(), &settings).unwrap();
+    settings.sort_frames_by_size = true;
+    let sorted = pack_textures(textures.clone(), &settings).unwrap();
+
+    let insertion_order_density = used_area as f32 / (48 * used_height(&insertion_order)) as f32;
+    let sorted_density = used_area as f32 / (48 * used_height(&sorted)) as f32;
+    assert_eq!(used_height(&insertion_order), 48);
+    assert_eq!(used_height(&sorted), 32);
+    assert!(sorted_density > insertion_order_density);
+
+    // Fits only when sorted
+    settings.atlas_size = Some((48, 32));
+    assert!(pack_textures(textures.clone(), &settings).is_ok());
+    settings.sort_frames_by_size = false;
+    assert!(pack_textures(textures, &settings).is_err());
+}
+
+#[test]
+fn test_extruded_frames_keep_their_rectangle() {
+    let mut texture = image::RgbaImage::new(4, 4);
+    texture.put_pixel(0, 0, image::Rgba([255, 0, 0, 255]));
+    let textures = vec![
+        ("a".to_owned(), DynamicImage::ImageRgba8(texture.clone())),
+        ("b".to_owned(), DynamicImage::ImageRgba8(texture)),
+    ];
+    let settings = PackSettings {
+        atlas_size: None,
+        max_page_size: None,
+        sort_frames_by_size: false,
+        padding: 1,
+        extrude: 2,
+        strategy: PackStrategy::Skyline,
+    };
+    let packed_sheet = pack_textures(textures, &settings).unwrap();
+    let page = packed_sheet.get_pages()[0].to_rgba();
+    for frame in packed_sheet.get_layout().values() {
+        assert_eq!(frame.size_in_sheet, (4, 4));
+        let (x, y) = frame.position_in_sheet;
+        assert!(x >= 2 && y >= 2);
+        assert_eq!(page.get_pixel(x, y).data[0], 255);
+        assert_eq!(page.get_pixel(x - 2, y - 2).data[0], 255);
+    }
+}
+
+#[test]
+fn test_pack_strategies_atlas_area() {
+    let textures: Vec<(String, DynamicImage)> = (0..40)
+        .map(|i| {
+            let size = (8 + (i * 7) % 25, 8 + (i * 13) % 29);
+            (i.to_string(), DynamicImage::new_rgba8(size.0, size.1))
+        })
+        .collect();
+    let used_area: u32 = textures.iter().map(|(_, t)| t.width() * t.height()).sum();
+
+    let atlas_area = |strategy| {
+        let settings = PackSettings {
+            atlas_size: None,
+            max_page_size: Some((256, 4096)),
+            sort_frames_by_size: false,
+            padding: 0,
+            extrude: 0,
+            strategy,
+        };
+        let packed_sheet = pack_textures(textures.clone(), &settings).unwrap();
+        assert_eq!(packed_sheet.get_layout().len(), textures.len());
+        let (width, height) = packed_sheet.get_size();
+        width * height
+    };
+
+    // Frames of varied sizes are where the strategies differ: packing them unsorted on shelves
+    // wastes the most space, grouping them by height or fitting them on a skyline much less
+    let skyline = atlas_area(PackStrategy::Skyline);
+    let shelf = atlas_area(PackStrategy::Shelf);
+    let row_by_height = atlas_area(PackStrategy::RowByHeight);
+    for area in &[skyline, shelf, row_by_height] {
+        assert!(*area >= used_area);
+    }
+    assert!(skyline <= shelf);
+    assert!(row_by_height <= shelf);
+    assert!(skyline < 2 * used_area);
+    assert!(row_by_height < used_area * 3 / 2);
+    assert!(shelf < 2 * used_area);
+}
+
+#[test]
+fn test_fixed_atlas_size_overflows_onto_pages() {
+    let textures = vec![
+        ("a".to_owned(), DynamicImage::new_rgba8(16, 16)),
+        ("b".to_owned(), DynamicImage::new_rgba8(16, 16)),
+        ("c".to_owned(), DynamicImage::new_rgba8(16, 16)),
+    ];
+    let mut settings = PackSettings {
+        atlas_size: Some((32, 16)),
+        max_page_size: None,
+        sort_frames_by_size: false,
+        padding: 0,
+        extrude: 0,
+        strategy: PackStrategy::Skyline,
+    };
+    assert!(pack_textures(textures.clone(), &settings).is_err());
+
+    settings.max_page_size = Some((4096, 4096));
+    let packed_sheet = pack_textures(textures, &settings).unwrap();
+    assert_eq!(packed_sheet.get_pages().len(), 2);
+    assert_eq!(packed_sheet.get_size(), (32, 16));
+    for page in packed_sheet.get_pages() {
+        assert_eq!(page.dimensions(), (32, 16));
+    }
+    let mut pages: Vec<u32> = packed_sheet.get_layout().values().map(|f| f.page).collect();
+    pages.sort();
+    assert_eq!(pages, vec![0, 0, 1]);
+}
+
+#[test]
+fn test_pack_stats_count_padding_as_wasted() {
+    let textures = vec![
+        ("a".to_owned(), DynamicImage::new_rgba8(8, 8)),
+        ("b".to_owned(), DynamicImage::new_rgba8(8, 8)),
+    ];
+    let settings = PackSettings {
+        atlas_size: Some((32, 16)),
+        max_page_size: None,
+        sort_frames_by_size: false,
+        padding: 2,
+        extrude: 0,
+        strategy: PackStrategy::Skyline,
+    };
+    let stats = pack_textures(textures, &settings).unwrap().get_stats();
+    assert_eq!(stats.page_size, (32, 16));
+    assert_eq!(stats.num_pages, 1);
+    assert_eq!(stats.used_area, 2 * 8 * 8);
+    assert_eq!(stats.total_area, 32 * 16);
+    assert_eq!(stats.get_wasted_area(), 32 * 16 - 2 * 8 * 8);
+}
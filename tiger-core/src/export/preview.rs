@@ -0,0 +1,108 @@
+use euclid::*;
+use failure::Error;
+use gif::{DisposalMethod, Encoder, Repeat, SetParameter};
+use image::imageops;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::export::{ExportError, ImageCache};
+use crate::sheet::{Animation, AnimationFrame, LoopMode, Sheet};
+
+struct PreviewFrame {
+    image: image::RgbaImage,
+    top_left: Vector2D<i32>,
+    duration: u32,
+}
+
+// Keyframes are composited onto a transparent canvas which covers all of them, so the
+// animation origin stays in place from one GIF frame to the next
+pub fn export_animation_gif<T: AsRef<Path>>(
+    sheet: &Sheet,
+    animation: &Animation,
+    destination: T,
+) -> Result<(), Error> {
+    let mut images = ImageCache::new();
+    let mut frames = Vec::new();
+    for animation_frame in animation.frames_iter() {
+        let frame = read_preview_frame(sheet, animation, animation_frame, &mut images)?;
+        frames.push(frame);
+    }
+
+    let bounds = frames
+        .iter()
+        .map(|f| {
+            let size = size2(f.image.width() as i32, f.image.height() as i32);
+            Rect::new(f.top_left.to_point(), size)
+        })
+        .fold(None, |bounds: Option<Rect<i32>>, r| {
+            Some(bounds.map_or(r, |b| b.union(&r)))
+        })
+        .ok_or(ExportError::EmptyAnimation)?;
+
+    // Ping-pong animations play their keyframes backward after playing them forward, the same
+    // way the workbench mirrors time over the second half of the period
+    let mut sequence: Vec<&PreviewFrame> = frames.iter().collect();
+    if animation.get_loop_mode() == LoopMode::PingPong {
+        sequence.extend(frames.iter().rev());
+    }
+
+    let (width, height) = (bounds.size.width as u16, bounds.size.height as u16);
+    let file = BufWriter::new(File::create(destination.as_ref())?);
+    let mut encoder = Encoder::new(file, width, height, &[])?;
+    if animation.get_loop_mode() != LoopMode::Once {
+        encoder.set(Repeat::Infinite)?;
+    }
+
+    let mut elapsed = 0;
+    for frame in sequence {
+        let mut canvas = image::RgbaImage::new(u32::from(width), u32::from(height));
+        let position = frame.top_left - bounds.origin.to_vector();
+        let (x, y) = (position.x as u32, position.y as u32);
+        imageops::overlay(&mut canvas, &frame.image, x, y);
+        let mut pixels = canvas.into_raw();
+        let mut gif_frame = gif::Frame::from_rgba(width, height, &mut pixels);
+
+        // GIF delays are in hundredths of a second. Rounding when each frame ends rather than
+        // its duration keeps the total length of the animation accurate.
+        let start = elapsed;
+        elapsed += frame.duration;
+        gif_frame.delay = ((elapsed + 5) / 10 - (start + 5) / 10) as u16;
+        gif_frame.dispose = DisposalMethod::Background;
+        encoder.write_frame(&gif_frame)?;
+    }
+
+    Ok(())
+}
+
+fn read_preview_frame(
+    sheet: &Sheet,
+    animation: &Animation,
+    animation_frame: &AnimationFrame,
+    images: &mut ImageCache,
+) -> Result<PreviewFrame, Error> {
+    let frame = sheet
+        .get_frame(animation_frame.get_frame())
+        .ok_or(ExportError::InvalidFrameReference)?;
+
+    let mut image = images.read_frame(frame)?;
+    if animation_frame.get_flip_x() {
+        image = image.fliph();
+    }
+    if animation_frame.get_flip_y() {
+        image = image.flipv();
+    }
+    let image = image.to_rgba();
+
+    // Frames are placed like in the workbench, with their anchor on the keyframe offset
+    let size = vec2(image.width(), image.height());
+    let anchor = animation_frame.flip_point(frame.get_anchor(), size);
+    let center = animation_frame.get_offset() - anchor;
+    let top_left = center - (size / 2).to_i32();
+
+    Ok(PreviewFrame {
+        image,
+        top_left,
+        duration: animation.get_frame_duration(animation_frame),
+    })
+}
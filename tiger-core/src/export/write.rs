@@ -0,0 +1,135 @@
+use failure::Error;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+
+use crate::export::*;
+use crate::sheet::Sheet;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ExportStep {
+    PackingFrames,
+    WritingTexture,
+    WritingMetadata,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExportProgress {
+    pub step: ExportStep,
+    pub frames_packed: u32,
+    pub num_frames: u32,
+}
+
+impl ExportProgress {
+    // Reading frames takes most of the export, writing files fills the end of the bar
+    pub fn get_completion(&self) -> f32 {
+        let packing = if self.num_frames > 0 {
+            self.frames_packed as f32 / self.num_frames as f32
+        } else {
+            1.0
+        };
+        match self.step {
+            ExportStep::PackingFrames => 0.8 * packing,
+            ExportStep::WritingTexture => 0.8,
+            ExportStep::WritingMetadata => 0.9,
+        }
+    }
+}
+
+pub type ExportCheckpoint<'a> = &'a dyn Fn(ExportProgress) -> Result<(), Error>;
+
+pub fn export_to_files(sheet: &Sheet) -> Result<(), Error> {
+    export_to_files_with_progress(sheet, &|_| Ok(()))
+}
+
+// Files are written next to their destination and only moved over it once the whole export
+// succeeded, so a failed or cancelled export leaves the previous one untouched. Moving the files
+// is not atomic: textures are moved first and metadata last, so a failure while moving them can
+// leave new textures next to the previous metadata, but never new metadata next to old textures.
+pub fn export_to_files_with_progress(
+    sheet: &Sheet,
+    checkpoint: ExportCheckpoint,
+) -> Result<(), Error> {
+    let mut written_files = Vec::new();
+    let result = write_export(sheet, checkpoint, &mut written_files).and_then(|_| {
+        for (temporary, destination) in &written_files {
+            std::fs::rename(temporary, destination)?;
+        }
+        Ok(())
+    });
+    if result.is_err() {
+        for (temporary, _) in written_files {
+            std::fs::remove_file(temporary).ok();
+        }
+    }
+    result
+}
+
+fn get_temporary_export_path(destination: &Path) -> PathBuf {
+    let mut file_name = destination
+        .file_name()
+        .map(|f| f.to_os_string())
+        .unwrap_or_default();
+    file_name.push(".tmp");
+    destination.with_file_name(file_name)
+}
+
+fn create_export_file(
+    destination: &Path,
+    written_files: &mut Vec<(PathBuf, PathBuf)>,
+) -> Result<File, Error> {
+    let temporary = get_temporary_export_path(destination);
+    written_files.push((temporary.clone(), destination.to_owned()));
+    Ok(File::create(temporary)?)
+}
+
+fn write_export(
+    sheet: &Sheet,
+    checkpoint: ExportCheckpoint,
+    written_files: &mut Vec<(PathBuf, PathBuf)>,
+) -> Result<(), Error> {
+    let export_settings = sheet
+        .get_export_settings()
+        .as_ref()
+        .ok_or(ExportError::MissingExportSettings)?;
+    let num_frames = sheet.frames_iter().count() as u32;
+    let progress = |step: ExportStep, frames_packed: u32| {
+        checkpoint(ExportProgress {
+            step,
+            frames_packed,
+            num_frames,
+        })
+    };
+
+    if export_settings.skip_texture {
+        let layout = measure_sheet(&sheet)?;
+        progress(ExportStep::WritingMetadata, num_frames)?;
+        let exported_data = export_sheet(&sheet, &export_settings, &layout, None)?;
+        let mut file = create_export_file(&export_settings.metadata_destination, written_files)?;
+        file.write_all(&exported_data.into_bytes())?;
+        return Ok(());
+    }
+
+    let on_frame_read = |n: u32| progress(ExportStep::PackingFrames, n);
+    let packed_sheet = pack_sheet_with_progress(&sheet, &export_settings.into(), &on_frame_read)?;
+    let exported_data = export_sheet(
+        &sheet,
+        &export_settings,
+        &packed_sheet.get_layout(),
+        Some(packed_sheet.get_size()),
+    )?;
+
+    let num_pages = packed_sheet.get_pages().len() as u32;
+    for (page, texture) in packed_sheet.get_pages().iter().enumerate() {
+        progress(ExportStep::WritingTexture, num_frames)?;
+        let destination = export_settings.get_texture_page_destination(page as u32, num_pages);
+        let mut file = create_export_file(&destination, written_files)?;
+        texture.write_to(&mut file, image::PNG)?;
+    }
+
+    progress(ExportStep::WritingMetadata, num_frames)?;
+    let mut file = create_export_file(&export_settings.metadata_destination, written_files)?;
+    file.write_all(&exported_data.into_bytes())?;
+
+    Ok(())
+}
@@ -0,0 +1,17 @@
+// Sheet data, texture packing and export, without any of the editor UI.
+//
+// A headless export goes through the same steps as exporting from the editor:
+//
+//     let sheet = tiger_core::sheet::Sheet::open("character.tiger")?;
+//     tiger_core::export::export_to_files(&sheet)?;
+//
+// `export::pack_sheet` and `export::export_sheet` expose the individual steps for tools
+// which want to handle the texture and metadata themselves.
+
+#[macro_use]
+extern crate failure;
+#[macro_use]
+extern crate serde_derive;
+
+pub mod export;
+pub mod sheet;
@@ -0,0 +1,130 @@
+use failure::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
+#[cfg(test)]
+use std::path::PathBuf;
+
+use crate::sheet::{self, Sheet};
+
+pub mod version1;
+pub mod version2;
+pub mod version3;
+
+// Sheet files are JSON documents of the form `{ "version": "Tiger3", "sheet": { ... } }`.
+// The layout of `sheet` for each version is described by the structs in the matching
+// `versionN` module, and each module converts the previous version into its own.
+// Changing the layout of a saved struct means adding a new version: copy the current module,
+// add a variant to `Version`, point `CURRENT_VERSION` at it and write the `From` conversions.
+#[derive(Serialize, Deserialize, PartialEq, Eq)]
+pub enum Version {
+    Tiger1,
+    Tiger2,
+    Tiger3,
+}
+const CURRENT_VERSION: Version = Version::Tiger3;
+
+impl Version {
+    fn number(&self) -> u32 {
+        match self {
+            Version::Tiger1 => 1,
+            Version::Tiger2 => 2,
+            Version::Tiger3 => 3,
+        }
+    }
+}
+
+#[derive(Fail, Debug)]
+pub enum CompatError {
+    #[fail(display = "File is not a Tiger sheet (no format version)")]
+    MissingVersion,
+    #[fail(
+        display = "Sheet was saved by a newer version of Tiger (format {}), update Tiger to open it",
+        _0
+    )]
+    NewerVersion(String),
+    #[fail(display = "Unknown sheet format version: {}", _0)]
+    UnknownVersion(String),
+}
+
+#[derive(Deserialize)]
+struct Versioned {
+    #[serde(default)]
+    version: Option<String>,
+}
+
+fn parse_version(name: String) -> Result<Version, CompatError> {
+    if let Ok(version) = serde_json::from_value(serde_json::Value::String(name.clone())) {
+        return Ok(version);
+    }
+    let is_newer = name.starts_with("Tiger")
+        && name["Tiger".len()..]
+            .parse::<u32>()
+            .map(|n| n > CURRENT_VERSION.number())
+            .unwrap_or(false);
+    if is_newer {
+        Err(CompatError::NewerVersion(name))
+    } else {
+        Err(CompatError::UnknownVersion(name))
+    }
+}
+
+#[derive(Serialize)]
+struct VersionedSheet<'a> {
+    version: Version,
+    sheet: &'a Sheet,
+}
+
+pub fn read_sheet<T: AsRef<Path>>(path: T) -> Result<Sheet, Error> {
+    let versioned: Versioned = serde_json::from_reader(BufReader::new(File::open(path.as_ref())?))?;
+    let version = parse_version(versioned.version.ok_or(CompatError::MissingVersion)?)?;
+    sheet::read_file(version, path)
+}
+
+pub fn write_sheet<T: AsRef<Path>>(path: T, sheet: &Sheet) -> Result<(), Error> {
+    let file = BufWriter::new(File::create(path.as_ref())?);
+    let versioned_sheet = VersionedSheet {
+        version: CURRENT_VERSION,
+        sheet: &sheet,
+    };
+    serde_json::to_writer_pretty(file, &versioned_sheet)?;
+    Ok(())
+}
+
+#[cfg(test)]
+fn fixture_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("test-data")
+        .join(name)
+}
+
+#[test]
+fn test_read_sheet_migrates_older_versions() {
+    let current = read_sheet(fixture_path("version3.tiger")).unwrap();
+    assert_eq!(read_sheet(fixture_path("version1.tiger")).unwrap(), current);
+    assert_eq!(read_sheet(fixture_path("version2.tiger")).unwrap(), current);
+}
+
+#[test]
+fn test_sheet_round_trip() {
+    for name in &["version1.tiger", "version2.tiger", "version3.tiger"] {
+        let sheet = read_sheet(fixture_path(name)).unwrap();
+        let path = std::env::temp_dir().join(format!("tiger-round-trip-{}", name));
+        write_sheet(&path, &sheet).unwrap();
+        let reloaded = read_sheet(&path);
+        std::fs::remove_file(&path).ok();
+        assert_eq!(reloaded.unwrap(), sheet);
+    }
+}
+
+#[test]
+fn test_read_sheet_rejects_newer_versions() {
+    let path = std::env::temp_dir().join("tiger-newer-version.tiger");
+    std::fs::write(&path, r#"{ "version": "Tiger99", "sheet": {} }"#).unwrap();
+    let result = read_sheet(&path);
+    std::fs::remove_file(&path).ok();
+    match result.unwrap_err().downcast::<CompatError>() {
+        Ok(CompatError::NewerVersion(v)) => assert_eq!(v, "Tiger99"),
+        _ => panic!("Expected a newer version error"),
+    }
+}
@@ -0,0 +1,281 @@
+use failure::Error;
+use serde::{Deserialize, Deserializer};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use crate::sheet::compat::version2 as previous_version;
+use crate::sheet::compat::Version;
+
+const THIS_VERSION: Version = Version::Tiger3;
+
+#[derive(Serialize, Deserialize)]
+pub struct VersionedSheet {
+    pub sheet: Sheet,
+}
+
+pub fn read_file<T: AsRef<Path>>(version: Version, path: T) -> Result<Sheet, Error> {
+    match version {
+        THIS_VERSION => {
+            let deserialized: VersionedSheet =
+                serde_json::from_reader(BufReader::new(File::open(path.as_ref())?))?;
+            Ok(deserialized.sheet)
+        }
+        _ => Ok(previous_version::read_file(version, path)?.into()),
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize, PartialEq)]
+pub struct Sheet {
+    pub frames: Vec<Frame>,
+    pub animations: Vec<Animation>,
+    pub export_settings: Option<ExportSettings>,
+}
+
+impl From<previous_version::Sheet> for Sheet {
+    fn from(old: previous_version::Sheet) -> Sheet {
+        Sheet {
+            frames: old.frames.into_iter().map(|o| o.into()).collect(),
+            animations: old.animations.into_iter().map(|o| o.into()).collect(),
+            export_settings: old.export_settings.map(|o| o.into()),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum LoopMode {
+    Once,
+    Loop,
+    PingPong,
+}
+
+// Sheets saved before loop modes existed store a boolean under `is_looping`
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LegacyLoopMode {
+    IsLooping(bool),
+    LoopMode(LoopMode),
+}
+
+fn deserialize_loop_mode<'de, D: Deserializer<'de>>(deserializer: D) -> Result<LoopMode, D::Error> {
+    Ok(match LegacyLoopMode::deserialize(deserializer)? {
+        LegacyLoopMode::IsLooping(true) => LoopMode::Loop,
+        LegacyLoopMode::IsLooping(false) => LoopMode::Once,
+        LegacyLoopMode::LoopMode(m) => m,
+    })
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Animation {
+    pub name: String,
+    pub timeline: Vec<AnimationFrame>,
+    #[serde(alias = "is_looping", deserialize_with = "deserialize_loop_mode")]
+    pub loop_mode: LoopMode,
+    #[serde(default)]
+    pub group: Option<String>,
+    #[serde(default)]
+    pub direction: Option<String>,
+    #[serde(default)]
+    pub frame_rate: Option<f32>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl From<previous_version::Animation> for Animation {
+    fn from(old: previous_version::Animation) -> Animation {
+        Animation {
+            name: old.name,
+            timeline: old.timeline.into_iter().map(|o| o.into()).collect(),
+            loop_mode: if old.is_looping {
+                LoopMode::Loop
+            } else {
+                LoopMode::Once
+            },
+            group: None,
+            direction: None,
+            frame_rate: None,
+            tags: vec![],
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Frame {
+    pub source: PathBuf,
+    pub hitboxes: Vec<Hitbox>,
+    #[serde(default)]
+    pub anchor: (i32, i32), // Relative to the center of the frame
+    #[serde(default)]
+    pub source_rect: Option<Rectangle>, // Set for frames sliced out of a larger image
+}
+
+impl From<previous_version::Frame> for Frame {
+    fn from(old: previous_version::Frame) -> Frame {
+        Frame {
+            source: old.source,
+            hitboxes: old.hitboxes.into_iter().map(|o| o.into()).collect(),
+            anchor: (0, 0),
+            source_rect: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct AnimationFrame {
+    pub frame: PathBuf,
+    pub duration: u32, // in ms
+    pub offset: (i32, i32),
+    #[serde(default)]
+    pub sound: Option<String>,
+    #[serde(default)]
+    pub flip_x: bool,
+    #[serde(default)]
+    pub flip_y: bool,
+    #[serde(default)]
+    pub user_data: Option<String>,
+}
+
+impl From<previous_version::AnimationFrame> for AnimationFrame {
+    fn from(old: previous_version::AnimationFrame) -> AnimationFrame {
+        AnimationFrame {
+            frame: old.frame,
+            duration: old.duration,
+            offset: old.offset,
+            sound: None,
+            flip_x: false,
+            flip_y: false,
+            user_data: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Hitbox {
+    pub name: String,
+    pub geometry: Shape,
+}
+
+impl From<previous_version::Hitbox> for Hitbox {
+    fn from(old: previous_version::Hitbox) -> Hitbox {
+        Hitbox {
+            name: old.name,
+            geometry: old.geometry.into(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Shape {
+    Rectangle(Rectangle),
+    Circle(Circle),
+}
+
+impl From<previous_version::Shape> for Shape {
+    fn from(old: previous_version::Shape) -> Shape {
+        match old {
+            previous_version::Shape::Rectangle(r) => Shape::Rectangle(r.into()),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Rectangle {
+    pub top_left: (i32, i32),
+    pub size: (u32, u32),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Circle {
+    pub center: (i32, i32),
+    pub radius: u32,
+}
+
+impl From<previous_version::Rectangle> for Rectangle {
+    fn from(old: previous_version::Rectangle) -> Rectangle {
+        Rectangle {
+            top_left: old.top_left,
+            size: old.size,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub enum ExportFormat {
+    Template(PathBuf),
+    Json,
+}
+
+impl From<previous_version::ExportFormat> for ExportFormat {
+    fn from(old: previous_version::ExportFormat) -> ExportFormat {
+        match old {
+            previous_version::ExportFormat::Template(p) => ExportFormat::Template(p),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum PackStrategy {
+    // Also stands in for MaxRects packing, which texture_packer does not provide. Skyline
+    // packing is nearly as tight on sprite sheets and keeps every strategy on maintained code.
+    Skyline,
+    Shelf,
+    RowByHeight,
+}
+
+impl Default for PackStrategy {
+    fn default() -> PackStrategy {
+        PackStrategy::Skyline
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct TemplateVariable {
+    pub name: String,
+    pub value: String,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ExportSettings {
+    pub format: ExportFormat,
+    pub texture_destination: PathBuf,
+    pub metadata_destination: PathBuf,
+    pub metadata_paths_root: PathBuf,
+    #[serde(default)]
+    pub skip_texture: bool,
+    #[serde(default)]
+    pub atlas_size: Option<(u32, u32)>,
+    #[serde(default)]
+    pub max_size: Option<(u32, u32)>,
+    #[serde(default)]
+    pub sort_frames_by_size: bool,
+    #[serde(default)]
+    pub padding: u32,
+    #[serde(default)]
+    pub extrude: u32,
+    #[serde(default)]
+    pub pack_strategy: PackStrategy,
+    #[serde(default)]
+    pub template_variables: Vec<TemplateVariable>,
+    #[serde(default)]
+    pub absolute_image_paths: bool,
+}
+
+impl From<previous_version::ExportSettings> for ExportSettings {
+    fn from(old: previous_version::ExportSettings) -> ExportSettings {
+        ExportSettings {
+            format: old.format.into(),
+            texture_destination: old.texture_destination,
+            metadata_destination: old.metadata_destination,
+            metadata_paths_root: old.metadata_paths_root,
+            skip_texture: false,
+            atlas_size: None,
+            max_size: None,
+            sort_frames_by_size: false,
+            padding: 0,
+            extrude: 0,
+            pack_strategy: PackStrategy::Skyline,
+            template_variables: Vec::new(),
+            absolute_image_paths: false,
+        }
+    }
+}
@@ -0,0 +1,1417 @@
+use core::cmp::Ordering;
+use dunce::canonicalize;
+use euclid::*;
+use failure::Error;
+use pathdiff::diff_paths;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+pub use self::compat::version3::*;
+use self::constants::*;
+
+pub mod compat;
+
+pub mod constants {
+    pub const MAX_ANIMATION_NAME_LENGTH: usize = 32;
+    pub const MAX_ANIMATION_GROUP_NAME_LENGTH: usize = 64;
+    pub const MAX_ANIMATION_DIRECTION_LENGTH: usize = 16;
+    pub const MAX_ANIMATION_TAG_LENGTH: usize = 32;
+    pub const MAX_HITBOX_NAME_LENGTH: usize = 32;
+    pub const MAX_SOUND_NAME_LENGTH: usize = 64;
+    pub const MAX_USER_DATA_LENGTH: usize = 256;
+    pub const MAX_TEMPLATE_VARIABLE_NAME_LENGTH: usize = 32;
+    pub const MAX_TEMPLATE_VARIABLE_VALUE_LENGTH: usize = 256;
+}
+
+// Hitboxes draw and export in one of these colors, derived from their name
+const HITBOX_PALETTE: [(u8, u8, u8); 8] = [
+    (255, 255, 255),
+    (255, 89, 94),
+    (255, 202, 58),
+    (138, 201, 38),
+    (25, 130, 196),
+    (106, 76, 147),
+    (255, 146, 76),
+    (82, 215, 199),
+];
+
+#[derive(Fail, Debug)]
+pub enum SheetError {
+    #[fail(display = "Animation was not found")]
+    AnimationNotFound,
+    #[fail(display = "Hitbox was not found")]
+    HitboxNotFound,
+    #[fail(display = "Animation name too long")]
+    AnimationNameTooLong,
+    #[fail(display = "Animation group name too long")]
+    AnimationGroupNameTooLong,
+    #[fail(display = "Animation direction too long")]
+    AnimationDirectionTooLong,
+    #[fail(display = "Animation tag too long")]
+    AnimationTagTooLong,
+    #[fail(display = "Hitbox name too long")]
+    HitboxNameTooLong,
+    #[fail(display = "Sound name too long")]
+    SoundNameTooLong,
+    #[fail(display = "User data too long")]
+    UserDataTooLong,
+    #[fail(display = "Invalid frame index")]
+    InvalidFrameIndex,
+    #[fail(display = "Invalid tile size")]
+    InvalidTileSize,
+}
+
+#[derive(Fail, Debug, PartialEq)]
+pub enum ValidationError {
+    #[fail(display = "Animation `{}` uses a frame which is not in the sheet", _0)]
+    DanglingFrameReference(String),
+    #[fail(display = "Frame `{}` has more than one hitbox named `{}`", _0, _1)]
+    DuplicateHitboxName(String, String),
+    #[fail(display = "Animation `{}` has a frame with a duration of zero", _0)]
+    InvalidFrameDuration(String),
+}
+
+impl Sheet {
+    // Reads a sheet file of any version, with paths resolved against its directory
+    pub fn open<T: AsRef<Path>>(path: T) -> Result<Sheet, Error> {
+        let mut directory = path.as_ref().to_owned();
+        directory.pop();
+        let sheet: Sheet = compat::read_sheet(path.as_ref())?;
+        sheet.with_absolute_paths(&directory)
+    }
+
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        for frame in self.frames_iter() {
+            for (index, hitbox) in frame.hitboxes_iter().enumerate() {
+                if frame.hitboxes[..index]
+                    .iter()
+                    .any(|h| h.name == hitbox.name)
+                {
+                    return Err(ValidationError::DuplicateHitboxName(
+                        frame.source.to_string_lossy().into_owned(),
+                        hitbox.name.clone(),
+                    ));
+                }
+            }
+        }
+        for animation in self.animations_iter() {
+            for animation_frame in animation.frames_iter() {
+                if !self.has_frame(&animation_frame.frame) {
+                    return Err(ValidationError::DanglingFrameReference(
+                        animation.name.clone(),
+                    ));
+                }
+                if animation_frame.duration == 0 {
+                    return Err(ValidationError::InvalidFrameDuration(
+                        animation.name.clone(),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn with_relative_paths<T: AsRef<Path>>(&self, relative_to: T) -> Result<Sheet, Error> {
+        let mut sheet = self.clone();
+        for frame in sheet.frames_iter_mut() {
+            frame.source = relative_path_or_absolute(&frame.source, &relative_to);
+        }
+        for animation in sheet.animations.iter_mut() {
+            for animation_frame in animation.frames_iter_mut() {
+                animation_frame.frame =
+                    relative_path_or_absolute(&animation_frame.frame, &relative_to);
+            }
+        }
+        if let Some(e) = sheet.export_settings {
+            sheet.export_settings = e.with_relative_paths(relative_to).ok();
+        }
+        Ok(sheet)
+    }
+
+    pub fn with_absolute_paths<T: AsRef<Path>>(&self, relative_to: T) -> Result<Sheet, Error> {
+        let mut sheet = self.clone();
+        for frame in sheet.frames_iter_mut() {
+            frame.source = absolute_frame_path(relative_to.as_ref().join(&frame.source))?;
+        }
+        for animation in sheet.animations.iter_mut() {
+            for animation_frame in animation.frames_iter_mut() {
+                animation_frame.frame =
+                    absolute_frame_path(relative_to.as_ref().join(&&animation_frame.frame))?;
+            }
+        }
+        if let Some(e) = sheet.export_settings {
+            sheet.export_settings = Some(e.with_absolute_paths(relative_to)?);
+        }
+        Ok(sheet)
+    }
+
+    // Keeps animation names and settings along with export settings, but no frames
+    pub fn to_template(&self) -> Sheet {
+        let mut sheet = self.clone();
+        sheet.frames.clear();
+        for animation in sheet.animations.iter_mut() {
+            animation.timeline.clear();
+        }
+        sheet
+    }
+
+    pub fn frames_iter(&self) -> std::slice::Iter<'_, Frame> {
+        self.frames.iter()
+    }
+
+    pub fn frames_iter_mut(&mut self) -> std::slice::IterMut<'_, Frame> {
+        self.frames.iter_mut()
+    }
+
+    pub fn animations_iter(&self) -> std::slice::Iter<'_, Animation> {
+        self.animations.iter()
+    }
+
+    pub fn animations_iter_mut(&mut self) -> std::slice::IterMut<'_, Animation> {
+        self.animations.iter_mut()
+    }
+
+    pub fn has_frame<T: AsRef<Path>>(&self, path: T) -> bool {
+        self.frames.iter().any(|f| f.source == path.as_ref())
+    }
+
+    pub fn has_animation<T: AsRef<str>>(&self, name: T) -> bool {
+        self.animations.iter().any(|a| a.name == name.as_ref())
+    }
+
+    pub fn add_frame<T: AsRef<Path>>(&mut self, path: T) {
+        if self.has_frame(&path) {
+            return;
+        }
+        let frame = Frame::new(path);
+        self.frames.push(frame);
+    }
+
+    // Cells are read left to right, top to bottom. Frames are named after the image they are
+    // sliced from: `walk.png#0`, `walk.png#1`...
+    pub fn add_sliced_frames<T: AsRef<Path>>(
+        &mut self,
+        image: T,
+        image_size: Vector2D<u32>,
+        tile_size: Vector2D<u32>,
+    ) -> Result<(), Error> {
+        if tile_size.x == 0 || tile_size.y == 0 {
+            return Err(SheetError::InvalidTileSize.into());
+        }
+        let num_columns = image_size.x / tile_size.x;
+        let num_rows = image_size.y / tile_size.y;
+        for row in 0..num_rows {
+            for column in 0..num_columns {
+                let index = row * num_columns + column;
+                let path = get_slice_path(&image, index);
+                if self.has_frame(&path) {
+                    continue;
+                }
+                let mut frame = Frame::new(path);
+                frame.source_rect = Some(Rectangle {
+                    top_left: ((column * tile_size.x) as i32, (row * tile_size.y) as i32),
+                    size: tile_size.to_tuple(),
+                });
+                self.frames.push(frame);
+            }
+        }
+        Ok(())
+    }
+
+    // Images which frames are read from but can no longer be found on disk
+    pub fn get_missing_images(&self) -> Vec<PathBuf> {
+        let mut images = vec![];
+        for image in self.frames.iter().map(Frame::get_image) {
+            if !image.exists() && !images.contains(&image) {
+                images.push(image);
+            }
+        }
+        images
+    }
+
+    // Missing images are matched by file name against `candidates`. Frame references in
+    // animations follow the frames they point to. Returns the images which found no match.
+    pub fn relink_frames(&mut self, candidates: &[PathBuf]) -> Vec<PathBuf> {
+        let mut unmatched = vec![];
+        let mut relinked = HashMap::new();
+        for index in 0..self.frames.len() {
+            let image = self.frames[index].get_image();
+            if image.exists() {
+                continue;
+            }
+            let candidate = candidates
+                .iter()
+                .find(|c| image.file_name().is_some() && c.file_name() == image.file_name());
+            let source = candidate.map(|c| relinked_frame_source(&self.frames[index], c));
+            match source {
+                Some(s) if !self.has_frame(&s) => {
+                    relinked.insert(self.frames[index].source.clone(), s.clone());
+                    self.frames[index].source = s;
+                }
+                _ => {
+                    if !unmatched.contains(&image) {
+                        unmatched.push(image);
+                    }
+                }
+            }
+        }
+        for animation in self.animations.iter_mut() {
+            for animation_frame in animation.timeline.iter_mut() {
+                if let Some(source) = relinked.get(&animation_frame.frame) {
+                    animation_frame.frame = source.clone();
+                }
+            }
+        }
+        unmatched
+    }
+
+    pub fn add_animation(&mut self, is_looping: bool) -> &mut Animation {
+        let name = unique_name("New Animation", |n| self.has_animation(n));
+        let mut animation = Animation::new(&name);
+        animation.set_loop_mode(if is_looping {
+            LoopMode::Loop
+        } else {
+            LoopMode::Once
+        });
+        self.animations.push(animation);
+        self.animations.last_mut().unwrap()
+    }
+
+    // Renames the animation if its name is already taken
+    pub fn insert_animation(&mut self, mut animation: Animation) -> &mut Animation {
+        animation.name = unique_name(&animation.name, |n| self.has_animation(n));
+        for animation_frame in animation.frames_iter() {
+            self.add_frame(animation_frame.get_frame());
+        }
+        self.animations.push(animation);
+        self.animations.last_mut().unwrap()
+    }
+
+    // Hitboxes are merged into the existing frame if there is one
+    pub fn insert_frame(&mut self, frame: Frame) {
+        match self.get_frame_mut(&frame.source) {
+            Some(existing_frame) => {
+                for hitbox in frame.hitboxes {
+                    existing_frame.insert_hitbox(hitbox);
+                }
+            }
+            None => self.frames.push(frame),
+        }
+    }
+
+    pub fn get_frame<T: AsRef<Path>>(&self, path: T) -> Option<&Frame> {
+        self.frames.iter().find(|f| f.source == path.as_ref())
+    }
+
+    pub fn get_frame_mut<T: AsRef<Path>>(&mut self, path: T) -> Option<&mut Frame> {
+        self.frames.iter_mut().find(|f| f.source == path.as_ref())
+    }
+
+    pub fn get_animation<T: AsRef<str>>(&self, name: T) -> Option<&Animation> {
+        self.animations.iter().find(|a| a.name == name.as_ref())
+    }
+
+    pub fn get_animation_mut<T: AsRef<str>>(&mut self, name: T) -> Option<&mut Animation> {
+        self.animations.iter_mut().find(|a| a.name == name.as_ref())
+    }
+
+    pub fn get_export_settings(&self) -> &Option<ExportSettings> {
+        &self.export_settings
+    }
+
+    pub fn set_export_settings(&mut self, export_settings: ExportSettings) {
+        self.export_settings = Some(export_settings);
+    }
+
+    pub fn rename_animation<T: AsRef<str>, U: AsRef<str>>(
+        &mut self,
+        old_name: T,
+        new_name: U,
+    ) -> Result<(), Error> {
+        if new_name.as_ref().len() > MAX_ANIMATION_NAME_LENGTH {
+            return Err(SheetError::AnimationNameTooLong.into());
+        }
+        let animation = self
+            .get_animation_mut(old_name)
+            .ok_or(SheetError::AnimationNotFound)?;
+        animation.name = new_name.as_ref().to_owned();
+        Ok(())
+    }
+
+    pub fn set_animation_group<T: AsRef<str>>(
+        &mut self,
+        name: T,
+        group: Option<String>,
+    ) -> Result<(), Error> {
+        if let Some(g) = &group {
+            if g.len() > MAX_ANIMATION_GROUP_NAME_LENGTH {
+                return Err(SheetError::AnimationGroupNameTooLong.into());
+            }
+        }
+        let animation = self
+            .get_animation_mut(name)
+            .ok_or(SheetError::AnimationNotFound)?;
+        animation.group = group;
+        Ok(())
+    }
+
+    pub fn set_animation_direction<T: AsRef<str>>(
+        &mut self,
+        name: T,
+        direction: Option<String>,
+    ) -> Result<(), Error> {
+        if let Some(d) = &direction {
+            if d.len() > MAX_ANIMATION_DIRECTION_LENGTH {
+                return Err(SheetError::AnimationDirectionTooLong.into());
+            }
+        }
+        let animation = self
+            .get_animation_mut(name)
+            .ok_or(SheetError::AnimationNotFound)?;
+        animation.direction = direction;
+        Ok(())
+    }
+
+    pub fn add_animation_tag<T: AsRef<str>>(&mut self, name: T, tag: String) -> Result<(), Error> {
+        if tag.len() > MAX_ANIMATION_TAG_LENGTH {
+            return Err(SheetError::AnimationTagTooLong.into());
+        }
+        let animation = self
+            .get_animation_mut(name)
+            .ok_or(SheetError::AnimationNotFound)?;
+        if !animation.has_tag(&tag) {
+            animation.tags.push(tag);
+        }
+        Ok(())
+    }
+
+    pub fn remove_animation_tag<T: AsRef<str>, U: AsRef<str>>(
+        &mut self,
+        name: T,
+        tag: U,
+    ) -> Result<(), Error> {
+        let animation = self
+            .get_animation_mut(name)
+            .ok_or(SheetError::AnimationNotFound)?;
+        animation.tags.retain(|t| t != tag.as_ref());
+        Ok(())
+    }
+
+    pub fn delete_frame<T: AsRef<Path>>(&mut self, path: T) {
+        self.frames.retain(|f| f.source != path.as_ref());
+        for animation in self.animations.iter_mut() {
+            animation.timeline.retain(|af| af.frame != path.as_ref())
+        }
+    }
+
+    pub fn delete_hitbox<T: AsRef<Path>, U: AsRef<str>>(&mut self, path: T, name: U) {
+        if let Some(frame) = self.get_frame_mut(path.as_ref()) {
+            frame.hitboxes.retain(|h| h.name != name.as_ref());
+        }
+    }
+
+    pub fn delete_animation<T: AsRef<str>>(&mut self, name: T) {
+        self.animations.retain(|a| a.name != name.as_ref());
+    }
+
+    pub fn delete_animation_frame<T: AsRef<str>>(&mut self, animation_name: T, frame_index: usize) {
+        if let Some(animation) = self.get_animation_mut(animation_name) {
+            if frame_index < animation.timeline.len() {
+                animation.timeline.remove(frame_index);
+            }
+        }
+    }
+}
+
+impl Animation {
+    pub fn new<T: AsRef<str>>(name: T) -> Animation {
+        Animation {
+            name: name.as_ref().to_owned(),
+            timeline: vec![],
+            loop_mode: LoopMode::Loop,
+            group: None,
+            direction: None,
+            frame_rate: None,
+            tags: vec![],
+        }
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_group(&self) -> Option<&str> {
+        self.group.as_ref().map(|g| g.as_str())
+    }
+
+    // Groups nest like folders, `enemy/boss/` is the `boss` group within the `enemy` group
+    pub fn get_group_path(&self) -> Vec<&str> {
+        let group = self.get_group().unwrap_or("");
+        group
+            .split('/')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .collect()
+    }
+
+    pub fn get_direction(&self) -> Option<&str> {
+        self.direction.as_ref().map(|d| d.as_str())
+    }
+
+    pub fn tags_iter(&self) -> std::slice::Iter<'_, String> {
+        self.tags.iter()
+    }
+
+    pub fn has_tag<T: AsRef<str>>(&self, tag: T) -> bool {
+        self.tags.iter().any(|t| t == tag.as_ref())
+    }
+
+    pub fn get_num_frames(&self) -> usize {
+        self.timeline.len()
+    }
+
+    pub fn get_loop_mode(&self) -> LoopMode {
+        self.loop_mode
+    }
+
+    pub fn set_loop_mode(&mut self, new_loop_mode: LoopMode) {
+        self.loop_mode = new_loop_mode;
+    }
+
+    // True for ping-pong animations too
+    pub fn is_looping(&self) -> bool {
+        self.loop_mode != LoopMode::Once
+    }
+
+    pub fn get_frame_rate(&self) -> Option<f32> {
+        self.frame_rate
+    }
+
+    pub fn set_frame_rate(&mut self, frame_rate: Option<f32>) {
+        self.frame_rate = frame_rate.map(|r| r.max(1.0));
+    }
+
+    // Keyframe durations are ignored when the animation has a frame rate
+    pub fn get_frame_duration(&self, animation_frame: &AnimationFrame) -> u32 {
+        match self.frame_rate {
+            Some(r) if r > 0.0 => std::cmp::max(1, (1000.0 / r).round() as u32),
+            _ => animation_frame.duration,
+        }
+    }
+
+    // Same keyframes and settings, regardless of name
+    pub fn has_same_content(&self, other: &Animation) -> bool {
+        self.timeline == other.timeline
+            && self.loop_mode == other.loop_mode
+            && self.group == other.group
+            && self.direction == other.direction
+            && self.frame_rate == other.frame_rate
+            && self.tags == other.tags
+    }
+
+    pub fn get_duration(&self) -> Option<u32> {
+        if self.timeline.is_empty() {
+            return None;
+        }
+        let frame_durations = self.timeline.iter().map(|f| self.get_frame_duration(f));
+        Some(frame_durations.sum())
+    }
+
+    pub fn get_frame(&self, index: usize) -> Option<&AnimationFrame> {
+        if index >= self.timeline.len() {
+            return None;
+        }
+        Some(&self.timeline[index])
+    }
+
+    pub fn get_frame_mut(&mut self, index: usize) -> Option<&mut AnimationFrame> {
+        if index >= self.timeline.len() {
+            return None;
+        }
+        Some(&mut self.timeline[index])
+    }
+
+    pub fn get_frame_at(&self, time: Duration) -> Option<(usize, &AnimationFrame)> {
+        let duration = match self.get_duration() {
+            None => return None,
+            Some(0) => return None,
+            Some(d) => d,
+        };
+        let time_ms = time.as_millis() as u64;
+        let duration = u64::from(duration);
+        let time = match self.loop_mode {
+            LoopMode::Once => time,
+            LoopMode::Loop => Duration::from_millis(time_ms % duration),
+            // Plays forward during the first half of the period, backward during the second
+            LoopMode::PingPong => {
+                let period_time = time_ms % (2 * duration);
+                if period_time < duration {
+                    Duration::from_millis(period_time)
+                } else {
+                    Duration::from_millis(2 * duration - period_time)
+                }
+            }
+        };
+        let mut cursor = Duration::new(0, 0);
+        for (index, frame) in self.timeline.iter().enumerate() {
+            cursor += Duration::from_millis(u64::from(self.get_frame_duration(frame)));
+            if time < cursor {
+                return Some((index, frame));
+            }
+        }
+        Some((
+            self.timeline.len() - 1,
+            self.timeline.iter().last().unwrap(),
+        )) // TODO no unwrap
+    }
+
+    pub fn get_frame_times(&self) -> Vec<u64> {
+        let mut cursor = 0;
+        self.frames_iter()
+            .map(|f| {
+                let t = cursor;
+                cursor += u64::from(self.get_frame_duration(f));
+                t
+            })
+            .collect()
+    }
+
+    pub fn insert_frame<T: AsRef<Path>>(&mut self, frame: T, index: usize) -> Result<(), Error> {
+        // TODO validate that frame exists in sheet!
+        if index > self.timeline.len() {
+            return Err(SheetError::InvalidFrameIndex.into());
+        }
+        let animation_frame = AnimationFrame::new(frame);
+        self.timeline.insert(index, animation_frame);
+        Ok(())
+    }
+
+    pub fn insert_animation_frame(
+        &mut self,
+        animation_frame: AnimationFrame,
+        index: usize,
+    ) -> Result<(), Error> {
+        if index > self.timeline.len() {
+            return Err(SheetError::InvalidFrameIndex.into());
+        }
+        self.timeline.insert(index, animation_frame);
+        Ok(())
+    }
+
+    pub fn reorder_frame(&mut self, old_index: usize, new_index: usize) -> Result<(), Error> {
+        if old_index >= self.timeline.len() || new_index > self.timeline.len() {
+            return Err(SheetError::InvalidFrameIndex.into());
+        }
+        let moving_element = self.timeline.remove(old_index);
+        let adjusted_index = if new_index > old_index {
+            new_index - 1
+        } else {
+            new_index
+        };
+        self.timeline.insert(adjusted_index, moving_element);
+        Ok(())
+    }
+
+    pub fn reverse(&mut self) {
+        self.timeline.reverse();
+    }
+
+    pub fn frames_iter(&self) -> std::slice::Iter<'_, AnimationFrame> {
+        self.timeline.iter()
+    }
+
+    pub fn frames_iter_mut(&mut self) -> std::slice::IterMut<'_, AnimationFrame> {
+        self.timeline.iter_mut()
+    }
+}
+
+impl Ord for Animation {
+    fn cmp(&self, other: &Animation) -> Ordering {
+        self.group
+            .cmp(&other.group)
+            .then_with(|| self.name.cmp(&other.name))
+    }
+}
+
+impl PartialOrd for Animation {
+    fn partial_cmp(&self, other: &Animation) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Frame {
+    pub fn new<T: AsRef<Path>>(path: T) -> Frame {
+        Frame {
+            source: path.as_ref().to_owned(),
+            hitboxes: vec![],
+            anchor: (0, 0),
+            source_rect: None,
+        }
+    }
+
+    // Image file containing the frame, which is not the frame source for sliced frames
+    pub fn get_image(&self) -> PathBuf {
+        match self.source_rect {
+            Some(_) => split_slice_path(&self.source)
+                .map(|(image, _)| image)
+                .unwrap_or_else(|| self.source.clone()),
+            None => self.source.clone(),
+        }
+    }
+
+    pub fn get_source_rect(&self) -> Option<Rect<u32>> {
+        self.source_rect.as_ref().map(|r| {
+            rect(
+                r.top_left.0.max(0) as u32,
+                r.top_left.1.max(0) as u32,
+                r.size.0,
+                r.size.1,
+            )
+        })
+    }
+
+    pub fn get_source(&self) -> &Path {
+        &self.source
+    }
+
+    pub fn get_anchor(&self) -> Vector2D<i32> {
+        self.anchor.into()
+    }
+
+    pub fn set_anchor(&mut self, new_anchor: Vector2D<i32>) {
+        self.anchor = new_anchor.to_tuple();
+    }
+
+    pub fn hitboxes_iter(&self) -> std::slice::Iter<'_, Hitbox> {
+        self.hitboxes.iter()
+    }
+
+    pub fn get_hitbox<T: AsRef<str>>(&self, name: T) -> Option<&Hitbox> {
+        self.hitboxes.iter().find(|a| a.name == name.as_ref())
+    }
+
+    pub fn get_hitbox_mut<T: AsRef<str>>(&mut self, name: T) -> Option<&mut Hitbox> {
+        self.hitboxes.iter_mut().find(|a| a.name == name.as_ref())
+    }
+
+    pub fn has_hitbox<T: AsRef<str>>(&self, name: T) -> bool {
+        self.hitboxes.iter().any(|a| a.name == name.as_ref())
+    }
+
+    pub fn add_hitbox(&mut self) -> &mut Hitbox {
+        let name = unique_name("New Hitbox", |n| self.has_hitbox(n));
+        self.hitboxes.push(Hitbox {
+            name,
+            geometry: Shape::Rectangle(Rectangle {
+                top_left: (0, 0),
+                size: (0, 0),
+            }),
+        });
+        self.hitboxes.last_mut().unwrap() // TODO no unwrap?
+    }
+
+    // Renames the hitbox if its name is already taken
+    pub fn insert_hitbox(&mut self, mut hitbox: Hitbox) -> &mut Hitbox {
+        hitbox.name = unique_name(&hitbox.name, |n| self.has_hitbox(n));
+        self.hitboxes.push(hitbox);
+        self.hitboxes.last_mut().unwrap()
+    }
+
+    pub fn rename_hitbox<T: AsRef<str>, U: AsRef<str>>(
+        &mut self,
+        old_name: T,
+        new_name: U,
+    ) -> Result<(), Error> {
+        if new_name.as_ref().len() > MAX_HITBOX_NAME_LENGTH {
+            return Err(SheetError::HitboxNameTooLong.into());
+        }
+        let hitbox = self
+            .get_hitbox_mut(old_name)
+            .ok_or(SheetError::HitboxNotFound)?;
+        hitbox.name = new_name.as_ref().to_owned();
+        Ok(())
+    }
+}
+
+impl Ord for Frame {
+    fn cmp(&self, other: &Frame) -> Ordering {
+        self.source
+            .to_string_lossy()
+            .cmp(&other.source.to_string_lossy())
+    }
+}
+
+impl PartialOrd for Frame {
+    fn partial_cmp(&self, other: &Frame) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Hitbox {
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_shape(&self) -> &Shape {
+        &self.geometry
+    }
+
+    pub fn is_circle(&self) -> bool {
+        match &self.geometry {
+            Shape::Rectangle(_) => false,
+            Shape::Circle(_) => true,
+        }
+    }
+
+    // Circles keep the top-left corner of their bounding box in place
+    pub fn set_is_circle(&mut self, is_circle: bool) {
+        if is_circle == self.is_circle() {
+            return;
+        }
+        let position = self.get_position();
+        let size = self.get_size();
+        self.geometry = if is_circle {
+            let radius = std::cmp::min(size.x, size.y) / 2;
+            Shape::Circle(Circle {
+                center: (position.x + radius as i32, position.y + radius as i32),
+                radius,
+            })
+        } else {
+            Shape::Rectangle(Rectangle {
+                top_left: position.to_tuple(),
+                size: size.to_tuple(),
+            })
+        };
+    }
+
+    // Bounding box for circles
+    pub fn get_rectangle(&self) -> Rect<i32> {
+        let position = self.get_position();
+        let size = self.get_size();
+        rect(position.x, position.y, size.x as i32, size.y as i32)
+    }
+
+    // Top-left of the bounding box for circles
+    pub fn get_position(&self) -> Vector2D<i32> {
+        match &self.geometry {
+            Shape::Rectangle(r) => r.top_left.into(),
+            Shape::Circle(c) => {
+                vec2(c.center.0, c.center.1) - vec2(c.radius as i32, c.radius as i32)
+            }
+        }
+    }
+
+    // Size of the bounding box for circles
+    pub fn get_size(&self) -> Vector2D<u32> {
+        match &self.geometry {
+            Shape::Rectangle(r) => r.size.into(),
+            Shape::Circle(c) => vec2(2 * c.radius, 2 * c.radius),
+        }
+    }
+
+    pub fn set_position(&mut self, new_position: Vector2D<i32>) {
+        match &mut self.geometry {
+            Shape::Rectangle(r) => {
+                r.top_left = new_position.to_tuple();
+            }
+            Shape::Circle(c) => {
+                let radius = c.radius as i32;
+                c.center = (new_position.x + radius, new_position.y + radius);
+            }
+        }
+    }
+
+    // Circles fit in the smallest dimension
+    pub fn set_size(&mut self, new_size: Vector2D<u32>) {
+        let position = self.get_position();
+        match &mut self.geometry {
+            Shape::Rectangle(r) => {
+                r.size = new_size.to_tuple();
+            }
+            Shape::Circle(c) => {
+                c.radius = std::cmp::min(new_size.x, new_size.y) / 2;
+                let radius = c.radius as i32;
+                c.center = (position.x + radius, position.y + radius);
+            }
+        }
+    }
+
+    // Hitboxes which only differ by a numbered suffix (eg. "hurt_1" and "hurt_2") form a group
+    // and share a color. This uses FNV-1a so colors are stable across builds and platforms.
+    pub fn get_color(&self) -> (u8, u8, u8) {
+        let group = self
+            .name
+            .trim_end_matches(|c: char| c.is_ascii_digit())
+            .trim_end_matches(|c: char| c == ' ' || c == '_' || c == '-');
+        let hash = group.bytes().fold(0x811c_9dc5u32, |hash, byte| {
+            (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193)
+        });
+        HITBOX_PALETTE[hash as usize % HITBOX_PALETTE.len()]
+    }
+}
+
+impl Ord for Hitbox {
+    fn cmp(&self, other: &Hitbox) -> Ordering {
+        self.name.cmp(&other.name)
+    }
+}
+
+impl PartialOrd for Hitbox {
+    fn partial_cmp(&self, other: &Hitbox) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl AnimationFrame {
+    pub fn new<T: AsRef<Path>>(frame: T) -> AnimationFrame {
+        AnimationFrame {
+            frame: frame.as_ref().to_owned(),
+            duration: 100, // TODO better default?
+            offset: (0, 0),
+            sound: None,
+            flip_x: false,
+            flip_y: false,
+            user_data: None,
+        }
+    }
+
+    pub fn get_frame(&self) -> &Path {
+        &self.frame
+    }
+
+    pub fn get_duration(&self) -> u32 {
+        self.duration
+    }
+
+    pub fn get_offset(&self) -> Vector2D<i32> {
+        self.offset.into()
+    }
+
+    pub fn get_sound(&self) -> Option<&str> {
+        self.sound.as_ref().map(|s| s.as_str())
+    }
+
+    pub fn get_user_data(&self) -> Option<&str> {
+        self.user_data.as_ref().map(|s| s.as_str())
+    }
+
+    pub fn get_flip_x(&self) -> bool {
+        self.flip_x
+    }
+
+    pub fn get_flip_y(&self) -> bool {
+        self.flip_y
+    }
+
+    pub fn set_flip_x(&mut self, flip_x: bool) {
+        self.flip_x = flip_x;
+    }
+
+    pub fn set_flip_y(&mut self, flip_y: bool) {
+        self.flip_y = flip_y;
+    }
+
+    // Mirrors a position relative to the center of the frame. The center of frames
+    // with odd dimensions lies in the middle of a pixel rather than between two.
+    pub fn flip_point(&self, point: Vector2D<i32>, frame_size: Vector2D<u32>) -> Vector2D<i32> {
+        let mut flipped = point;
+        if self.flip_x {
+            flipped.x = frame_size.x as i32 % 2 - point.x;
+        }
+        if self.flip_y {
+            flipped.y = frame_size.y as i32 % 2 - point.y;
+        }
+        flipped
+    }
+
+    pub fn flip_rectangle(&self, rectangle: Rect<i32>, frame_size: Vector2D<u32>) -> Rect<i32> {
+        let mut flipped = rectangle;
+        let flipped_origin = self.flip_point(rectangle.origin.to_vector(), frame_size);
+        if self.flip_x {
+            flipped.origin.x = flipped_origin.x - rectangle.size.width;
+        }
+        if self.flip_y {
+            flipped.origin.y = flipped_origin.y - rectangle.size.height;
+        }
+        flipped
+    }
+
+    pub fn set_duration(&mut self, new_duration: u32) {
+        self.duration = new_duration;
+    }
+
+    pub fn set_offset(&mut self, new_offset: Vector2D<i32>) {
+        self.offset = new_offset.to_tuple();
+    }
+
+    pub fn set_sound(&mut self, new_sound: Option<String>) -> Result<(), Error> {
+        if let Some(s) = &new_sound {
+            if s.len() > MAX_SOUND_NAME_LENGTH {
+                return Err(SheetError::SoundNameTooLong.into());
+            }
+        }
+        self.sound = new_sound;
+        Ok(())
+    }
+
+    pub fn set_user_data(&mut self, new_user_data: Option<String>) -> Result<(), Error> {
+        if let Some(s) = &new_user_data {
+            if s.len() > MAX_USER_DATA_LENGTH {
+                return Err(SheetError::UserDataTooLong.into());
+            }
+        }
+        self.user_data = new_user_data;
+        Ok(())
+    }
+}
+
+impl ExportFormat {
+    pub fn with_relative_paths<T: AsRef<Path>>(
+        &self,
+        relative_to: T,
+    ) -> Result<ExportFormat, Error> {
+        match self {
+            ExportFormat::Template(p) => Ok(ExportFormat::Template(relative_path_or_absolute(
+                &p,
+                relative_to,
+            ))),
+            ExportFormat::Json => Ok(ExportFormat::Json),
+        }
+    }
+
+    pub fn with_absolute_paths<T: AsRef<Path>>(
+        &self,
+        relative_to: T,
+    ) -> Result<ExportFormat, Error> {
+        match self {
+            ExportFormat::Template(p) => Ok(ExportFormat::Template(canonicalize(
+                relative_to.as_ref().join(&p),
+            )?)),
+            ExportFormat::Json => Ok(ExportFormat::Json),
+        }
+    }
+}
+
+impl ExportSettings {
+    pub fn new() -> ExportSettings {
+        ExportSettings {
+            format: ExportFormat::Template(PathBuf::new()),
+            texture_destination: PathBuf::new(),
+            metadata_destination: PathBuf::new(),
+            metadata_paths_root: PathBuf::new(),
+            skip_texture: false,
+            atlas_size: None,
+            max_size: None,
+            sort_frames_by_size: false,
+            padding: 0,
+            extrude: 0,
+            pack_strategy: PackStrategy::Skyline,
+            template_variables: Vec::new(),
+            absolute_image_paths: false,
+        }
+    }
+
+    // Metadata-only exports are allowed to leave the texture destination empty
+    pub fn has_texture_destination(&self) -> bool {
+        !self.skip_texture || !self.texture_destination.as_os_str().is_empty()
+    }
+
+    // Atlases split across several pages get one numbered texture per page
+    pub fn get_texture_page_destination(&self, page: u32, num_pages: u32) -> PathBuf {
+        if num_pages <= 1 {
+            return self.texture_destination.clone();
+        }
+        let stem = self
+            .texture_destination
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let file_name = match self.texture_destination.extension() {
+            Some(e) => format!("{}_{}.{}", stem, page, e.to_string_lossy()),
+            None => format!("{}_{}", stem, page),
+        };
+        self.texture_destination.with_file_name(file_name)
+    }
+
+    pub fn with_relative_paths<T: AsRef<Path>>(
+        &self,
+        relative_to: T,
+    ) -> Result<ExportSettings, Error> {
+        let texture_destination = if self.has_texture_destination() {
+            relative_path_or_absolute(&self.texture_destination, &relative_to)
+        } else {
+            PathBuf::new()
+        };
+        Ok(ExportSettings {
+            format: self.format.with_relative_paths(&relative_to)?,
+            texture_destination,
+            metadata_destination: relative_path_or_absolute(
+                &self.metadata_destination,
+                &relative_to,
+            ),
+            metadata_paths_root: relative_path_or_absolute(&self.metadata_paths_root, &relative_to),
+            skip_texture: self.skip_texture,
+            atlas_size: self.atlas_size,
+            max_size: self.max_size,
+            sort_frames_by_size: self.sort_frames_by_size,
+            padding: self.padding,
+            extrude: self.extrude,
+            pack_strategy: self.pack_strategy,
+            template_variables: self.template_variables.clone(),
+            absolute_image_paths: self.absolute_image_paths,
+        })
+    }
+
+    pub fn with_absolute_paths<T: AsRef<Path>>(
+        &self,
+        relative_to: T,
+    ) -> Result<ExportSettings, Error> {
+        let texture_destination = if self.has_texture_destination() {
+            canonicalize(relative_to.as_ref().join(&self.texture_destination))?
+        } else {
+            PathBuf::new()
+        };
+        Ok(ExportSettings {
+            format: self.format.with_absolute_paths(&relative_to)?,
+            texture_destination,
+            metadata_destination: canonicalize(
+                relative_to.as_ref().join(&self.metadata_destination),
+            )?,
+            metadata_paths_root: canonicalize(
+                relative_to.as_ref().join(&self.metadata_paths_root),
+            )?,
+            skip_texture: self.skip_texture,
+            atlas_size: self.atlas_size,
+            max_size: self.max_size,
+            sort_frames_by_size: self.sort_frames_by_size,
+            padding: self.padding,
+            extrude: self.extrude,
+            pack_strategy: self.pack_strategy,
+            template_variables: self.template_variables.clone(),
+            absolute_image_paths: self.absolute_image_paths,
+        })
+    }
+}
+
+fn get_slice_path<T: AsRef<Path>>(image: T, index: u32) -> PathBuf {
+    let mut path = image.as_ref().as_os_str().to_owned();
+    path.push(format!("#{}", index));
+    PathBuf::from(path)
+}
+
+// Splits `walk.png#3` into `walk.png` and `#3`
+fn split_slice_path(path: &Path) -> Option<(PathBuf, String)> {
+    let file_name = path.file_name()?.to_str()?;
+    let separator = file_name.rfind('#')?;
+    let (image_name, suffix) = file_name.split_at(separator);
+    if suffix.len() < 2 || !suffix[1..].chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some((path.with_file_name(image_name), suffix.to_owned()))
+}
+
+// Frame source for `frame` once its image is found at `image`
+fn relinked_frame_source(frame: &Frame, image: &Path) -> PathBuf {
+    match (&frame.source_rect, split_slice_path(&frame.source)) {
+        (Some(_), Some((_, suffix))) => {
+            let mut path = image.as_os_str().to_owned();
+            path.push(suffix);
+            PathBuf::from(path)
+        }
+        _ => image.to_owned(),
+    }
+}
+
+fn get_root(path: &Path) -> Option<Component<'_>> {
+    match path.components().next() {
+        Some(c @ Component::Prefix(_)) | Some(c @ Component::RootDir) => Some(c),
+        _ => None,
+    }
+}
+
+// Paths which have no relative form, like files on another drive than `relative_to`,
+// are kept absolute. Joining an absolute path onto `relative_to` leaves it unchanged
+// so these still resolve when reading the sheet back.
+pub fn relative_path_or_absolute<T: AsRef<Path>, U: AsRef<Path>>(
+    path: T,
+    relative_to: U,
+) -> PathBuf {
+    let path = path.as_ref();
+    let relative_to = relative_to.as_ref();
+    match diff_paths(path, relative_to) {
+        Some(p) if get_root(path) == get_root(relative_to) => p,
+        _ => path.to_owned(),
+    }
+}
+
+// Sliced frames do not exist on disk, only the image they come from does.
+// Missing images keep their path so the sheet can still be opened and relinked.
+fn absolute_frame_path(path: PathBuf) -> Result<PathBuf, Error> {
+    match split_slice_path(&path) {
+        Some((image, suffix)) if !path.exists() => {
+            let mut path = canonicalize(&image).unwrap_or(image).into_os_string();
+            path.push(suffix);
+            Ok(PathBuf::from(path))
+        }
+        _ => Ok(canonicalize(&path).unwrap_or(path)),
+    }
+}
+
+// Appends an increasing number to `base` until the name is free (`Walk`, `Walk 2`, `Walk 3`...)
+fn unique_name<F: Fn(&str) -> bool>(base: &str, is_taken: F) -> String {
+    let mut name = base.to_owned();
+    let mut index = 2;
+    while is_taken(&name) {
+        name = format!("{} {}", base, index);
+        index += 1;
+    }
+    name
+}
+
+#[test]
+fn test_validate_accepts_valid_sheet() {
+    let mut sheet = Sheet::default();
+    sheet.add_frame("walk_0.png");
+    sheet.get_frame_mut("walk_0.png").unwrap().add_hitbox();
+    sheet
+        .add_animation(true)
+        .insert_frame("walk_0.png", 0)
+        .unwrap();
+    assert_eq!(sheet.validate(), Ok(()));
+}
+
+#[test]
+fn test_validate_rejects_dangling_frame_reference() {
+    let mut sheet = Sheet::default();
+    sheet.add_frame("walk_0.png");
+    sheet
+        .add_animation(true)
+        .insert_frame("walk_1.png", 0)
+        .unwrap();
+    assert_eq!(
+        sheet.validate(),
+        Err(ValidationError::DanglingFrameReference(
+            "New Animation".to_owned()
+        ))
+    );
+}
+
+#[test]
+fn test_validate_rejects_duplicate_hitbox_names() {
+    let mut sheet = Sheet::default();
+    sheet.add_frame("walk_0.png");
+    {
+        let frame = sheet.get_frame_mut("walk_0.png").unwrap();
+        frame.add_hitbox();
+        frame.add_hitbox();
+        frame.rename_hitbox("New Hitbox 2", "New Hitbox").unwrap();
+    }
+    assert_eq!(
+        sheet.validate(),
+        Err(ValidationError::DuplicateHitboxName(
+            "walk_0.png".to_owned(),
+            "New Hitbox".to_owned()
+        ))
+    );
+}
+
+#[test]
+fn test_validate_rejects_zero_durations() {
+    let mut sheet = Sheet::default();
+    sheet.add_frame("walk_0.png");
+    {
+        let animation = sheet.add_animation(true);
+        animation.insert_frame("walk_0.png", 0).unwrap();
+        animation.get_frame_mut(0).unwrap().set_duration(0);
+    }
+    assert_eq!(
+        sheet.validate(),
+        Err(ValidationError::InvalidFrameDuration(
+            "New Animation".to_owned()
+        ))
+    );
+}
+
+#[test]
+fn test_insert_animation_renames_duplicates() {
+    let mut sheet = Sheet::default();
+    sheet.insert_animation(Animation::new("walk"));
+    sheet.insert_animation(Animation::new("walk"));
+    sheet.insert_animation(Animation::new("walk"));
+    assert!(sheet.has_animation("walk"));
+    assert!(sheet.has_animation("walk 2"));
+    assert!(sheet.has_animation("walk 3"));
+}
+
+#[test]
+fn test_add_sliced_frames() {
+    let mut sheet = Sheet::default();
+    sheet
+        .add_sliced_frames("walk.png", vec2(70, 40), vec2(32, 16))
+        .unwrap();
+    assert_eq!(sheet.frames_iter().count(), 4);
+    let frame = sheet.get_frame("walk.png#3").unwrap();
+    assert_eq!(frame.get_image(), PathBuf::from("walk.png"));
+    assert_eq!(frame.get_source_rect(), Some(rect(32, 16, 32, 16)));
+}
+
+#[test]
+fn test_animation_tags() {
+    let mut sheet = Sheet::default();
+    sheet.insert_animation(Animation::new("walk"));
+    sheet.add_animation_tag("walk", "hero".to_owned()).unwrap();
+    sheet.add_animation_tag("walk", "hero".to_owned()).unwrap();
+    sheet.add_animation_tag("walk", "idle".to_owned()).unwrap();
+    sheet.remove_animation_tag("walk", "idle").unwrap();
+    let animation = sheet.get_animation("walk").unwrap();
+    assert_eq!(animation.tags_iter().collect::<Vec<_>>(), vec!["hero"]);
+}
+
+#[test]
+fn test_flip_animation_frame() {
+    let mut animation_frame = AnimationFrame::new("walk.png");
+    animation_frame.set_flip_x(true);
+    assert_eq!(
+        animation_frame.flip_point(vec2(5, 3), vec2(32, 32)),
+        vec2(-5, 3)
+    );
+    assert_eq!(
+        animation_frame.flip_point(vec2(5, 3), vec2(33, 33)),
+        vec2(-4, 3)
+    );
+    assert_eq!(
+        animation_frame.flip_rectangle(rect(2, 2, 4, 4), vec2(32, 32)),
+        rect(-6, 2, 4, 4)
+    );
+}
+
+#[test]
+fn test_legacy_loop_mode() {
+    let read = |json: &str| {
+        serde_json::from_str::<Animation>(json)
+            .unwrap()
+            .get_loop_mode()
+    };
+    let old_looping = r#"{"name": "walk", "timeline": [], "is_looping": true}"#;
+    let old_not_looping = r#"{"name": "walk", "timeline": [], "is_looping": false}"#;
+    let new_ping_pong = r#"{"name": "walk", "timeline": [], "loop_mode": "PingPong"}"#;
+    assert_eq!(read(old_looping), LoopMode::Loop);
+    assert_eq!(read(old_not_looping), LoopMode::Once);
+    assert_eq!(read(new_ping_pong), LoopMode::PingPong);
+}
+
+#[test]
+fn test_ping_pong_frame_at() {
+    let mut animation = Animation::new("walk");
+    animation.set_loop_mode(LoopMode::PingPong);
+    for frame in &["a.png", "b.png", "c.png"] {
+        let position = animation.get_num_frames();
+        animation.insert_frame(frame, position).unwrap();
+    }
+    let frame_at = |t| animation.get_frame_at(Duration::from_millis(t)).unwrap().0;
+    assert_eq!(frame_at(50), 0);
+    assert_eq!(frame_at(250), 2);
+    assert_eq!(frame_at(350), 2);
+    assert_eq!(frame_at(450), 1);
+    assert_eq!(frame_at(550), 0);
+    assert_eq!(frame_at(650), 0);
+}
+
+#[test]
+fn test_relative_path_or_absolute() {
+    assert_eq!(
+        relative_path_or_absolute("/project/frames/walk_0.png", "/project/sheets"),
+        PathBuf::from("../frames/walk_0.png")
+    );
+    assert_eq!(
+        relative_path_or_absolute("/frames/walk_0.png", "project/sheets"),
+        PathBuf::from("/frames/walk_0.png")
+    );
+    assert_eq!(
+        relative_path_or_absolute("frames/walk_0.png", "/project/sheets"),
+        PathBuf::from("frames/walk_0.png")
+    );
+}
+
+#[cfg(windows)]
+#[test]
+fn test_relative_path_or_absolute_across_drives() {
+    assert_eq!(
+        relative_path_or_absolute(r"D:\frames\walk_0.png", r"C:\project"),
+        PathBuf::from(r"D:\frames\walk_0.png")
+    );
+    assert_eq!(
+        relative_path_or_absolute(r"C:\frames\walk_0.png", r"C:\project"),
+        PathBuf::from(r"..\frames\walk_0.png")
+    );
+}
+
+#[test]
+fn test_relink_frames() {
+    let mut sheet = Sheet::default();
+    sheet.add_frame("/missing/walk_0.png");
+    sheet.add_frame("/missing/idle.png");
+    sheet
+        .add_sliced_frames("/missing/run.png", vec2(64, 32), vec2(32, 32))
+        .unwrap();
+    {
+        let animation = sheet.add_animation(true);
+        animation.insert_frame("/missing/walk_0.png", 0).unwrap();
+        animation.insert_frame("/missing/run.png#1", 1).unwrap();
+    }
+    let candidates = vec![
+        PathBuf::from("/moved/walk_0.png"),
+        PathBuf::from("/moved/run.png"),
+    ];
+    let unmatched = sheet.relink_frames(&candidates);
+    assert_eq!(unmatched, vec![PathBuf::from("/missing/idle.png")]);
+    assert!(sheet.has_frame("/moved/walk_0.png"));
+    assert!(sheet.has_frame("/moved/run.png#0"));
+    assert!(sheet.has_frame("/moved/run.png#1"));
+    assert!(sheet.has_frame("/missing/idle.png"));
+    let animation = sheet.get_animation("New Animation").unwrap();
+    assert_eq!(
+        animation.get_frame(1).unwrap().get_frame(),
+        Path::new("/moved/run.png#1")
+    );
+}
+
+#[test]
+fn test_hitbox_color_is_shared_by_group() {
+    let mut frame = Frame::new("walk_0.png");
+    for name in &["hurt", "hurt_1", "hurt 2", "hit"] {
+        let hitbox = frame.add_hitbox();
+        hitbox.name = (*name).to_owned();
+    }
+    let color = |name| frame.get_hitbox(name).unwrap().get_color();
+    assert_eq!(color("hurt"), color("hurt_1"));
+    assert_eq!(color("hurt"), color("hurt 2"));
+    assert_ne!(color("hurt"), color("hit"));
+}
+
+#[test]
+fn test_fractional_frame_rate_overrides_keyframe_durations() {
+    let mut animation = Animation::new("walk");
+    animation.insert_frame("walk_0.png", 0).unwrap();
+    animation.insert_frame("walk_1.png", 1).unwrap();
+    animation.get_frame_mut(0).unwrap().set_duration(250);
+    assert_eq!(animation.get_duration(), Some(350));
+    animation.set_frame_rate(Some(12.5));
+    assert_eq!(animation.get_duration(), Some(160));
+    animation.set_frame_rate(Some(29.97));
+    let animation_frame = animation.get_frame(0).unwrap();
+    assert_eq!(animation.get_frame_duration(animation_frame), 33);
+}
+
+#[test]
+fn test_animation_group_path() {
+    let mut animation = Animation::new("walk");
+    assert!(animation.get_group_path().is_empty());
+    animation.group = Some("player/".to_owned());
+    assert_eq!(animation.get_group_path(), vec!["player"]);
+    animation.group = Some("enemy/ boss//flying".to_owned());
+    assert_eq!(animation.get_group_path(), vec!["enemy", "boss", "flying"]);
+    animation.group = Some("/".to_owned());
+    assert!(animation.get_group_path().is_empty());
+}